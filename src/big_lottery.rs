@@ -1,75 +1,86 @@
 use std::collections::HashMap;
 use crate::*;
 
-/// for a big lottery only
-#[derive(
-    BorshSerialize, 
-    BorshDeserialize, 
-    Serialize,
-    PartialEq,
-    PartialOrd, 
-    Eq,
-    Clone,
-    Debug,
-    Hash
-)]
-#[serde(crate = "near_sdk::serde")]
-pub enum WinnerType {
-    UpToFiftyPercent,
-    UpToTenPercent,
-    Cashback
-}
-
 #[derive(BorshSerialize, BorshDeserialize, Serialize)]
 #[serde(crate = "near_sdk::serde")]
 pub struct BigLottery {
     pub id: LotteryId,
     pub lottery_token_id: AccountId,
     pub lottery_status: LotteryStatus,
-    /// A list of account_ids in this lottery
-    pub entries: Vec<AccountId>,
+    /// Entries with tickets num in entry order
+    pub entries: LotteryEntries,
     /// Amount to participate a lottery
     pub entry_fee: Balance,
     /// Current amount deposited
     pub current_pool: Balance,
     /// Required total amount for lottery to start
     pub required_pool: Balance,
-    pub winners: HashMap<WinnerType, Vec<AccountId>>,
-    pub cashbacked_num: u32,
-    pub ten_percent_winners_num: u32,
-    pub fifty_percent_winners_num: u32,
-    pub refferal_transfered: Balance
+    /// Tickets one account can hold
+    pub max_tickets: u32,
+    pub prize_table_id: String,
+    /// Prize table in effect when lottery was created
+    pub prize_table: PrizeTable,
+    /// Winners num of every prize tier, set to actual winners nums when lottery is drawn.
+    /// Tier taking the rest counts tickets
+    pub tier_winners_nums: Vec<u32>,
+    /// Winners of every prize tier
+    pub winners: Vec<Vec<AccountId>>,
+    pub refferal_transfered: Balance,
+    /// Referral rewards paid on entry: entrant -> reward
+    pub referrals: HashMap<AccountId, Balance>,
+    /// Lottery is refunded if it was not filled before expiration
+    pub expires_at: Option<LotteryExpiration>,
+    /// Fee parameters in effect when lottery was created
+    pub fees: LotteryFees,
+    pub draw_mode: DrawMode,
+    /// Block of the final entry for `Delayed` draw mode
+    pub filled_block_height: Option<BlockHeight>,
+    /// Entrants secrets for `CommitReveal` draw mode
+    pub entropy: EntrantsEntropy,
+    /// Inputs and result of the draw
+    pub draw_record: Option<DrawRecord>
 }
 
 impl BigLottery {
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         id: LotteryId,
         lottery_token_id: AccountId,
         num_participants: u32,
-        entry_fee: Balance 
+        entry_fee: Balance,
+        expires_at: Option<LotteryExpiration>,
+        fees: LotteryFees,
+        draw_mode: DrawMode,
+        prize_table_id: String,
+        prize_table: PrizeTable,
+        max_tickets: u32
     ) -> Self {
-        //for cashback count
-        assert!(num_participants % 2 == 0, "Number of participants must be divisible by two");
-        assert!(num_participants % 5 == 0, "Number of participants must be divisible by five");
-        assert!(num_participants <= 64, "Unexpected random result for more than 64 participants");
+        assert!(num_participants >= 2, "Big lottery requires at least two participants");
         let required_pool:Balance = match entry_fee.checked_mul(num_participants as u128) {
             Some(amount) => amount,
             None => panic!("Incorrect lottery setup, math overflow through  `entry_fee * num_participants`"),
         };
-        assert!(required_pool % 2 == 0, "required_pool must be divisible by two");
         let lottery = Self {
             id,
             lottery_token_id,
             lottery_status: LotteryStatus::Active,
-            entries: vec![],
+            entries: LotteryEntries::new(id),
             entry_fee,
             current_pool: 0,
             required_pool,
-            winners: HashMap::new(),
-            cashbacked_num: num_participants / 2,
-            ten_percent_winners_num: num_participants / 2 - num_participants / 5,
-            fifty_percent_winners_num: num_participants / 5,
-            refferal_transfered: 0
+            max_tickets,
+            tier_winners_nums: prize_table.winners_nums(num_participants),
+            prize_table_id,
+            prize_table,
+            winners: vec![],
+            refferal_transfered: 0,
+            referrals: HashMap::new(),
+            expires_at,
+            fees,
+            draw_mode,
+            filled_block_height: None,
+            entropy: EntrantsEntropy::default(),
+            draw_record: None
         };
         lottery.assert_valid();
         lottery
@@ -78,6 +89,12 @@ impl BigLottery {
     fn assert_valid(&self) {
         assert!(self.entry_fee > 0, "entry_fee cannot be zero");
         assert!(self.required_pool > 0, "num_participants cannot be zero");
+        let num_participants = (self.required_pool / self.entry_fee) as u32;
+        let referral_rewards = ratio(self.entry_fee, self.fees.referral_ratio) * num_participants as u128;
+        assert!(
+            self.prize_table.payout(self.entry_fee, &self.tier_winners_nums) + referral_rewards < self.required_pool,
+            "Prize table payouts exceed pool minus fees for {} participants", num_participants
+        );
     }
 
     fn assert_equals_pool(&self) {
@@ -96,39 +113,117 @@ impl BigLottery {
         self.lottery_status == LotteryStatus::Finished && self.is_pools_equal() && !self.winners.is_empty()
     }
 
-    // fn contains_entry(&self, account_id: &AccountId) -> bool {
-    //     self.entries
-    //         .iter()
-    //         .any(|entry| &entry.account_id == account_id)
-    // }
-
     pub fn assert_is_finished(&self) {
         self.assert_equals_pool();
         assert!(!self.winners.is_empty());
     }
 
-    pub fn add_refferal_transfered(&mut self, amount: Balance) {
-        self.refferal_transfered += amount
+    pub fn add_refferal_transfered(&mut self, account_id: &AccountId, amount: Balance) {
+        self.refferal_transfered += amount;
+        *self.referrals.entry(account_id.clone()).or_default() += amount;
+    }
+
+    /// Removes entry of active lottery with all its tickets.
+    /// Returns refund: entry fees of tickets minus referral reward already paid for them
+    pub fn leave(&mut self, account_id: &AccountId) -> Balance {
+        assert_eq!(self.lottery_status, LotteryStatus::Active, "Lottery is not active");
+        let tickets = self.entries.remove(account_id).expect("Not entered");
+        let entry_fees = self.entry_fee * tickets as u128;
+        self.current_pool -= entry_fees;
+
+        self.entropy.remove(account_id);
+        let referral_reward = self.referrals.remove(account_id).unwrap_or_default();
+        self.refferal_transfered -= referral_reward;
+        entry_fees - referral_reward
+    }
+
+    pub fn cancel(&mut self) {
+        assert_eq!(self.lottery_status, LotteryStatus::Active, "Lottery is not active");
+        self.lottery_status = LotteryStatus::Cancelled;
+    }
+
+    /// Lottery was not filled before expiration
+    pub fn is_expired(&self) -> bool {
+        self.lottery_status == LotteryStatus::Active
+            && !self.is_pools_equal() 
+            && self.expires_at.map(|expires_at| expires_at.is_reached()).unwrap_or(false)
     }
 
     pub fn update(&mut self) -> LotteryStatus {
-        if self.is_pools_equal() {
-            self.lottery_status = LotteryStatus::Finished;
-            self.set_winner();
+        if self.lottery_status == LotteryStatus::Active && self.is_pools_equal() {
+            if self.draw_mode.is_delayed() {
+                self.lottery_status = LotteryStatus::Drawing;
+                self.filled_block_height = Some(env::block_height());
+            } else {
+                self.lottery_status = LotteryStatus::Finished;
+                self.set_winner(env::random_seed_array());
+            }
         }
         self.lottery_status
     }
 
-    /// Draw lottery entry
-    pub fn draw_enter(&mut self, account_id: &AccountId, amount: Balance) -> LotteryStatus {
+    /// Picks winners of filled lottery in a later block than the final entry
+    pub fn draw(&mut self) -> LotteryStatus {
+        assert_eq!(self.lottery_status, LotteryStatus::Drawing, "Lottery is not waiting for draw");
+        assert!(
+            self.filled_block_height.is_some_and(|filled_at| env::block_height() > filled_at),
+            "Draw is available in a later block than the final entry"
+        );
+        let random_seed = if let DrawMode::CommitReveal { .. } = self.draw_mode {
+            assert!(
+                self.entropy.is_all_revealed() || !self.is_reveal_window_open(),
+                "Reveal window is not over"
+            );
+            self.entropy.seed()
+        } else {
+            env::random_seed_array()
+        };
+        self.lottery_status = LotteryStatus::Finished;
+        self.set_winner(random_seed);
+        self.lottery_status
+    }
+
+    fn is_reveal_window_open(&self) -> bool {
+        match (self.draw_mode, self.filled_block_height) {
+            (DrawMode::CommitReveal { reveal_window }, Some(filled_at)) => {
+                env::block_height() <= filled_at + reveal_window.0
+            },
+            _ => false,
+        }
+    }
+
+    /// Reveals entrant secret of lottery waiting for draw
+    pub fn reveal_secret(&mut self, account_id: &AccountId, secret: &str) {
+        assert_eq!(self.lottery_status, LotteryStatus::Drawing, "Lottery is not waiting for draw");
+        assert!(self.is_reveal_window_open(), "Reveal window is over");
+        self.entropy.reveal(account_id, secret);
+    }
+
+    /// Draw lottery entry of `tickets` tickets
+    pub fn draw_enter(
+        &mut self,
+        account_id: &AccountId,
+        amount: Balance,
+        tickets: u32,
+        secret_hash: Option<CryptoHash>
+    ) -> LotteryStatus {
         if !self.is_finished() {
             assert_eq!(
-                amount, self.entry_fee,
+                amount, self.entry_fee * tickets as u128,
                 "Supplied: {}, but Required amount to paticipate is: {}",
-                self.entry_fee, amount
+                self.entry_fee * tickets as u128, amount
+            );
+            assert_tickets_allowed(
+                self.entries.tickets_of(account_id),
+                tickets,
+                self.max_tickets,
+                ((self.required_pool - self.current_pool) / self.entry_fee) as _
             );
-            assert!(!self.entries.contains(account_id), "Already entered");
-            self.entries.push(account_id.clone());
+            if let DrawMode::CommitReveal { .. } = self.draw_mode {
+                let secret_hash = secret_hash.expect("Secret hash is required in commit-reveal draw mode");
+                self.entropy.commit(account_id, secret_hash);
+            }
+            self.entries.add_tickets(account_id, tickets);
             self.current_pool += amount;
         }
 
@@ -136,54 +231,54 @@ impl BigLottery {
         self.update()
     }
 
-    fn set_winner(&mut self) {
-        // 50 accounts
-        let total_entries = self.entries.len();
-        let shuffled_entries = shuffle(self.entries.clone());
-        check_account_duplicates(&shuffled_entries);
-
-        let up_to_fifty_num = self.fifty_percent_winners_num as usize;
-        let up_to_ten_num = self.ten_percent_winners_num as usize;
-
-        let up_to_fifty_vec = &shuffled_entries[..up_to_fifty_num];
-        let up_to_ten_vec = &shuffled_entries[up_to_fifty_num..up_to_ten_num + up_to_fifty_num];
-        let cashback_vec = &shuffled_entries[up_to_ten_num + up_to_fifty_num..];
-
-        assert_eq!(cashback_vec.len(), total_entries / 2, "Incorrect cashback accounts num");
-        assert_eq!(up_to_fifty_vec.len(), up_to_fifty_num, "Incorrect winners +50% accounts num");
-        assert_eq!(up_to_ten_vec.len(), up_to_ten_num, "Incorrect winners +10% accounts num");
-
-        assert!(
-            up_to_fifty_num + up_to_ten_num == total_entries / 2,
-            "Mismatched len of rewarded and cashback accounts: Rewarded {} Cashback {}",
-            up_to_fifty_num + up_to_ten_num, total_entries / 2,
+    /// Picks winners of sampled tiers weighted by tickets, an account wins one tier at most with one ticket.
+    /// Tier taking the rest gets all other tickets
+    fn set_winner(&mut self, random_seed: [u8; 32]) {
+        let mut tier_winners_nums = self.tier_winners_nums.clone();
+        // the last tier of table covering all participants takes the rest, it is not sampled
+        if self.prize_table.covers_all() {
+            tier_winners_nums.pop();
+        }
+        // only entries holding sampled tickets are read, entrants not revealed secrets are sampled last
+        let mut accounts = HashMap::new();
+        let (winner_indices, excluded_indices) = pick_ticket_winners(
+            random_seed,
+            self.id,
+            RandomPurpose::BigLotteryOrder,
+            self.entries.len(),
+            self.entries.tickets_num(),
+            tier_winners_nums.iter().sum(),
+            |ticket| {
+                let index = self.entries.entry_of_ticket(ticket);
+                let entry = accounts.entry(index).or_insert_with(|| self.entries.get(index));
+                (index, self.entropy.is_excluded(&entry.account_id))
+            }
         );
 
-        self.winners.insert(WinnerType::UpToFiftyPercent, up_to_fifty_vec.to_vec());
-        self.winners.insert(WinnerType::UpToTenPercent, up_to_ten_vec.to_vec());
-        self.winners.insert(WinnerType::Cashback, cashback_vec.to_vec());
-    }
-
-    pub fn get_winners(&self, winner_type: WinnerType) -> &[AccountId] {
-        match winner_type {
-            WinnerType::UpToFiftyPercent => {
-                self.winners.get(&WinnerType::UpToFiftyPercent).expect("No required winners found")
-            },
-            WinnerType::UpToTenPercent => {
-                self.winners.get(&WinnerType::UpToTenPercent).expect("No required winners found")
-            },
-            WinnerType::Cashback => {
-                self.winners.get(&WinnerType::Cashback).expect("No required winners found")
-            },
+        let mut sampled = winner_indices.iter().map(|index| accounts[index].account_id.clone());
+        let mut winners = tier_winners_nums
+            .iter()
+            .map(|num| sampled.by_ref().take(*num as usize).collect::<Vec<_>>())
+            .collect::<Vec<_>>();
+        let mut tier_winners_nums = winners.iter().map(|winners| winners.len() as u32).collect::<Vec<_>>();
+        // the rest of tickets is paid in batches after lottery is distributed, see `StoredSettlement`
+        if self.prize_table.covers_all() {
+            let won_tickets = winner_indices.len() as u32;
+            tier_winners_nums.push(self.entries.tickets_num() - won_tickets);
+            winners.push(vec![]);
         }
+
+        self.tier_winners_nums = tier_winners_nums;
+        self.winners = winners;
+        self.draw_record = Some(DrawRecord::new(
+            self.id,
+            LotteryType::BigLottery,
+            random_seed,
+            self.entries.hash(),
+            self.entries.len(),
+            self.entries.tickets_num(),
+            excluded_indices,
+            winner_indices
+        ));
     }
 }
-
-fn check_account_duplicates(checked_vec: &Vec<AccountId>) {
-    let mut dedup = checked_vec.clone();
-    dedup.dedup();
-    assert_eq!(
-        &dedup, checked_vec,
-        "Duplicated accounts found"
-    )
-}
\ No newline at end of file
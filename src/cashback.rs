@@ -0,0 +1,332 @@
+use std::collections::HashMap;
+
+use near_sdk::collections::LookupMap;
+use near_sdk::json_types::U64;
+
+use crate::*;
+use crate::payouts::has_gas_for_payout;
+
+/// Max cashbacks transfered along with lottery entry
+pub const CASHBACK_BATCH_ON_ENTER: u32 = 5;
+
+#[derive(BorshSerialize, BorshDeserialize)]
+pub struct CashbackEntry {
+    pub account_id: AccountId,
+    pub settled: bool
+}
+
+/// Cashbacks of finished lottery waiting for transfer
+#[derive(BorshSerialize, BorshDeserialize)]
+pub struct StoredCashback {
+    pub lottery_token_id: AccountId,
+    pub amount: Balance,
+    pub accounts: Vec<CashbackEntry>
+}
+
+impl StoredCashback {
+    pub fn new(lottery_token_id: AccountId, amount: Balance, accounts: &[AccountId]) -> Self {
+        Self {
+            lottery_token_id,
+            amount,
+            accounts: accounts
+                .iter()
+                .map(|account_id| CashbackEntry { account_id: account_id.clone(), settled: false })
+                .collect()
+        }
+    }
+
+    pub fn pending_accounts(&self) -> Vec<AccountId> {
+        self.accounts
+            .iter()
+            .filter(|entry| !entry.settled)
+            .map(|entry| entry.account_id.clone())
+            .collect()
+    }
+
+    pub fn is_settled(&self) -> bool {
+        self.accounts.iter().all(|entry| entry.settled)
+    }
+}
+
+/// Payout of every entry of settled lottery
+#[derive(BorshSerialize, BorshDeserialize)]
+pub enum EntriesPayout {
+    /// entries are only released
+    Release,
+    /// big lottery tier taking the rest of tickets, paid for every ticket which did not win sampled tiers
+    RestTier {
+        amount: Balance,
+        claim_mode: bool,
+        /// sorted indices of entries winning sampled tiers
+        winner_indices: Vec<u32>
+    },
+    /// entry fees of closed lottery, referral rewards not covered by contract fees are deducted
+    Refund {
+        entry_fee: Balance,
+        referrals: HashMap<AccountId, Balance>
+    },
+    /// lotto paytable tiers, all entries are counted among tier winners before tier pools are paid
+    LottoTiers {
+        series: LottoSeries,
+        drawn_numbers: Vec<u32>,
+        tiers: Vec<LottoTierPayout>,
+        /// entries counted from the first one
+        counted_num: u32,
+        claim_mode: bool
+    }
+}
+
+impl EntriesPayout {
+    /// Payout of entry at `index` with its lotto picked numbers and whether it is credited to claimable balance
+    pub fn entry_payout(&self, index: u32, entry: &Entry, numbers: Option<&[u32]>) -> (Balance, bool) {
+        match self {
+            EntriesPayout::Release => (0, false),
+            EntriesPayout::RestTier { amount, claim_mode, winner_indices } => {
+                // one ticket of sampled tier winner is paid by its tier
+                let won_tickets = winner_indices.binary_search(&index).is_ok() as u32;
+                (amount * (entry.tickets - won_tickets) as u128, *claim_mode)
+            },
+            EntriesPayout::Refund { entry_fee, referrals } => {
+                let referral_reward = referrals.get(&entry.account_id).copied().unwrap_or_default();
+                ((entry_fee * entry.tickets as u128).saturating_sub(referral_reward), false)
+            },
+            EntriesPayout::LottoTiers { drawn_numbers, tiers, claim_mode, .. } => {
+                let matches = lotto_matches(numbers.expect("Numbers were not found"), drawn_numbers);
+                let amount = tiers
+                    .iter()
+                    .find(|tier| tier.matches == matches)
+                    .map_or(0, |tier| tier.pool / tier.winners_num as u128);
+                (amount, *claim_mode)
+            },
+        }
+    }
+}
+
+/// Entries of finished or closed lottery waiting for payouts.
+/// Entries are paid from the last one in gas-bounded batches and released,
+/// so lottery is settled without reading all its entries
+#[derive(BorshSerialize, BorshDeserialize)]
+pub struct StoredSettlement {
+    pub lottery_token_id: AccountId,
+    pub entries: LotteryEntries,
+    /// lotto picked numbers, released along with entries
+    pub picks: Option<LookupMap<AccountId, Vec<u32>>>,
+    pub payout: EntriesPayout,
+    /// amount not paid yet
+    pub pending_amount: Balance
+}
+
+impl StoredSettlement {
+    pub fn new(
+        lottery_token_id: AccountId,
+        entries: LotteryEntries,
+        picks: Option<LookupMap<AccountId, Vec<u32>>>,
+        mut payout: EntriesPayout,
+        pending_amount: Balance
+    ) -> Self {
+        if let EntriesPayout::RestTier { winner_indices, .. } = &mut payout {
+            winner_indices.sort_unstable();
+        }
+        Self {
+            lottery_token_id,
+            entries,
+            picks,
+            payout,
+            pending_amount
+        }
+    }
+
+    /// Counts the next lotto entry among winners of its tier.
+    /// Returns false if there is nothing to count
+    pub fn count_next_entry(&mut self) -> bool {
+        if let EntriesPayout::LottoTiers { drawn_numbers, tiers, counted_num, .. } = &mut self.payout {
+            if *counted_num < self.entries.len() {
+                let entry = self.entries.get(*counted_num);
+                let numbers = self.picks
+                    .as_ref()
+                    .and_then(|picks| picks.get(&entry.account_id))
+                    .expect("Numbers were not found");
+                let matches = lotto_matches(&numbers, drawn_numbers);
+                if let Some(tier) = tiers.iter_mut().find(|tier| tier.matches == matches) {
+                    tier.winners_num += 1;
+                }
+                *counted_num += 1;
+                return true;
+            }
+        }
+        false
+    }
+
+    /// Entries can be paid, lotto entries are all counted
+    pub fn is_counted(&self) -> bool {
+        match &self.payout {
+            EntriesPayout::LottoTiers { counted_num, .. } => *counted_num >= self.entries.len(),
+            _ => true,
+        }
+    }
+
+    /// Entries are only released
+    pub fn release(lottery_token_id: AccountId, entries: LotteryEntries, picks: Option<LookupMap<AccountId, Vec<u32>>>) -> Self {
+        Self::new(lottery_token_id, entries, picks, EntriesPayout::Release, 0)
+    }
+}
+
+#[derive(Serialize, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct CashbackView {
+    pub lottery_id: U64,
+    pub lottery_token_id: AccountId,
+    pub amount: U128,
+    pub pending_accounts: Vec<AccountId>
+}
+
+#[derive(Serialize, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct SettlementView {
+    pub lottery_id: U64,
+    pub lottery_token_id: AccountId,
+    /// amount not paid yet
+    pub pending_amount: U128,
+    /// entries not released yet
+    pub entries_num: u32
+}
+
+impl Contract {
+    /// Transfers up to `limit` pending cashbacks while there is enough gas for payouts,
+    /// then settles entries of finished and closed lotteries.
+    /// Returns number of transfered cashbacks and settled entries
+    pub (crate) fn internal_process_cashbacks(&mut self, limit: u32) -> u32 {
+        let processed = self.internal_process_listed_cashbacks(limit);
+        processed + self.internal_process_settlements(limit - processed)
+    }
+
+    fn internal_process_listed_cashbacks(&mut self, limit: u32) -> u32 {
+        let mut processed = 0;
+        for lottery_id in self.cashbacks.keys().collect::<Vec<_>>() {
+            let mut stored_cashback = self.cashbacks.get(&lottery_id).unwrap();
+            for entry in stored_cashback.accounts.iter_mut().filter(|entry| !entry.settled) {
+                if processed == limit || !has_gas_for_payout(&stored_cashback.lottery_token_id) {
+                    break;
+                }
+                self.internal_payout(&entry.account_id, &stored_cashback.lottery_token_id, stored_cashback.amount);
+                entry.settled = true;
+                processed += 1;
+            }
+
+            if stored_cashback.is_settled() {
+                self.cashbacks.remove(&lottery_id);
+            } else {
+                self.cashbacks.insert(&lottery_id, &stored_cashback);
+                break;
+            }
+        }
+        processed
+    }
+
+    fn internal_process_settlements(&mut self, limit: u32) -> u32 {
+        let mut processed = 0;
+        for lottery_id in self.settlements.keys().collect::<Vec<_>>() {
+            let mut settlement = self.settlements.get(&lottery_id).unwrap();
+            while processed < limit && has_gas_for_payout(&settlement.lottery_token_id) {
+                if settlement.count_next_entry() {
+                    if settlement.is_counted() {
+                        self.internal_finish_lotto_count(lottery_id, &mut settlement);
+                    }
+                    processed += 1;
+                    continue;
+                }
+                let entry = match settlement.entries.pop() {
+                    Some(entry) => entry,
+                    None => break,
+                };
+                let numbers = settlement.picks.as_mut().and_then(|picks| picks.remove(&entry.account_id));
+                let (amount, claim_mode) = settlement.payout.entry_payout(settlement.entries.len(), &entry, numbers.as_deref());
+                if amount > 0 {
+                    self.internal_reward(&entry.account_id, &settlement.lottery_token_id, amount, claim_mode);
+                    settlement.pending_amount = settlement.pending_amount.saturating_sub(amount);
+                }
+                processed += 1;
+            }
+
+            if settlement.entries.is_empty() {
+                self.settlements.remove(&lottery_id);
+            } else {
+                self.settlements.insert(&lottery_id, &settlement);
+                break;
+            }
+        }
+        processed
+    }
+
+    /// Total amount of not transfered cashbacks per token
+    pub (crate) fn internal_cashback_liabilities(&self) -> HashMap<AccountId, Balance> {
+        let mut liabilities: HashMap<AccountId, Balance> = HashMap::new();
+        for stored_cashback in self.cashbacks.values() {
+            let pending_num = stored_cashback.pending_accounts().len() as u128;
+            *liabilities.entry(stored_cashback.lottery_token_id.clone()).or_default() += stored_cashback.amount * pending_num;
+        }
+        for settlement in self.settlements.values().filter(|settlement| settlement.pending_amount > 0) {
+            *liabilities.entry(settlement.lottery_token_id.clone()).or_default() += settlement.pending_amount;
+        }
+        liabilities
+    }
+}
+
+#[near_bindgen]
+impl Contract {
+    /// Transfers pending cashbacks in queue order, then settles entries of finished and closed lotteries entry by entry:
+    /// pays big lotteries rest tiers and lotto tiers, refunds entries of closed lotteries and releases entries storage.
+    /// Lotto entries are all counted among tier winners before they are paid
+    /// - Can be called by anyone
+    /// - Stops when `limit` reached or there is not enough gas left for next transfer
+    /// - Returns number of transfered cashbacks and settled entries
+    pub fn process_cashbacks(&mut self, limit: Option<u32>) -> u32 {
+        self.internal_process_cashbacks(limit.unwrap_or(u32::MAX))
+    }
+
+    /// Returns not transfered cashbacks from a given index up to a given limit.
+    pub fn get_pending_cashbacks(&self, from_index: Option<u64>, limit: Option<u64>) -> Vec<CashbackView> {
+        let keys = self.cashbacks.keys_as_vector();
+        let from_index = from_index.unwrap_or(0);
+        let limit = limit.unwrap_or(keys.len());
+        (from_index..std::cmp::min(keys.len(), from_index + limit))
+            .map(|index| {
+                let lottery_id = keys.get(index).unwrap();
+                let stored_cashback = self.cashbacks.get(&lottery_id).unwrap();
+                CashbackView {
+                    lottery_id: U64(lottery_id),
+                    pending_accounts: stored_cashback.pending_accounts(),
+                    lottery_token_id: stored_cashback.lottery_token_id,
+                    amount: U128(stored_cashback.amount),
+                }
+            })
+            .collect()
+    }
+
+    /// Returns lotteries which entries are not settled from a given index up to a given limit.
+    pub fn get_pending_settlements(&self, from_index: Option<u64>, limit: Option<u64>) -> Vec<SettlementView> {
+        let keys = self.settlements.keys_as_vector();
+        let from_index = from_index.unwrap_or(0);
+        let limit = limit.unwrap_or(keys.len());
+        (from_index..std::cmp::min(keys.len(), from_index + limit))
+            .map(|index| {
+                let lottery_id = keys.get(index).unwrap();
+                let settlement = self.settlements.get(&lottery_id).unwrap();
+                SettlementView {
+                    lottery_id: U64(lottery_id),
+                    pending_amount: U128(settlement.pending_amount),
+                    entries_num: settlement.entries.len(),
+                    lottery_token_id: settlement.lottery_token_id,
+                }
+            })
+            .collect()
+    }
+
+    /// Returns total amount of not transfered cashbacks including pending settlements as a list of ( token_id, amount )
+    pub fn get_cashback_liabilities(&self) -> Vec<(AccountId, U128)> {
+        self.internal_cashback_liabilities()
+            .into_iter()
+            .map(|(token_id, amount)| (token_id, U128(amount)))
+            .collect()
+    }
+}
@@ -1,6 +1,8 @@
+use near_sdk::json_types::U64;
 use near_sdk::require;
 
 use crate::*;
+use crate::upgrade::ConfigV1;
 
 pub const MAX_RATIO: u32 = 10000;
 
@@ -30,17 +32,95 @@ pub struct Config {
     pub treasury_ratio: u32,
     /// fees taken from `contract_fee` to investor
     pub investor_ratio: u32,
+    /// referrer reward taken from entry fee, paid from contract fees
+    #[serde(default = "default_referral_ratio")]
+    pub referral_ratio: u32,
     /// treasury account
     pub treasury: AccountId,
     /// investor account
     pub investor: AccountId,
     /// accepted subaccounts
-    pub accepted_subs: String
+    pub accepted_subs: String,
+    /// proposed owner. Becomes an owner after `accept_ownership`
+    #[serde(default)]
+    pub pending_owner_id: Option<AccountId>,
+    /// delay in nanoseconds before proposed fees config can be applied
+    #[serde(default = "no_timelock")]
+    pub fees_timelock: U64
+}
+
+/// Config as it is kept in the contract state.
+/// New layouts are added as new variants, so stored config survives upgrades
+#[derive(BorshSerialize, BorshDeserialize)]
+pub enum VersionedConfig {
+    V1(ConfigV1),
+    V2(Config)
+}
+
+impl From<VersionedConfig> for Config {
+    fn from(config: VersionedConfig) -> Self {
+        match config {
+            VersionedConfig::V1(config) => config.into(),
+            VersionedConfig::V2(config) => config,
+        }
+    }
+}
+
+impl From<Config> for VersionedConfig {
+    fn from(config: Config) -> Self {
+        VersionedConfig::V2(config)
+    }
+}
+
+fn no_timelock() -> U64 {
+    U64(0)
+}
+
+fn default_referral_ratio() -> u32 {
+    ONE_PERCENT_RATIO
+}
+
+/// Changes of fee ratios and beneficiary accounts. Not set fields are kept
+#[derive(BorshSerialize, BorshDeserialize, Serialize, Deserialize, Clone, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct FeesConfigUpdate {
+    pub contract_fee_ratio: Option<u32>,
+    pub treasury_ratio: Option<u32>,
+    pub investor_ratio: Option<u32>,
+    #[serde(default)]
+    pub referral_ratio: Option<u32>,
+    pub treasury: Option<AccountId>,
+    pub investor: Option<AccountId>,
+    /// timelock for the next changes
+    pub fees_timelock: Option<U64>
+}
+
+/// Fees config change waiting for the timelock
+#[derive(BorshSerialize, BorshDeserialize, Serialize, Clone, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct PendingFeesConfig {
+    pub update: FeesConfigUpdate,
+    /// timestamp after which change can be applied
+    pub applicable_at: U64
+}
+
+/// Record of accepted ownership transfer
+#[derive(BorshSerialize, BorshDeserialize, Serialize, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct OwnershipTransfer {
+    pub previous_owner_id: AccountId,
+    pub new_owner_id: AccountId,
+    pub timestamp: U64
 }
 
 impl Config {
     pub fn assert_valid(&self) {
         assert!(self.contract_fee_ratio <= MAX_RATIO, "fees cannot be more than 100% in Basis Points");
+        // referral rewards are paid from contract fees
+        assert!(
+            self.contract_fee_ratio > self.referral_ratio,
+            "Contract fee ratio must be greater than referral ratio"
+        );
         assert!(self.treasury_ratio <= MAX_RATIO, "treasury ratio cannot be more than 100% from contract fees");
         assert!(
             self.investor_ratio + self.treasury_ratio < MAX_RATIO * 9 / 10,
@@ -49,6 +129,32 @@ impl Config {
     }
 }
 
+impl Config {
+    pub fn apply_fees_update(&mut self, update: FeesConfigUpdate) {
+        if let Some(contract_fee_ratio) = update.contract_fee_ratio {
+            self.contract_fee_ratio = contract_fee_ratio;
+        }
+        if let Some(treasury_ratio) = update.treasury_ratio {
+            self.treasury_ratio = treasury_ratio;
+        }
+        if let Some(investor_ratio) = update.investor_ratio {
+            self.investor_ratio = investor_ratio;
+        }
+        if let Some(referral_ratio) = update.referral_ratio {
+            self.referral_ratio = referral_ratio;
+        }
+        if let Some(treasury) = update.treasury {
+            self.treasury = treasury;
+        }
+        if let Some(investor) = update.investor {
+            self.investor = investor;
+        }
+        if let Some(fees_timelock) = update.fees_timelock {
+            self.fees_timelock = fees_timelock;
+        }
+    }
+}
+
 impl Contract {
     pub (crate) fn assert_owner(&self) {
         assert_eq!(
@@ -59,58 +165,69 @@ impl Contract {
     }
 
     pub (crate) fn internal_config(&self) -> Config {
-        self.config.get().unwrap()
+        self.config.get().unwrap().into()
     }
 
     pub (crate) fn accepted_subs(&self) -> String {
         self.internal_config().accepted_subs
     }
 
-    pub (crate) fn treasury(&self) -> AccountId {
-        self.internal_config().treasury
-    }
-
-    pub (crate) fn investor(&self) -> AccountId {
-        self.internal_config().investor
-    }
-
-    /// Contract fees ratio in basis points
-    pub (crate) fn get_contract_fee_ratio(&self) -> u32 {
-        self.internal_config().contract_fee_ratio
+    /// Fee parameters in effect for new lotteries
+    pub (crate) fn internal_lottery_fees(&self) -> LotteryFees {
+        let config = self.internal_config();
+        LotteryFees {
+            contract_fee_ratio: config.contract_fee_ratio,
+            treasury_ratio: config.treasury_ratio,
+            investor_ratio: config.investor_ratio,
+            referral_ratio: config.referral_ratio,
+            treasury: config.treasury,
+            investor: config.investor
+        }
     }
+}
 
-    /// Crop from `contract_fee_ratio` in basis points (b.p)
-    pub (crate) fn get_treasury_ratio_from_contract_fees(&self) -> u32 {
-        self.internal_config().treasury_ratio
-    }
+fn compute_internal_fee_ratio(contract_fees: Balance, ratio_from_contract_fees: u32) -> Balance {
+    ratio(contract_fees, ratio_from_contract_fees)
+}
 
-    /// Crop from `contract_fee_ratio` in basis points (b.p)
-    pub (crate) fn get_investor_ratio_from_contract_fees(&self) -> u32 {
-        self.internal_config().investor_ratio
-    }
+/// Fee parameters snapshotted into lottery when it is created.
+/// Config changes are not applied to already created lotteries
+#[derive(BorshSerialize, BorshDeserialize, Serialize, Clone, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct LotteryFees {
+    /// fees taken from prize pool to contract
+    pub contract_fee_ratio: u32,
+    /// fees taken from `contract_fee` to treasury
+    pub treasury_ratio: u32,
+    /// fees taken from `contract_fee` to investor
+    pub investor_ratio: u32,
+    /// referrer reward taken from entry fee
+    pub referral_ratio: u32,
+    pub treasury: AccountId,
+    pub investor: AccountId
+}
 
+impl LotteryFees {
     /// Treasury ratio in basis points
-    pub (crate) fn get_treasury_taken_amount(&self, contract_fees: Balance) -> Balance {
-        compute_internal_fee_ratio(contract_fees, self.get_treasury_ratio_from_contract_fees())
+    pub fn get_treasury_taken_amount(&self, contract_fees: Balance) -> Balance {
+        compute_internal_fee_ratio(contract_fees, self.treasury_ratio)
     }
 
     /// Investor ratio in basis points
-    pub (crate) fn get_investor_taken_amount(&self, contract_fees: Balance) -> Balance {
-        compute_internal_fee_ratio(contract_fees, self.get_investor_ratio_from_contract_fees())
+    pub fn get_investor_taken_amount(&self, contract_fees: Balance) -> Balance {
+        compute_internal_fee_ratio(contract_fees, self.investor_ratio)
     }
 }
 
-fn compute_internal_fee_ratio(contract_fees: Balance, ratio_from_contract_fees: u32) -> Balance {
-    ratio(contract_fees, ratio_from_contract_fees)
-}
-
 #[near_bindgen]
 impl Contract {
     /// Change accepted subs
+    /// - Requires one yoctoNEAR.
+    /// - Requires to be called by the contract owner or `ConfigManager`.
     #[payable]
     pub fn change_accepted_subs(&mut self, accepted_subs: String) -> bool {
         assert_one_yocto();
-        self.assert_owner();
+        self.assert_role(Role::ConfigManager);
 
         let accepted_subs_near_env = accepted_subs.split('.').collect::<Vec<_>>();
         require!(accepted_subs_near_env.len() == 2, "Expected format is sub.near");
@@ -123,18 +240,116 @@ impl Contract {
 
         let mut config = self.internal_config();
         config.accepted_subs = accepted_subs;
-        self.config.set(&config);
+        self.config.set(&config.into());
 
         true
     }
-    /// Add FT to the whitelist.
+    /// Proposes new contract owner. Ownership is transfered after `accept_ownership` call
     /// - Requires one yoctoNEAR.
     /// - Requires to be called by the contract owner.
+    #[payable]
+    pub fn propose_owner(&mut self, new_owner_id: AccountId) {
+        assert_one_yocto();
+        self.assert_owner();
+
+        let mut config = self.internal_config();
+        require!(config.owner_id != new_owner_id, "Already an owner");
+        config.pending_owner_id = Some(new_owner_id);
+        self.config.set(&config.into());
+    }
+
+    /// Cancels ownership proposal.
+    /// - Requires one yoctoNEAR.
+    /// - Requires to be called by the contract owner.
+    #[payable]
+    pub fn cancel_owner_proposal(&mut self) {
+        assert_one_yocto();
+        self.assert_owner();
+
+        let mut config = self.internal_config();
+        require!(config.pending_owner_id.is_some(), "No proposed owner");
+        config.pending_owner_id = None;
+        self.config.set(&config.into());
+    }
+
+    /// Accepts contract ownership.
+    /// - Requires one yoctoNEAR.
+    /// - Requires to be called by the proposed owner.
+    #[payable]
+    pub fn accept_ownership(&mut self) {
+        assert_one_yocto();
+
+        let mut config = self.internal_config();
+        let new_owner_id = env::predecessor_account_id();
+        require!(config.pending_owner_id.as_ref() == Some(&new_owner_id), "Not a proposed owner");
+
+        let previous_owner_id = std::mem::replace(&mut config.owner_id, new_owner_id.clone());
+        config.pending_owner_id = None;
+        self.config.set(&config.into());
+
+        self.ownership_transfers.push(&OwnershipTransfer {
+            previous_owner_id,
+            new_owner_id,
+            timestamp: U64(env::block_timestamp())
+        });
+    }
+
+    /// Proposes changes of fee ratios and beneficiary accounts.
+    /// - Requires one yoctoNEAR.
+    /// - Requires to be called by the contract owner.
+    /// - Changes can be applied with `apply_fees_config` after `fees_timelock` passed
+    /// - Replaces previously proposed changes
+    #[payable]
+    pub fn propose_fees_config(&mut self, update: FeesConfigUpdate) {
+        assert_one_yocto();
+        self.assert_owner();
+
+        let mut config = self.internal_config();
+        let applicable_at = env::block_timestamp() + config.fees_timelock.0;
+        config.apply_fees_update(update.clone());
+        config.assert_valid();
+        self.internal_lottery_config().assert_valid_prize_tables(config.referral_ratio);
+
+        self.pending_fees_config.set(&PendingFeesConfig {
+            update,
+            applicable_at: U64(applicable_at)
+        });
+    }
+
+    /// Applies proposed fees config.
+    /// - Can be called by anyone
+    /// - Panics if timelock has not passed yet
+    pub fn apply_fees_config(&mut self) {
+        let pending = self.pending_fees_config.get().expect("No pending fees config");
+        require!(env::block_timestamp() >= pending.applicable_at.0, "Fees config timelock has not passed");
+
+        let mut config = self.internal_config();
+        config.apply_fees_update(pending.update);
+        config.assert_valid();
+        self.internal_lottery_config().assert_valid_prize_tables(config.referral_ratio);
+        self.config.set(&config.into());
+        self.pending_fees_config.remove();
+    }
+
+    /// Cancels proposed fees config.
+    /// - Requires one yoctoNEAR.
+    /// - Requires to be called by the contract owner.
+    #[payable]
+    pub fn cancel_fees_config(&mut self) {
+        assert_one_yocto();
+        self.assert_owner();
+
+        require!(self.pending_fees_config.remove(), "No pending fees config");
+    }
+
+    /// Add FT to the whitelist.
+    /// - Requires one yoctoNEAR.
+    /// - Requires to be called by the contract owner or `TokenManager`.
     /// - Requires this token not being already whitelisted.
     #[payable]
     pub fn whitelist_token(&mut self, token_id: AccountId) {
         assert_one_yocto();
-        self.assert_owner();
+        self.assert_role(Role::TokenManager);
 
         assert!(!self.whitelisted_tokens.contains(&token_id), "Already whitelisted");
         self.whitelisted_tokens.insert(&token_id);
@@ -142,12 +357,12 @@ impl Contract {
 
     /// Removes FT to the whitelist.
     /// - Requires one yoctoNEAR.
-    /// - Requires to be called by the contract owner.
+    /// - Requires to be called by the contract owner or `TokenManager`.
     /// - Requires this token being whitelisted.
     #[payable]
     pub fn remove_whitelist_token(&mut self, token_id: AccountId) {
         assert_one_yocto();
-        self.assert_owner();
+        self.assert_role(Role::TokenManager);
 
         assert!(self.whitelisted_tokens.contains(&token_id), "Not fount in whitelisted list");
         self.whitelisted_tokens.remove(&token_id);
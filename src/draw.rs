@@ -0,0 +1,412 @@
+use std::collections::HashSet;
+
+use near_sdk::json_types::Base58CryptoHash;
+use near_sdk::CryptoHash;
+
+use crate::*;
+
+/// Inputs and result of lottery draw kept after lottery is finished.
+/// Winners can be recomputed from these inputs with `verify_draw`
+#[derive(BorshSerialize, BorshDeserialize, Serialize, Clone, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct DrawRecord {
+    pub lottery_id: LotteryId,
+    pub lottery_type: LotteryType,
+    /// seed winners were picked from
+    pub seed: Base58CryptoHash,
+    pub block_height: BlockHeight,
+    /// hash of entries in entry order, see `hash_entries`
+    pub entries_hash: Base58CryptoHash,
+    pub entries_num: u32,
+    pub tickets_num: u32,
+    /// Simple and big lottery: entries which could not win met during the draw.
+    /// Other lotteries: tickets which could not win.
+    /// Entries cannot win if their secrets were not revealed in `CommitReveal` draw mode
+    pub excluded_indices: Vec<u32>,
+    /// Simple lottery: entries of winners in rank order.
+    /// Big lottery: entries of winners in winning order, top prize tier goes first.
+    /// Timed lottery and raffle: ticket of the winner.
+    /// Jackpot lottery: ticket of the winner, empty if drawn number matches no ticket.
+    /// Lotto: empty, winners are counted by matched numbers when entries are settled.
+    /// Tickets are indexed in entry order, tickets of an entry go in a row
+    pub winner_indices: Vec<u32>,
+    /// Jackpot lottery: ticket number is drawn from `[0, ticket_range)`.
+    /// Lotto: numbers are drawn from `1..=ticket_range`
+    pub ticket_range: Option<u32>,
+    /// Lotto: drawn numbers in ascending order
+    pub drawn_numbers: Vec<u32>,
+    /// Lotto: hash of picked numbers, see `hash_picks`
+    pub picks_hash: Option<Base58CryptoHash>,
+    /// Lotto: matched numbers of paytable tiers
+    pub winning_matches: Vec<u32>,
+    /// Lotto: winners of every paytable tier, set when all entries are counted in settlement
+    pub tier_winners_nums: Vec<u32>
+}
+
+impl DrawRecord {
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        lottery_id: LotteryId,
+        lottery_type: LotteryType,
+        seed: [u8; 32],
+        entries_hash: CryptoHash,
+        entries_num: u32,
+        tickets_num: u32,
+        excluded_indices: Vec<u32>,
+        winner_indices: Vec<u32>
+    ) -> Self {
+        Self {
+            lottery_id,
+            lottery_type,
+            seed: seed.into(),
+            block_height: env::block_height(),
+            entries_hash: entries_hash.into(),
+            entries_num,
+            tickets_num,
+            excluded_indices,
+            winner_indices,
+            ticket_range: None,
+            drawn_numbers: vec![],
+            picks_hash: None,
+            winning_matches: vec![],
+            tier_winners_nums: vec![]
+        }
+    }
+
+    /// Winner indices computed again from stored inputs, given entries and lotto picked numbers
+    pub fn recompute_winner_indices(&self, entries: &[Entry], picks: &[Vec<u32>]) -> Vec<u32> {
+        let purpose = match self.lottery_type {
+            LotteryType::SimpleLottery | LotteryType::BigLottery => {
+                let purpose = if self.lottery_type == LotteryType::SimpleLottery {
+                    RandomPurpose::SimpleLotteryWinner
+                } else {
+                    RandomPurpose::BigLotteryOrder
+                };
+                let tickets_ends = tickets_ends(entries);
+                let (winner_indices, _) = pick_ticket_winners(
+                    self.seed.into(),
+                    self.lottery_id,
+                    purpose,
+                    self.entries_num,
+                    self.tickets_num,
+                    self.winner_indices.len() as _,
+                    |ticket| {
+                        let index = entry_of_ticket(&tickets_ends, ticket);
+                        (index, self.excluded_indices.binary_search(&index).is_ok())
+                    }
+                );
+                return winner_indices;
+            },
+            LotteryType::TimedLottery => RandomPurpose::TimedLotteryWinner,
+            LotteryType::Raffle => RandomPurpose::RaffleWinner,
+            LotteryType::JackpotLottery => {
+                let ticket_range = self.ticket_range.expect("Ticket range was not found");
+                let drawn_number = draw_ticket_number(self.seed.into(), self.lottery_id, ticket_range);
+                return (drawn_number < self.tickets_num).then_some(drawn_number).into_iter().collect();
+            },
+            LotteryType::Lotto => {
+                let drawn_numbers = self.recompute_lotto_numbers();
+                return picks
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, numbers)| self.winning_matches.contains(&lotto_matches(numbers, &drawn_numbers)))
+                    .map(|(index, _)| index as u32)
+                    .collect();
+            },
+        };
+        pick_winners(
+            self.seed.into(),
+            self.lottery_id,
+            purpose,
+            self.tickets_num,
+            &self.excluded_indices,
+            self.winner_indices.len() as _
+        )
+    }
+
+    fn recompute_lotto_numbers(&self) -> Vec<u32> {
+        let max_number = self.ticket_range.expect("Numbers range was not found");
+        draw_lotto_numbers(self.seed.into(), self.lottery_id, self.drawn_numbers.len() as _, max_number)
+    }
+
+    /// Lotto winners of every paytable tier among given picked numbers
+    fn recompute_tier_winners_nums(&self, picks: &[Vec<u32>]) -> Vec<u32> {
+        let drawn_numbers = self.recompute_lotto_numbers();
+        self.winning_matches
+            .iter()
+            .map(|matches| {
+                picks.iter().filter(|numbers| lotto_matches(numbers, &drawn_numbers) == *matches).count() as u32
+            })
+            .collect()
+    }
+
+    /// Winner of `winner_index` among given entries and their ticket holders
+    fn winner(&self, entries: &[Entry], ticket_holders: &[AccountId], winner_index: u32) -> AccountId {
+        match self.lottery_type {
+            LotteryType::SimpleLottery | LotteryType::BigLottery => entries[winner_index as usize].account_id.clone(),
+            _ => ticket_holders[winner_index as usize].clone(),
+        }
+    }
+}
+
+#[derive(Serialize, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct DrawVerification {
+    /// given entries match stored entries hash
+    pub entries_match: bool,
+    /// recomputed winners match stored winners
+    pub winners_match: bool,
+    /// recomputed winners in winning order
+    pub winners: Vec<AccountId>
+}
+
+/// Hash of entries list which is updated entry by entry, so the draw does not read all entries:
+/// sum of sha256 of borsh serialized `(index, entry)` of every entry modulo 2^256
+pub (crate) fn hash_entries(entries: &[Entry]) -> CryptoHash {
+    entries
+        .iter()
+        .enumerate()
+        .fold(CryptoHash::default(), |entries_hash, (index, entry)| add_entry_hash(entries_hash, index as _, entry))
+}
+
+fn entry_hash(index: u32, entry: &Entry) -> U256 {
+    U256::from_little_endian(&env::sha256_array(&(index, entry.clone()).try_to_vec().unwrap()))
+}
+
+fn add_hash(hash: CryptoHash, item_hash: U256) -> CryptoHash {
+    let mut result = CryptoHash::default();
+    U256::from_little_endian(&hash)
+        .overflowing_add(item_hash).0
+        .to_little_endian(&mut result);
+    result
+}
+
+fn sub_hash(hash: CryptoHash, item_hash: U256) -> CryptoHash {
+    let mut result = CryptoHash::default();
+    U256::from_little_endian(&hash)
+        .overflowing_sub(item_hash).0
+        .to_little_endian(&mut result);
+    result
+}
+
+pub (crate) fn add_entry_hash(entries_hash: CryptoHash, index: u32, entry: &Entry) -> CryptoHash {
+    add_hash(entries_hash, entry_hash(index, entry))
+}
+
+pub (crate) fn sub_entry_hash(entries_hash: CryptoHash, index: u32, entry: &Entry) -> CryptoHash {
+    sub_hash(entries_hash, entry_hash(index, entry))
+}
+
+/// Hash of lotto picked numbers which is updated entry by entry, entry order is covered by entries hash:
+/// sum of sha256 of borsh serialized `(account_id, numbers)` of every entry modulo 2^256
+pub (crate) fn hash_picks(accounts: &[AccountId], picks: &[Vec<u32>]) -> CryptoHash {
+    accounts
+        .iter()
+        .zip(picks)
+        .fold(CryptoHash::default(), |picks_hash, (account_id, numbers)| add_picks_hash(picks_hash, account_id, numbers))
+}
+
+fn entry_picks_hash(account_id: &AccountId, numbers: &[u32]) -> U256 {
+    U256::from_little_endian(&env::sha256_array(&(account_id, numbers).try_to_vec().unwrap()))
+}
+
+pub (crate) fn add_picks_hash(picks_hash: CryptoHash, account_id: &AccountId, numbers: &[u32]) -> CryptoHash {
+    add_hash(picks_hash, entry_picks_hash(account_id, numbers))
+}
+
+pub (crate) fn sub_picks_hash(picks_hash: CryptoHash, account_id: &AccountId, numbers: &[u32]) -> CryptoHash {
+    sub_hash(picks_hash, entry_picks_hash(account_id, numbers))
+}
+
+/// Picked numbers found among drawn numbers, both are in ascending order
+pub (crate) fn lotto_matches(numbers: &[u32], drawn_numbers: &[u32]) -> u32 {
+    numbers.iter().filter(|number| drawn_numbers.binary_search(number).is_ok()).count() as u32
+}
+
+/// Entries of ticket holders list, tickets of an entry go in a row
+fn entries_of(ticket_holders: &[AccountId]) -> Vec<Entry> {
+    let mut entries: Vec<Entry> = vec![];
+    for account_id in ticket_holders {
+        match entries.last_mut() {
+            Some(entry) if &entry.account_id == account_id => entry.tickets += 1,
+            _ => entries.push(Entry { account_id: account_id.clone(), tickets: 1 }),
+        }
+    }
+    entries
+}
+
+/// Tickets held by every entry and all entries before it
+pub (crate) fn tickets_ends(entries: &[Entry]) -> Vec<u32> {
+    entries
+        .iter()
+        .scan(0, |tickets, entry| {
+            *tickets += entry.tickets;
+            Some(*tickets)
+        })
+        .collect()
+}
+
+/// Index of entry holding `ticket` given `tickets_ends` of entries
+pub (crate) fn entry_of_ticket(tickets_ends: &[u32], ticket: u32) -> u32 {
+    tickets_ends.partition_point(|end| *end <= ticket) as _
+}
+
+/// Index of `candidate`-th entry which is not excluded, so candidates are not listed
+fn nth_candidate(candidate: u32, excluded_indices: &[u32]) -> u32 {
+    let mut index = candidate;
+    for excluded in excluded_indices {
+        if *excluded > index {
+            break;
+        }
+        index += 1;
+    }
+    index
+}
+
+/// Indices of lottery winners in winning order.
+/// Excluded entries win only if there are not enough other entries
+pub (crate) fn pick_winners(
+    seed: [u8; 32],
+    lottery_id: LotteryId,
+    purpose: RandomPurpose,
+    entries_num: u32,
+    excluded_indices: &[u32],
+    winners_num: u32
+) -> Vec<u32> {
+    let mut rng = LotteryRng::new(seed, lottery_id, purpose);
+    let candidates_num = entries_num - excluded_indices.len() as u32;
+    let from_candidates = std::cmp::min(winners_num, candidates_num);
+
+    let mut winners = rng
+        .sample(candidates_num, from_candidates)
+        .into_iter()
+        .map(|candidate| nth_candidate(candidate, excluded_indices))
+        .collect::<Vec<_>>();
+    winners.extend(
+        rng.sample(excluded_indices.len() as _, winners_num - from_candidates)
+            .into_iter()
+            .map(|index| excluded_indices[index as usize])
+    );
+    winners
+}
+
+/// Indices of entries holding winning tickets in winning order.
+/// Tickets are drawn in random order, so entries win in proportion to tickets,
+/// and only entries of drawn tickets are found by `read_ticket` as `(entry index, is_excluded)`.
+/// Entry wins once, its other tickets are skipped after that.
+/// Excluded entries win only if there are not enough other entries.
+/// Returns winner indices and sorted indices of excluded entries met during the draw
+pub (crate) fn pick_ticket_winners(
+    seed: [u8; 32],
+    lottery_id: LotteryId,
+    purpose: RandomPurpose,
+    entries_num: u32,
+    tickets_num: u32,
+    winners_num: u32,
+    mut read_ticket: impl FnMut(u32) -> (u32, bool)
+) -> (Vec<u32>, Vec<u32>) {
+    let mut rng = LotteryRng::new(seed, lottery_id, purpose);
+    let mut won = HashSet::new();
+    let mut winners = vec![];
+    let mut excluded_winners = vec![];
+    for ticket in rng.permutation(tickets_num) {
+        if winners.len() as u32 == winners_num || won.len() as u32 == entries_num {
+            break;
+        }
+        let (index, is_excluded) = read_ticket(ticket);
+        if !won.insert(index) {
+            continue;
+        }
+        if is_excluded {
+            excluded_winners.push(index);
+        } else {
+            winners.push(index);
+        }
+    }
+    let mut excluded_indices = excluded_winners.clone();
+    excluded_indices.sort_unstable();
+
+    let excluded_num = std::cmp::min(winners_num as usize - winners.len(), excluded_winners.len());
+    winners.extend_from_slice(&excluded_winners[..excluded_num]);
+    (winners, excluded_indices)
+}
+
+/// Jackpot ticket number in `[0, ticket_range)`
+pub (crate) fn draw_ticket_number(seed: [u8; 32], lottery_id: LotteryId, ticket_range: u32) -> u32 {
+    LotteryRng::new(seed, lottery_id, RandomPurpose::JackpotNumber).gen_range(0, ticket_range)
+}
+
+/// Lotto numbers from `1..=max_number` in ascending order
+pub (crate) fn draw_lotto_numbers(seed: [u8; 32], lottery_id: LotteryId, pick_count: u32, max_number: u32) -> Vec<u32> {
+    let mut numbers = LotteryRng::new(seed, lottery_id, RandomPurpose::LottoNumbers)
+        .sample(max_number, pick_count)
+        .into_iter()
+        .map(|number| number + 1)
+        .collect::<Vec<_>>();
+    numbers.sort_unstable();
+    numbers
+}
+
+#[near_bindgen]
+impl Contract {
+    /// Returns draw record of finished lottery
+    pub fn get_draw(&self, lottery_id: LotteryId) -> Option<DrawRecord> {
+        self.draws.get(&lottery_id)
+    }
+
+    /// Recomputes winners of finished lottery from its draw record.
+    /// - `entries` - lottery entries in entry order, e.g. `participants` of logged lottery result
+    ///   or entries read with `get_lottery_entries` before the draw, results of large lotteries do not list participants.
+    ///   Account holding several tickets is repeated for every ticket
+    /// - Lotto draw is verified with `verify_lotto_draw`
+    pub fn verify_draw(&self, lottery_id: LotteryId, entries: Vec<AccountId>) -> DrawVerification {
+        let record = self.draws.get(&lottery_id).expect("Draw record was not found");
+        assert_ne!(record.lottery_type, LotteryType::Lotto, "Lotto draw is verified with picked numbers");
+        internal_verify_draw(&record, entries, vec![])
+    }
+
+    /// Recomputes winners of finished lotto from its draw record and picked numbers.
+    /// - `tickets` - picked numbers of lotto entries in entry order, e.g. read with `get_lotto_tickets` before the draw
+    /// - Matched numbers of every ticket are counted against recomputed drawn numbers
+    /// - Winners match if winners num of every tier matches the record, it is stored when lotto entries are counted
+    pub fn verify_lotto_draw(&self, lottery_id: LotteryId, tickets: Vec<LottoTicket>) -> DrawVerification {
+        let record = self.draws.get(&lottery_id).expect("Draw record was not found");
+        assert_eq!(record.lottery_type, LotteryType::Lotto, "Lottery is not lotto");
+        let (entries, picks) = tickets
+            .into_iter()
+            .map(|ticket| (ticket.account_id, ticket.numbers))
+            .unzip();
+        internal_verify_draw(&record, entries, picks)
+    }
+}
+
+fn internal_verify_draw(record: &DrawRecord, entries: Vec<AccountId>, picks: Vec<Vec<u32>>) -> DrawVerification {
+    let ticket_holders = entries;
+    let entries = entries_of(&ticket_holders);
+    let entries_match = hash_entries(&entries) == <[u8; 32]>::from(record.entries_hash)
+        && entries.len() as u32 == record.entries_num
+        && ticket_holders.len() as u32 == record.tickets_num
+        && record.picks_hash.map_or(true, |picks_hash| hash_picks(&ticket_holders, &picks) == <[u8; 32]>::from(picks_hash));
+
+    let winner_indices = record.recompute_winner_indices(&entries, &picks);
+    let winners_match = match record.lottery_type {
+        // lotto winners are not stored, only their number in every tier
+        LotteryType::Lotto => {
+            record.recompute_lotto_numbers() == record.drawn_numbers
+                && record.recompute_tier_winners_nums(&picks) == record.tier_winners_nums
+        },
+        _ => winner_indices == record.winner_indices,
+    };
+    DrawVerification {
+        entries_match,
+        winners_match,
+        winners: if entries_match {
+            winner_indices
+                .iter()
+                .map(|index| record.winner(&entries, &ticket_holders, *index))
+                .collect()
+        } else {
+            vec![]
+        }
+    }
+}
@@ -0,0 +1,65 @@
+use std::collections::HashMap;
+
+use near_sdk::{require, CryptoHash};
+
+use crate::*;
+
+/// Secrets committed by entrants of lottery in `CommitReveal` draw mode.
+/// Revealed secrets are mixed with block seed when winners are picked.
+/// Entrants who did not reveal their secret before `draw` cannot win
+#[derive(BorshSerialize, BorshDeserialize, Serialize, Default)]
+#[cfg_attr(not(target_arch = "wasm32"), derive(Clone))]
+#[serde(crate = "near_sdk::serde")]
+pub struct EntrantsEntropy {
+    /// sha256 of entrant secret
+    pub commitments: HashMap<AccountId, CryptoHash>,
+    /// entrants which revealed secrets
+    pub revealed: Vec<AccountId>,
+    /// revealed secrets mixed together
+    pub mixed: CryptoHash
+}
+
+impl EntrantsEntropy {
+    pub fn commit(&mut self, account_id: &AccountId, secret_hash: CryptoHash) {
+        self.commitments.insert(account_id.clone(), secret_hash);
+    }
+
+    pub fn remove(&mut self, account_id: &AccountId) {
+        self.commitments.remove(account_id);
+    }
+
+    pub fn reveal(&mut self, account_id: &AccountId, secret: &str) {
+        let commitment = self.commitments.get(account_id).expect("No committed secret");
+        require!(!self.revealed.contains(account_id), "Secret already revealed");
+        require!(
+            env::sha256(secret.as_bytes()).as_slice() == commitment,
+            "Secret does not match commitment"
+        );
+
+        let mut mixed = self.mixed.to_vec();
+        mixed.extend_from_slice(secret.as_bytes());
+        self.mixed = env::sha256_array(&mixed);
+        self.revealed.push(account_id.clone());
+    }
+
+    pub fn is_all_revealed(&self) -> bool {
+        self.revealed.len() == self.commitments.len()
+    }
+
+    pub fn is_revealed(&self, account_id: &AccountId) -> bool {
+        self.revealed.contains(account_id)
+    }
+
+    /// Entrant not revealed secret cannot win.
+    /// If nobody revealed a secret, all entrants can win
+    pub fn is_excluded(&self, account_id: &AccountId) -> bool {
+        !self.revealed.is_empty() && !self.is_revealed(account_id)
+    }
+
+    /// Current block seed mixed with revealed secrets
+    pub fn seed(&self) -> [u8; 32] {
+        let mut seed = env::random_seed();
+        seed.extend_from_slice(&self.mixed);
+        env::sha256_array(&seed)
+    }
+}
@@ -0,0 +1,104 @@
+use near_sdk::json_types::U64;
+use near_sdk::{is_promise_success, require, Gas};
+
+use crate::*;
+
+const GAS_FOR_AFTER_FEES_WITHDRAW: Gas = Gas(Gas::ONE_TERA.0 * 10);
+
+/// Confirmed withdrawal of contract fees
+#[derive(BorshSerialize, BorshDeserialize, Serialize, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct FeesWithdrawal {
+    pub token_id: AccountId,
+    pub amount: U128,
+    pub receiver_id: AccountId,
+    pub timestamp: U64
+}
+
+impl Contract {
+    /// Fees which can be withdrawn now (collected fees without withdrawals in progress)
+    pub (crate) fn internal_available_fees(&self, token_id: &AccountId) -> Balance {
+        let collected = self.fees.get(token_id).unwrap_or_default();
+        let locked = self.fees_locked.get(token_id).unwrap_or_default();
+        collected - locked
+    }
+
+    fn internal_unlock_fees(&mut self, token_id: &AccountId, amount: Balance) {
+        let locked = self.fees_locked.get(token_id).unwrap_or_default() - amount;
+        if locked == 0 {
+            self.fees_locked.remove(token_id);
+        } else {
+            self.fees_locked.insert(token_id, &locked);
+        }
+    }
+}
+
+#[near_bindgen]
+impl Contract {
+    /// Withdraws collected contract fees to given receiver.
+    /// - Requires one yoctoNEAR.
+    /// - Requires to be called by the contract owner or `TreasuryManager`.
+    /// - Panics if amount is greater than fees available for withdrawal
+    /// - Fees balance is deducted only after transfer succeeded
+    #[payable]
+    pub fn withdraw_fees(&mut self, token_id: AccountId, amount: U128, receiver_id: AccountId) -> Promise {
+        assert_one_yocto();
+        self.assert_role(Role::TreasuryManager);
+
+        let amount = amount.0;
+        require!(amount > 0, "Amount cannot be zero");
+        require!(
+            amount <= self.internal_available_fees(&token_id),
+            "Not enough fees to withdraw"
+        );
+
+        let locked = self.fees_locked.get(&token_id).unwrap_or_default();
+        self.fees_locked.insert(&token_id, &(locked + amount));
+
+        let transfer = if token_id == near() {
+            Promise::new(receiver_id.clone()).transfer(amount)
+        } else {
+            self.internal_ft_transfer(&receiver_id, &token_id, amount)
+        };
+
+        transfer.then(Self::ext(env::current_account_id())
+            .with_static_gas(GAS_FOR_AFTER_FEES_WITHDRAW)
+            .after_fees_withdraw(token_id, amount.into(), receiver_id)
+        )
+    }
+
+    /// Deducts fees balance and stores withdrawal if transfer succeeded
+    #[private]
+    pub fn after_fees_withdraw(
+        &mut self,
+        token_id: AccountId,
+        amount: U128,
+        receiver_id: AccountId
+    ) -> bool {
+        self.internal_unlock_fees(&token_id, amount.0);
+
+        let promise_success = is_promise_success();
+        if promise_success {
+            let collected = self.fees.get(&token_id).unwrap_or_default();
+            self.fees.insert(&token_id, &(collected - amount.0));
+            self.fees_withdrawals.push(&FeesWithdrawal {
+                token_id,
+                amount,
+                receiver_id,
+                timestamp: U64(env::block_timestamp())
+            });
+        } else {
+            log!("Fees withdrawal ( {} yocto{} ) to @{} failed", amount.0, match_token_id(&token_id), receiver_id);
+        }
+        promise_success
+    }
+
+    /// Returns confirmed fees withdrawals from a given index up to a given limit.
+    pub fn get_fees_withdrawals(&self, from_index: Option<u64>, limit: Option<u64>) -> Vec<FeesWithdrawal> {
+        let from_index = from_index.unwrap_or(0);
+        let limit = limit.unwrap_or(self.fees_withdrawals.len());
+        (from_index..std::cmp::min(self.fees_withdrawals.len(), from_index + limit))
+            .map(|index| self.fees_withdrawals.get(index).unwrap())
+            .collect()
+    }
+}
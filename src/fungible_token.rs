@@ -1,12 +1,18 @@
 use crate::*;
 use near_contract_standards::fungible_token::receiver::FungibleTokenReceiver;
-use near_sdk::json_types::U128;
+use near_sdk::json_types::{Base58CryptoHash, U128};
 use near_sdk::{Gas, ext_contract, is_promise_success, serde_json, PromiseOrValue, ONE_YOCTO};
 
 const GAS_FOR_FT_TRANSFER: Gas = Gas(Gas::ONE_TERA.0 * 10);
-const GAS_FOR_AFTER_FT_TRANSFER: Gas = Gas(Gas::ONE_TERA.0 * 20);
+const GAS_FOR_AFTER_FT_TRANSFER: Gas = Gas(Gas::ONE_TERA.0 * 5);
+const GAS_FOR_AFTER_NEAR_TRANSFER: Gas = Gas(Gas::ONE_TERA.0 * 5);
+/// Estimated gas for checked NEAR transfer including callback and data receipt
+pub const GAS_FOR_NEAR_PAYOUT: Gas = Gas(Gas::ONE_TERA.0 * 20);
+/// Estimated gas for checked FT transfer including callback and data receipt
+pub const GAS_FOR_FT_PAYOUT: Gas = Gas(Gas::ONE_TERA.0 * 35);
 
 #[ext_contract(ext_ft)]
+#[allow(dead_code)]
 pub trait FungibleToken {
     fn ft_transfer(&mut self, receiver_id: AccountId, amount: U128, memo: Option<String>) -> Promise;
 }
@@ -16,10 +22,25 @@ pub trait FungibleToken {
 #[derive(Deserialize)]
 #[cfg_attr(not(target_arch = "wasm32"), derive(Debug, Serialize))]
 #[serde(crate = "near_sdk::serde")]
+#[allow(clippy::enum_variant_names)]
 pub enum TokenReceiverMsg {
     DrawEnter {
         num_participants: u32,
         lottery_type: String,
+        referrer_id: Option<AccountId>,
+        /// sha256 of entrant secret, required in `CommitReveal` draw mode
+        secret_hash: Option<Base58CryptoHash>,
+        /// tickets to buy, transfered amount is split between them. One by default
+        tickets: Option<u32>
+    },
+    LottoEnter {
+        /// distinct numbers from `1..=max_number` of lotto config, as many as `pick_count`
+        numbers: Vec<u32>,
+        referrer_id: Option<AccountId>
+    },
+    RaffleEnter {
+        /// raffle created by NFT deposit, transfered amount equals to its entry fee
+        lottery_id: LotteryId,
         referrer_id: Option<AccountId>
     }
 }
@@ -48,18 +69,49 @@ impl FungibleTokenReceiver for Contract {
             TokenReceiverMsg::DrawEnter { 
                 num_participants, 
                 lottery_type,
-                referrer_id
+                referrer_id,
+                secret_hash,
+                tickets
             } => {
+                let lottery_type = LotteryType::from(lottery_type);
+                self.assert_entries_not_paused(&token_id, lottery_type);
                 let lottery_id = self.draw_enter(
                     &sender_id,
                     token_id,
-                    LotteryType::from(lottery_type),
+                    lottery_type,
                     num_participants,
                     amount.0,
-                    referrer_id
+                    EntryOptions {
+                        referrer_id,
+                        secret_hash: secret_hash.map(CryptoHash::from),
+                        tickets: tickets.unwrap_or(1),
+                        numbers: None
+                    }
                 );
                 log!("Draw enter. Lottery ID: {}, account: @{}", lottery_id, sender_id);
             },
+            TokenReceiverMsg::LottoEnter { numbers, referrer_id } => {
+                self.assert_entries_not_paused(&token_id, LotteryType::Lotto);
+                let lottery_id = self.draw_enter(
+                    &sender_id,
+                    token_id,
+                    LotteryType::Lotto,
+                    0,
+                    amount.0,
+                    EntryOptions {
+                        referrer_id,
+                        secret_hash: None,
+                        tickets: 1,
+                        numbers: Some(numbers)
+                    }
+                );
+                log!("Lotto enter. Lottery ID: {}, account: @{}", lottery_id, sender_id);
+            },
+            TokenReceiverMsg::RaffleEnter { lottery_id, referrer_id } => {
+                self.assert_entries_not_paused(&token_id, LotteryType::Raffle);
+                self.raffle_enter(&sender_id, token_id, lottery_id, amount.0, referrer_id);
+                log!("Raffle enter. Lottery ID: {}, account: @{}", lottery_id, sender_id);
+            },
         }
 
         PromiseOrValue::Value(U128(0))
@@ -89,39 +141,54 @@ impl Contract {
         token_id: &AccountId,
         amount: Balance,
     ) -> Promise {
-        ext_ft::ext(token_id.clone())
-            .with_attached_deposit(ONE_YOCTO)
-            .with_static_gas(GAS_FOR_FT_TRANSFER)
-            .ft_transfer(
-                account_id.clone(), 
-                amount.into(), 
-                Some("withdrawal from vault supplied".into())
+        self.internal_ft_transfer(account_id, token_id, amount)
+            .then(Self::ext(env::current_account_id())
+                .with_static_gas(GAS_FOR_AFTER_FT_TRANSFER)
+                .after_ft_transfer(account_id.clone(), token_id.clone(), amount.into())
             )
-        .then(Self::ext(env::current_account_id())
-            .with_static_gas(GAS_FOR_AFTER_FT_TRANSFER)
-            .after_ft_transfer(account_id.clone(), token_id.clone(), amount)
-        )
     }
-}
 
-#[ext_contract(ext_self)]
-trait ExtSelf {
-    fn after_ft_transfer(&mut self, account_id: AccountId, token_id: AccountId, amount: Balance)
-        -> bool;
+    pub fn internal_near_transfer_checked(
+        &mut self,
+        account_id: &AccountId,
+        amount: Balance,
+    ) -> Promise {
+        Promise::new(account_id.clone())
+            .transfer(amount)
+            .then(Self::ext(env::current_account_id())
+                .with_static_gas(GAS_FOR_AFTER_NEAR_TRANSFER)
+                .after_near_transfer(account_id.clone(), amount.into())
+            )
+    }
 }
 
 #[near_bindgen]
-impl ExtSelf for Contract {
+impl Contract {
+    /// Stores amount to `failed_payouts` if FT transfer was not succeeded
     #[private]
-    fn after_ft_transfer(
+    pub fn after_ft_transfer(
         &mut self,
-        _account_id: AccountId,
-        _token_id: AccountId,
-        _amount: Balance,
+        account_id: AccountId,
+        token_id: AccountId,
+        amount: U128,
+    ) -> bool {
+        let promise_success = is_promise_success();
+        if !promise_success {
+            self.internal_record_failed_payout(&account_id, &token_id, amount.0);
+        }
+        promise_success
+    }
+
+    /// Stores amount to `failed_payouts` if NEAR transfer was not succeeded
+    #[private]
+    pub fn after_near_transfer(
+        &mut self,
+        account_id: AccountId,
+        amount: U128,
     ) -> bool {
         let promise_success = is_promise_success();
         if !promise_success {
-            //revert to store and do stuff again
+            self.internal_record_failed_payout(&account_id, &near(), amount.0);
         }
         promise_success
     }
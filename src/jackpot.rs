@@ -0,0 +1,251 @@
+use std::collections::HashMap;
+
+use near_sdk::json_types::U64;
+use near_sdk::Timestamp;
+
+use crate::*;
+
+/// Rounds of jackpot lotteries with the same token and entry fee
+#[derive(BorshSerialize, BorshDeserialize, Serialize, Deserialize, PartialEq, Clone, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct JackpotSeries {
+    pub lottery_token_id: AccountId,
+    pub entry_fee: U128
+}
+
+/// Jackpot of series rolled over between rounds
+#[derive(BorshSerialize, BorshDeserialize, Default)]
+pub struct Jackpot {
+    pub balance: Balance,
+    /// drawn rounds
+    pub rounds_num: u64
+}
+
+/// Drawn jackpot round
+#[derive(BorshSerialize, BorshDeserialize, Serialize, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct JackpotRound {
+    pub round: U64,
+    pub lottery_id: U64,
+    pub entries_num: u32,
+    /// entries of the round minus contract fees
+    pub round_pool: U128,
+    /// jackpot with round pool at the draw
+    pub jackpot: U128,
+    pub drawn_number: u32,
+    pub winner: Option<AccountId>,
+    /// jackpot left for the next round
+    pub rolled_over: U128,
+    pub timestamp: U64
+}
+
+/// Round of progressive jackpot.
+/// Every entrant holds a ticket number equal to its entry index. A number from `[0, ticket_range)` is drawn
+/// after draw time, if it matches a ticket the holder wins the whole jackpot, otherwise jackpot rolls over
+#[derive(BorshSerialize, BorshDeserialize, Serialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct JackpotLottery {
+    pub id: LotteryId,
+    pub lottery_token_id: AccountId,
+    pub lottery_status: LotteryStatus,
+    /// A list of account_ids in this lottery
+    pub entries: LotteryEntries,
+    /// Amount to participate a lottery
+    pub entry_fee: Balance,
+    /// Current amount deposited
+    pub current_pool: Balance,
+    /// Block timestamp after which entries are closed and lottery can be drawn
+    pub draw_at: Timestamp,
+    /// Ticket numbers range in effect when lottery was created
+    pub ticket_range: u32,
+    pub drawn_number: Option<u32>,
+    pub winner: Option<AccountId>,
+    pub refferal_transfered: Balance,
+    /// Referral rewards paid on entry: entrant -> reward
+    pub referrals: HashMap<AccountId, Balance>,
+    /// Fee parameters in effect when lottery was created
+    pub fees: LotteryFees,
+    /// Inputs and result of the draw
+    pub draw_record: Option<DrawRecord>
+}
+
+impl JackpotLottery {
+    pub fn new(
+        id: LotteryId,
+        lottery_token_id: AccountId,
+        entry_fee: Balance,
+        draw_at: Timestamp,
+        ticket_range: u32,
+        fees: LotteryFees
+    ) -> Self {
+        assert!(entry_fee > 0, "entry_fee cannot be zero");
+        assert!(draw_at > env::block_timestamp(), "Draw time must be in the future");
+        Self {
+            id,
+            lottery_token_id,
+            lottery_status: LotteryStatus::Active,
+            entries: LotteryEntries::new(id),
+            entry_fee,
+            current_pool: 0,
+            draw_at,
+            ticket_range,
+            drawn_number: None,
+            winner: None,
+            refferal_transfered: 0,
+            referrals: HashMap::new(),
+            fees,
+            draw_record: None
+        }
+    }
+
+    pub fn series(&self) -> JackpotSeries {
+        JackpotSeries {
+            lottery_token_id: self.lottery_token_id.clone(),
+            entry_fee: U128(self.entry_fee)
+        }
+    }
+
+    pub fn is_draw_time_reached(&self) -> bool {
+        env::block_timestamp() >= self.draw_at
+    }
+
+    pub fn assert_is_finished(&self) {
+        assert_eq!(self.lottery_status, LotteryStatus::Finished, "Lottery is not finished");
+        assert!(self.drawn_number.is_some());
+    }
+
+    /// Entries are closed at draw time, lottery waits for `draw`
+    pub fn update(&mut self) -> LotteryStatus {
+        if self.lottery_status == LotteryStatus::Active && self.is_draw_time_reached() {
+            self.lottery_status = LotteryStatus::Drawing;
+        }
+        self.lottery_status
+    }
+
+    /// Draws ticket number after draw time
+    pub fn draw(&mut self) -> LotteryStatus {
+        assert!(self.is_draw_time_reached(), "Draw is available after draw time");
+        assert_eq!(self.lottery_status, LotteryStatus::Drawing, "Lottery is not waiting for draw");
+        self.lottery_status = LotteryStatus::Finished;
+        self.set_winner(env::random_seed_array());
+        self.lottery_status
+    }
+
+    pub fn add_refferal_transfered(&mut self, account_id: &AccountId, amount: Balance) {
+        self.refferal_transfered += amount;
+        *self.referrals.entry(account_id.clone()).or_default() += amount;
+    }
+
+    /// Removes entry of active lottery, the last entrant takes its ticket number.
+    /// Returns refund: entry fee minus referral reward already paid for that entry
+    pub fn leave(&mut self, account_id: &AccountId) -> Balance {
+        assert_eq!(self.lottery_status, LotteryStatus::Active, "Lottery is not active");
+        self.entries.remove(account_id).expect("Not entered");
+        self.current_pool -= self.entry_fee;
+
+        let referral_reward = self.referrals.remove(account_id).unwrap_or_default();
+        self.refferal_transfered -= referral_reward;
+        self.entry_fee - referral_reward
+    }
+
+    /// Round waiting for draw can be cancelled too, so a round which cannot be drawn doesn't block its series
+    pub fn cancel(&mut self) {
+        assert!(
+            matches!(self.update(), LotteryStatus::Active | LotteryStatus::Drawing),
+            "Lottery is not active"
+        );
+        self.lottery_status = LotteryStatus::Cancelled;
+    }
+
+    /// Draw lottery entry
+    pub fn draw_enter(&mut self, account_id: &AccountId, amount: Balance) -> LotteryStatus {
+        assert_eq!(self.update(), LotteryStatus::Active, "Lottery entries are closed");
+        assert_eq!(
+            amount, self.entry_fee,
+            "Supplied: {}, but Required amount to paticipate is: {}",
+            self.entry_fee, amount
+        );
+        assert!(self.entries.len() < self.ticket_range, "All jackpot tickets are taken");
+        self.entries.push(account_id, 1);
+        self.current_pool += amount;
+        self.lottery_status
+    }
+
+    fn set_winner(&mut self, random_seed: [u8; 32]) {
+        let tickets_num = self.entries.tickets_num();
+        let drawn_number = draw_ticket_number(random_seed, self.id, self.ticket_range);
+        let winner_indices = if drawn_number < tickets_num {
+            vec![drawn_number]
+        } else {
+            vec![]
+        };
+
+        self.drawn_number = Some(drawn_number);
+        // every entry is one ticket, so only the matching entry is read
+        self.winner = winner_indices.first().map(|index| self.entries.get(*index).account_id);
+        let mut draw_record = DrawRecord::new(
+            self.id,
+            LotteryType::JackpotLottery,
+            random_seed,
+            self.entries.hash(),
+            self.entries.len(),
+            tickets_num,
+            vec![],
+            winner_indices
+        );
+        draw_record.ticket_range = Some(self.ticket_range);
+        self.draw_record = Some(draw_record);
+    }
+}
+
+impl Contract {
+    pub (crate) fn internal_jackpot(&self, series: &JackpotSeries) -> Jackpot {
+        self.jackpots.get(series).unwrap_or_default()
+    }
+
+    /// Only one round of series is open at a time, so jackpot rolls over in round order
+    pub (crate) fn internal_open_jackpot_round(&mut self, series: &JackpotSeries, lottery_id: LotteryId) {
+        assert!(!self.open_jackpot_rounds.contains_key(series), "Jackpot round is waiting for draw");
+        self.open_jackpot_rounds.insert(series, &lottery_id);
+    }
+
+    /// Adds round pool to the jackpot of series and pays it to the round winner.
+    /// Returns drawn round
+    pub (crate) fn internal_settle_jackpot(
+        &mut self,
+        lottery: &JackpotLottery,
+        round_pool: Balance,
+        claim_mode: bool
+    ) -> JackpotRound {
+        let series = lottery.series();
+        let mut jackpot = self.internal_jackpot(&series);
+        jackpot.balance += round_pool;
+        jackpot.rounds_num += 1;
+
+        let prize = jackpot.balance;
+        if let Some(winner) = &lottery.winner {
+            self.internal_reward(winner, &lottery.lottery_token_id, prize, claim_mode);
+            jackpot.balance = 0;
+        } else {
+            log!(
+                "Jackpot ( {} yocto{} ) rolled over to round {}",
+                prize, match_token_id(&lottery.lottery_token_id), jackpot.rounds_num + 1
+            );
+        }
+
+        let round = JackpotRound {
+            round: U64(jackpot.rounds_num),
+            lottery_id: U64(lottery.id),
+            entries_num: lottery.entries.len(),
+            round_pool: U128(round_pool),
+            jackpot: U128(prize),
+            drawn_number: lottery.drawn_number.expect("Ticket number was not drawn"),
+            winner: lottery.winner.clone(),
+            rolled_over: U128(jackpot.balance),
+            timestamp: U64(env::block_timestamp())
+        };
+        self.jackpot_rounds.insert(&(series.clone(), jackpot.rounds_num), &round);
+        self.jackpots.insert(&series, &jackpot);
+        round
+    }
+}
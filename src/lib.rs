@@ -1,29 +1,57 @@
+use std::collections::HashMap;
+
 use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
-use near_sdk::collections::{LazyOption, UnorderedMap, UnorderedSet};
+use near_sdk::collections::{LazyOption, LookupMap, UnorderedMap, UnorderedSet, Vector};
 use near_sdk::json_types::U128;
 use near_sdk::serde::{Deserialize, Serialize};
 use near_sdk::{
-    assert_one_yocto, env, log, near_bindgen, AccountId, Balance, BorshStorageKey,
-    PanicOnDefault, Promise,
+    assert_one_yocto, env, log, near_bindgen, AccountId, Balance, BlockHeight, BorshStorageKey,
+    CryptoHash, PanicOnDefault, Promise,
 };
 
-use rand::rngs::StdRng;
-use rand::{Rng, SeedableRng};
 
+mod cashback;
 mod config;
+mod draw;
+mod entropy;
+mod fees;
 mod fungible_token;
+mod jackpot;
+mod lotto;
 mod lottery;
 mod lottery_config;
+mod lottery_entries;
 mod big_lottery;
+mod non_fungible_token;
 mod simple_lottery;
+mod timed_lottery;
+mod pause;
+mod prize_table;
+mod payouts;
+mod raffle;
+mod roles;
+mod upgrade;
 mod views;
 mod utils;
 
+use crate::cashback::*;
 use crate::config::*;
+use crate::draw::*;
+use crate::entropy::*;
+use crate::fees::*;
+use crate::jackpot::*;
+use crate::lotto::*;
 use crate::lottery::*;
 use crate::lottery_config::*;
+use crate::lottery_entries::*;
 use crate::big_lottery::*;
 use crate::simple_lottery::*;
+use crate::timed_lottery::*;
+use crate::pause::*;
+use crate::prize_table::*;
+use crate::raffle::*;
+use crate::roles::*;
+use crate::upgrade::write_state_version;
 use crate::utils::*;
 
 pub type LotteryId = u64;
@@ -35,24 +63,79 @@ enum StorageKey {
     Fees,
     Lotteries,
     Cashbacks,
-    WhitelistedTokens
+    WhitelistedTokens,
+    FailedPayouts,
+    Claimable,
+    FeesLocked,
+    FeesWithdrawals,
+    Cancellations,
+    PauseState,
+    OwnershipTransfers,
+    Roles,
+    PendingFeesConfig,
+    Draws,
+    LotteryEntries { lottery_id: LotteryId },
+    LotteryEntryPositions { lottery_id: LotteryId },
+    Jackpots,
+    JackpotRounds,
+    LottoPicks { lottery_id: LotteryId },
+    LottoRollovers,
+    FailedNftTransfers,
+    Settlements,
+    OpenJackpotRounds,
+    LotteryTicketIndex { lottery_id: LotteryId },
+    OpenLottoRounds
 }
 
 #[near_bindgen]
 #[derive(BorshDeserialize, BorshSerialize, PanicOnDefault)]
 pub struct Contract {
-    pub config: LazyOption<Config>,
-    pub lotteries: UnorderedMap<LotteryId, Lottery>,
+    pub config: LazyOption<VersionedConfig>,
+    pub lotteries: UnorderedMap<LotteryId, VersionedLottery>,
     /// a list of allowed fungible tokens
     pub whitelisted_tokens: UnorderedSet<AccountId>,
     /// contract fees balance
     pub fees: UnorderedMap<AccountId, Balance>,
     /// counter for lotteries
     pub next_lottery_id: LotteryId,
-    /// buffer for accounts to cashback transfer
-    pub cashback_accounts: UnorderedMap<AccountId, StoredCashback>,
+    /// queue of lotteries cashbacks waiting for transfer
+    pub cashbacks: UnorderedMap<LotteryId, StoredCashback>,
     /// lotteries config
-    pub lotteries_config: LazyOption<LotteryConfig>
+    pub lotteries_config: LazyOption<VersionedLotteryConfig>,
+    /// failed transfers stored for retry: account -> ( token -> amount )
+    pub failed_payouts: UnorderedMap<AccountId, HashMap<AccountId, Balance>>,
+    /// rewards credited in claim mode: account -> ( token -> amount )
+    pub claimable: UnorderedMap<AccountId, HashMap<AccountId, Balance>>,
+    /// fees withdrawals waiting for transfer callback
+    pub fees_locked: UnorderedMap<AccountId, Balance>,
+    /// history of confirmed fees withdrawals
+    pub fees_withdrawals: Vector<FeesWithdrawal>,
+    /// lotteries cancelled by owner
+    pub cancellations: UnorderedMap<LotteryId, LotteryCancellation>,
+    /// paused lottery entries
+    pub pause_state: LazyOption<PauseState>,
+    /// history of contract ownership transfers
+    pub ownership_transfers: Vector<OwnershipTransfer>,
+    /// admin roles granted by owner
+    pub roles: UnorderedMap<AccountId, Vec<Role>>,
+    /// fees config change waiting for timelock
+    pub pending_fees_config: LazyOption<PendingFeesConfig>,
+    /// draw records of finished lotteries
+    pub draws: UnorderedMap<LotteryId, DrawRecord>,
+    /// jackpots rolled over between rounds of jackpot lotteries
+    pub jackpots: UnorderedMap<JackpotSeries, Jackpot>,
+    /// drawn jackpot rounds: ( series, round ) -> round
+    pub jackpot_rounds: LookupMap<(JackpotSeries, u64), JackpotRound>,
+    /// lotto prize pool rolled over between rounds of series
+    pub lotto_rollovers: UnorderedMap<LottoSeries, Balance>,
+    /// failed NFT transfers stored for retry: account -> NFTs
+    pub failed_nft_transfers: UnorderedMap<AccountId, Vec<RafflePrize>>,
+    /// queue of finished and closed lotteries entries waiting for payouts and release
+    pub settlements: UnorderedMap<LotteryId, StoredSettlement>,
+    /// jackpot rounds not drawn yet: series -> lottery
+    pub open_jackpot_rounds: LookupMap<JackpotSeries, LotteryId>,
+    /// lotto rounds not drawn yet: series -> lottery
+    pub open_lotto_rounds: LookupMap<LottoSeries, LotteryId>
 }
 
 #[near_bindgen]
@@ -71,14 +154,33 @@ impl Contract {
             big_lottery_num_participants
         );
         lottery_config.assert_valid();
+        lottery_config.assert_valid_prize_tables(config.referral_ratio);
+        write_state_version();
         Self {
-            config: LazyOption::new(StorageKey::Config, Some(&config)),
+            config: LazyOption::new(StorageKey::Config, Some(&config.into())),
             whitelisted_tokens: UnorderedSet::new(StorageKey::WhitelistedTokens),
             lotteries: UnorderedMap::new(StorageKey::Lotteries),
             fees: UnorderedMap::new(StorageKey::Fees),
             next_lottery_id: 0,
-            cashback_accounts: UnorderedMap::new(StorageKey::Cashbacks),
-            lotteries_config: LazyOption::new(StorageKey::LotteryConfig, Some(&lottery_config))
+            cashbacks: UnorderedMap::new(StorageKey::Cashbacks),
+            lotteries_config: LazyOption::new(StorageKey::LotteryConfig, Some(&lottery_config.into())),
+            failed_payouts: UnorderedMap::new(StorageKey::FailedPayouts),
+            claimable: UnorderedMap::new(StorageKey::Claimable),
+            fees_locked: UnorderedMap::new(StorageKey::FeesLocked),
+            fees_withdrawals: Vector::new(StorageKey::FeesWithdrawals),
+            cancellations: UnorderedMap::new(StorageKey::Cancellations),
+            pause_state: LazyOption::new(StorageKey::PauseState, Some(&PauseState::default())),
+            ownership_transfers: Vector::new(StorageKey::OwnershipTransfers),
+            roles: UnorderedMap::new(StorageKey::Roles),
+            pending_fees_config: LazyOption::new(StorageKey::PendingFeesConfig, None),
+            draws: UnorderedMap::new(StorageKey::Draws),
+            jackpots: UnorderedMap::new(StorageKey::Jackpots),
+            jackpot_rounds: LookupMap::new(StorageKey::JackpotRounds),
+            lotto_rollovers: UnorderedMap::new(StorageKey::LottoRollovers),
+            failed_nft_transfers: UnorderedMap::new(StorageKey::FailedNftTransfers),
+            settlements: UnorderedMap::new(StorageKey::Settlements),
+            open_jackpot_rounds: LookupMap::new(StorageKey::OpenJackpotRounds),
+            open_lotto_rounds: LookupMap::new(StorageKey::OpenLottoRounds)
         }
     }
 }
@@ -87,16 +189,20 @@ impl Contract {
 mod tests {
     use super::*;
     use crate::fungible_token::TokenReceiverMsg;
+    use crate::non_fungible_token::NftReceiverMsg;
+    use crate::views::{LotteryResult, JackpotLotteryResult, LottoResult};
     use near_contract_standards::fungible_token::receiver::FungibleTokenReceiver;
+    use near_contract_standards::non_fungible_token::core::NonFungibleTokenReceiver;
     use near_sdk::ONE_NEAR;
+    use near_sdk::json_types::U64;
     const ONE_USN:Balance = 1_000_000_000_000_000_000;
     // use near_contract_standards::{
     //     storage_management::StorageManagement,
     //     non_fungible_token::core::NonFungibleTokenReceiver,
     //     fungible_token::receiver::FungibleTokenReceiver,
     // };
-    use near_sdk::test_utils::{VMContextBuilder};
-    use near_sdk::{testing_env, ONE_YOCTO};
+    use near_sdk::test_utils::{accounts, VMContextBuilder};
+    use near_sdk::{testing_env, PromiseResult, RuntimeFeesConfig, VMConfig, ONE_YOCTO};
 
     fn user(user: &str) -> AccountId {
         (user.to_owned() + ".sub1.near")
@@ -134,9 +240,12 @@ mod tests {
             contract_fee_ratio: 1000, //10%
             treasury_ratio: 0, //0% from contract_fee_ratio
             investor_ratio: 4000, //40% from contract_fee_ratio
+            referral_ratio: ONE_PERCENT_RATIO,
             treasury: user("treasury"),
             investor: user("investor"),
             accepted_subs: "sub.near".to_string(),
+            pending_owner_id: None,
+            fees_timelock: U64(0),
         };
         config.assert_valid();
         config
@@ -176,6 +285,33 @@ mod tests {
         );
     }
 
+    fn callback_env(
+        context: &mut VMContextBuilder,
+        promise_result: PromiseResult
+    ) {
+        testing_env!(
+            context
+                .predecessor_account_id(accounts(0))
+                .current_account_id(accounts(0))
+                .build(),
+            VMConfig::test(),
+            RuntimeFeesConfig::test(),
+            Default::default(),
+            vec![promise_result]
+        );
+    }
+
+    /// NEAR transfers created in current context
+    fn near_transfers() -> Vec<(AccountId, Balance)> {
+        near_sdk::test_utils::get_created_receipts()
+            .into_iter()
+            .filter_map(|receipt| match receipt.actions.as_slice() {
+                [near_sdk::mock::VmAction::Transfer { deposit }] => Some((receipt.receiver_id, *deposit)),
+                _ => None,
+            })
+            .collect()
+    }
+
     fn change_subs(
         contract: &mut Contract,
         context: &mut VMContextBuilder
@@ -201,7 +337,9 @@ mod tests {
             &TokenReceiverMsg::DrawEnter {
                 num_participants: lottery_num_participants,
                 lottery_type,
-                referrer_id
+                referrer_id,
+                secret_hash: None,
+                tickets: None
             }
         )
         .expect("Parse repay FT error");
@@ -259,7 +397,7 @@ mod tests {
             .build()
         );
         let prev_lotteries_num = contract.get_lotteries_num();
-        let lottery_id = contract.draw_near_enter(lottery_type, lottery_num_participants, referrer_id);
+        let lottery_id = contract.draw_near_enter(lottery_type, lottery_num_participants, referrer_id, None, None);
 
         if is_last {
             assert!(contract.get_lottery(lottery_id).is_none());
@@ -682,5 +820,2023 @@ mod tests {
         //7200000000000000000000000 - 40%
         //10800000000000000000000000 - 60%
     }
+    #[test]
+    fn test_failed_payout_retry() {
+        let (mut contract, mut context) = contract_context();
+
+        callback_env(&mut context, PromiseResult::Failed);
+        assert!(!contract.after_near_transfer(user("winner"), U128(ONE_NEAR)));
+        callback_env(&mut context, PromiseResult::Failed);
+        assert!(!contract.after_ft_transfer(user("winner"), token("usdt.near"), U128(ONE_USN)));
+        callback_env(&mut context, PromiseResult::Successful(vec![]));
+        assert!(contract.after_near_transfer(user("winner"), U128(ONE_NEAR)));
+
+        let failed = contract.get_failed_payouts(user("winner"));
+        assert_eq!(failed.len(), 2);
+        assert!(failed.contains(&(near(), U128(ONE_NEAR))));
+
+        testing_env!(context
+            .predecessor_account_id(user("winner"))
+            .attached_deposit(ONE_YOCTO)
+            .build()
+        );
+        contract.retry_failed_payout(near());
+        assert_eq!(
+            contract.get_failed_payouts(user("winner")),
+            vec![(token("usdt.near"), U128(ONE_USN))]
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "No failed payouts found")]
+    fn test_retry_without_failed_payout() {
+        let (mut contract, mut context) = contract_context();
+        testing_env!(context
+            .predecessor_account_id(user("winner"))
+            .attached_deposit(ONE_YOCTO)
+            .build()
+        );
+        contract.retry_failed_payout(near());
+    }
+    #[test]
+    fn test_claim_mode() {
+        let (mut contract, mut context) = contract_context();
+        change_subs(&mut contract, &mut context);
+        owner_env(&mut context);
+        contract.set_lottery_type_claim_mode(SIMPLE_LOTTERY.to_string(), true);
+        assert_eq!(
+            contract.get_contract_params().config.claim_mode_lottery_types,
+            vec![LotteryType::SimpleLottery]
+        );
+
+        for index in 1..=5 {
+            enter_lottery(
+                &mut contract, 
+                &mut context, 
+                &user(&format!("user{}", index)), 
+                SIMPLE_LOTTERY.to_string(), 
+                U128(ONE_NEAR), 
+                5u32,
+                index == 1,
+                index == 5,
+                None
+            );
+        }
+
+        let winners = (1..=5)
+            .map(|index| user(&format!("user{}", index)))
+            .filter(|account| !contract.get_claimable(account.clone()).is_empty())
+            .collect::<Vec<_>>();
+        assert_eq!(winners.len(), 1);
+        let winner = winners[0].clone();
+        let reward = 5 * ONE_NEAR - ratio(5 * ONE_NEAR, 1000);
+        assert_eq!(contract.get_claimable(winner.clone()), vec![(near(), U128(reward))]);
+
+        testing_env!(context
+            .predecessor_account_id(winner.clone())
+            .attached_deposit(ONE_YOCTO)
+            .build()
+        );
+        contract.claim_all();
+        assert!(contract.get_claimable(winner).is_empty());
+    }
+
+    #[test]
+    #[should_panic(expected = "Nothing to claim")]
+    fn test_claim_without_reward() {
+        let (mut contract, mut context) = contract_context();
+        testing_env!(context
+            .predecessor_account_id(user("user"))
+            .attached_deposit(ONE_YOCTO)
+            .build()
+        );
+        contract.claim(near());
+    }
+    #[test]
+    fn test_withdraw_fees() {
+        let (mut contract, mut context) = contract_context();
+        contract.deposit_fees(&near(), ONE_NEAR);
+
+        owner_env(&mut context);
+        contract.withdraw_fees(near(), U128(ONE_NEAR / 2), user("treasury"));
+        assert_eq!(contract.get_contract_params().fees_locked, vec![(near(), U128(ONE_NEAR / 2))]);
+
+        callback_env(&mut context, PromiseResult::Failed);
+        assert!(!contract.after_fees_withdraw(near(), U128(ONE_NEAR / 2), user("treasury")));
+        let params = contract.get_contract_params();
+        assert!(params.fees_locked.is_empty());
+        assert_eq!(params.fees_collected, vec![(near(), U128(ONE_NEAR))]);
+        assert!(contract.get_fees_withdrawals(None, None).is_empty());
+
+        owner_env(&mut context);
+        contract.withdraw_fees(near(), U128(ONE_NEAR / 2), user("treasury"));
+        callback_env(&mut context, PromiseResult::Successful(vec![]));
+        assert!(contract.after_fees_withdraw(near(), U128(ONE_NEAR / 2), user("treasury")));
+        assert_eq!(contract.get_contract_params().fees_collected, vec![(near(), U128(ONE_NEAR / 2))]);
+        let withdrawals = contract.get_fees_withdrawals(None, None);
+        assert_eq!(withdrawals.len(), 1);
+        assert_eq!(withdrawals[0].receiver_id, user("treasury"));
+    }
+
+    #[test]
+    #[should_panic(expected = "Not enough fees to withdraw")]
+    fn test_withdraw_locked_fees() {
+        let (mut contract, mut context) = contract_context();
+        contract.deposit_fees(&near(), ONE_NEAR);
+
+        owner_env(&mut context);
+        contract.withdraw_fees(near(), U128(ONE_NEAR / 2), user("treasury"));
+        contract.withdraw_fees(near(), U128(ONE_NEAR), user("treasury"));
+    }
+    #[test]
+    fn test_cashback_queue() {
+        let entry_fee = ONE_NEAR;
+        let (mut contract, mut context) = contract_context();
+        change_subs(&mut contract, &mut context);
+
+        for index in 0..50 {
+            enter_lottery(
+                &mut contract, 
+                &mut context, 
+                &user(&format!("user{}", index)), 
+                BIG_LOTTERY.to_string(), 
+                U128(entry_fee), 
+                50u32,
+                index == 0,
+                index == 49,
+                None
+            );
+        }
+
+        // cashback tier takes the rest of entries
+        assert!(contract.get_pending_cashbacks(None, None).is_empty());
+        let pending = contract.get_pending_settlements(None, None);
+        assert_eq!(pending.len(), 1);
+        assert_eq!((pending[0].pending_amount, pending[0].entries_num), (U128(25 * entry_fee / 2), 50));
+        assert_eq!(contract.get_cashback_liabilities(), vec![(near(), U128(25 * entry_fee / 2))]);
+
+        testing_env!(context.predecessor_account_id(user("keeper")).build());
+        assert_eq!(contract.process_cashbacks(Some(10)), 10);
+        let pending = contract.get_pending_settlements(None, None);
+        assert_eq!(pending[0].entries_num, 40);
+        assert_eq!(
+            contract.get_cashback_liabilities(),
+            vec![(near(), pending[0].pending_amount)]
+        );
+
+        // gas bounded batches
+        let mut batches = 0;
+        while !contract.get_pending_settlements(None, None).is_empty() {
+            testing_env!(context.predecessor_account_id(user("keeper")).build());
+            assert!(contract.process_cashbacks(None) > 0);
+            batches += 1;
+        }
+        assert!(batches > 1);
+        assert!(contract.get_cashback_liabilities().is_empty());
+        assert!(contract.get_contract_params().cashback_accounts_num.is_empty());
+    }
+    #[test]
+    fn test_expired_lottery_refund() {
+        let (mut contract, mut context) = contract_context();
+        change_subs(&mut contract, &mut context);
+        owner_env(&mut context);
+        contract.set_lottery_expiration(
+            SIMPLE_LOTTERY.to_string(), 
+            Some(LotteryExpiration::Timestamp(U64(1000)))
+        );
+
+        enter_lottery(
+            &mut contract, 
+            &mut context, 
+            &user("user1"), 
+            SIMPLE_LOTTERY.to_string(), 
+            U128(ONE_NEAR), 
+            5u32,
+            true,
+            false,
+            None
+        );
+        enter_lottery(
+            &mut contract, 
+            &mut context, 
+            &user("user2"), 
+            SIMPLE_LOTTERY.to_string(), 
+            U128(ONE_NEAR), 
+            5u32,
+            false,
+            false,
+            Some(user("referrer"))
+        );
+        assert_eq!(
+            contract.get_lottery(0).unwrap().expires_at,
+            Some(LotteryExpiration::Timestamp(U64(1000)))
+        );
+
+        // entrant after expiration gets into a new lottery
+        context.block_timestamp(1000);
+        let lottery_id = {
+            testing_env!(context
+                .predecessor_account_id(user("user3"))
+                .attached_deposit(ONE_NEAR)
+                .build()
+            );
+            contract.draw_near_enter(SIMPLE_LOTTERY.to_string(), 5, None, None, None)
+        };
+        assert_eq!(lottery_id, 1);
+
+        testing_env!(context.predecessor_account_id(user("keeper")).attached_deposit(0).build());
+        contract.close_expired_lottery(0);
+        assert!(contract.get_lottery(0).is_none());
+
+        // no contract fees to cover referral reward, so it is deducted from referred entrant
+        let referral_reward = ratio(ONE_NEAR, ONE_PERCENT_RATIO);
+        let transfers = near_transfers();
+        assert!(transfers.contains(&(user("user1"), ONE_NEAR)));
+        assert!(transfers.contains(&(user("user2"), ONE_NEAR - referral_reward)));
+    }
+
+    #[test]
+    #[should_panic(expected = "Lottery is not expired")]
+    fn test_close_not_expired_lottery() {
+        let (mut contract, mut context) = contract_context();
+        change_subs(&mut contract, &mut context);
+        enter_lottery(
+            &mut contract, 
+            &mut context, 
+            &user("user1"), 
+            SIMPLE_LOTTERY.to_string(), 
+            U128(ONE_NEAR), 
+            5u32,
+            true,
+            false,
+            None
+        );
+        contract.close_expired_lottery(0);
+    }
+    #[test]
+    fn test_leave_lottery() {
+        let (mut contract, mut context) = contract_context();
+        change_subs(&mut contract, &mut context);
+        enter_lottery(
+            &mut contract, 
+            &mut context, 
+            &user("user1"), 
+            SIMPLE_LOTTERY.to_string(), 
+            U128(ONE_NEAR), 
+            5u32,
+            true,
+            false,
+            None
+        );
+        enter_lottery(
+            &mut contract, 
+            &mut context, 
+            &user("user2"), 
+            SIMPLE_LOTTERY.to_string(), 
+            U128(ONE_NEAR), 
+            5u32,
+            false,
+            false,
+            Some(user("referrer"))
+        );
+
+        testing_env!(context
+            .predecessor_account_id(user("user2"))
+            .attached_deposit(ONE_YOCTO)
+            .build()
+        );
+        contract.leave_lottery(0);
+        let referral_reward = ratio(ONE_NEAR, ONE_PERCENT_RATIO);
+        assert_eq!(near_transfers(), vec![(user("user2"), ONE_NEAR - referral_reward)]);
+
+        let lottery = contract.get_lottery(0).unwrap();
+        assert_eq!(lottery.entries, vec![user("user1")]);
+        assert_eq!(lottery.current_pool, U128(ONE_NEAR));
+
+        testing_env!(context
+            .predecessor_account_id(user("user1"))
+            .attached_deposit(ONE_YOCTO)
+            .build()
+        );
+        contract.leave_lottery(0);
+        assert_eq!(near_transfers(), vec![(user("user1"), ONE_NEAR)]);
+        assert!(contract.get_lottery(0).is_none());
+    }
+
+    #[test]
+    #[should_panic(expected = "Not entered")]
+    fn test_leave_lottery_not_entered() {
+        let (mut contract, mut context) = contract_context();
+        change_subs(&mut contract, &mut context);
+        enter_lottery(
+            &mut contract, 
+            &mut context, 
+            &user("user1"), 
+            SIMPLE_LOTTERY.to_string(), 
+            U128(ONE_NEAR), 
+            5u32,
+            true,
+            false,
+            None
+        );
+        testing_env!(context
+            .predecessor_account_id(user("user2"))
+            .attached_deposit(ONE_YOCTO)
+            .build()
+        );
+        contract.leave_lottery(0);
+    }
+    #[test]
+    fn test_cancel_lottery() {
+        let (mut contract, mut context) = contract_context();
+        change_subs(&mut contract, &mut context);
+        contract.deposit_fees(&near(), ONE_NEAR);
+        enter_lottery(
+            &mut contract, 
+            &mut context, 
+            &user("user1"), 
+            SIMPLE_LOTTERY.to_string(), 
+            U128(ONE_NEAR), 
+            5u32,
+            true,
+            false,
+            Some(user("referrer"))
+        );
+        enter_lottery(
+            &mut contract, 
+            &mut context, 
+            &user("user2"), 
+            SIMPLE_LOTTERY.to_string(), 
+            U128(ONE_NEAR), 
+            5u32,
+            false,
+            false,
+            None
+        );
+
+        owner_env(&mut context);
+        contract.cancel_lottery(0, "bad config".to_string());
+
+        // referral reward is covered by contract fees
+        let referral_reward = ratio(ONE_NEAR, ONE_PERCENT_RATIO);
+        let transfers = near_transfers();
+        assert!(transfers.contains(&(user("user1"), ONE_NEAR)));
+        assert!(transfers.contains(&(user("user2"), ONE_NEAR)));
+        assert_eq!(
+            contract.get_contract_params().fees_collected,
+            vec![(near(), U128(ONE_NEAR - referral_reward))]
+        );
+
+        assert!(contract.get_lottery(0).is_none());
+        let cancellation = contract.get_cancellation(0).unwrap();
+        assert_eq!(cancellation.reason, "bad config");
+        assert_eq!((cancellation.entries_num, cancellation.tickets_num), (2, 2));
+        assert_eq!(
+            cancellation.entries_hash,
+            hash_entries(&[
+                Entry { account_id: user("user1"), tickets: 1 },
+                Entry { account_id: user("user2"), tickets: 1 }
+            ]).into()
+        );
+
+        testing_env!(context
+            .predecessor_account_id(user("user1"))
+            .attached_deposit(ONE_NEAR)
+            .build()
+        );
+        assert_eq!(contract.draw_near_enter(SIMPLE_LOTTERY.to_string(), 5, None, None, None), 1);
+    }
+
+    #[test]
+    fn test_cancel_lottery_refunds_in_batches() {
+        let (mut contract, mut context) = contract_context();
+        change_subs(&mut contract, &mut context);
+        for index in 0..10 {
+            enter_lottery(
+                &mut contract, 
+                &mut context, 
+                &user(&format!("user{}", index)), 
+                BIG_LOTTERY.to_string(), 
+                U128(ONE_NEAR), 
+                50u32,
+                index == 0,
+                false,
+                if index == 0 { Some(user("referrer")) } else { None }
+            );
+        }
+
+        owner_env(&mut context);
+        contract.cancel_lottery(0, "bad config".to_string());
+        assert!(contract.get_lottery(0).is_none());
+        assert!(near_transfers().is_empty());
+        let cancellation = contract.get_cancellation(0).unwrap();
+        assert_eq!((cancellation.entries_num, cancellation.tickets_num), (10, 10));
+
+        // no contract fees to cover referral reward, so it is deducted from referred entrant
+        let referral_reward = ratio(ONE_NEAR, ONE_PERCENT_RATIO);
+        let pending = contract.get_pending_settlements(None, None);
+        assert_eq!((pending[0].pending_amount, pending[0].entries_num), (U128(10 * ONE_NEAR - referral_reward), 10));
+        assert_eq!(contract.get_cashback_liabilities(), vec![(near(), U128(10 * ONE_NEAR - referral_reward))]);
+
+        testing_env!(context.predecessor_account_id(user("keeper")).attached_deposit(0).build());
+        assert_eq!(contract.process_cashbacks(Some(4)), 4);
+        assert_eq!(near_transfers().len(), 4);
+        assert_eq!(contract.get_pending_settlements(None, None)[0].entries_num, 6);
+
+        testing_env!(context.predecessor_account_id(user("keeper")).build());
+        assert_eq!(contract.process_cashbacks(None), 6);
+        assert!(near_transfers().contains(&(user("user0"), ONE_NEAR - referral_reward)));
+        assert!(contract.get_pending_settlements(None, None).is_empty());
+        assert!(contract.get_cashback_liabilities().is_empty());
+    }
+
+    #[test]
+    #[should_panic(expected = "Requires ConfigManager role")]
+    fn test_cancel_lottery_without_role() {
+        let (mut contract, mut context) = contract_context();
+        change_subs(&mut contract, &mut context);
+        enter_lottery(
+            &mut contract, 
+            &mut context, 
+            &user("user1"), 
+            SIMPLE_LOTTERY.to_string(), 
+            U128(ONE_NEAR), 
+            5u32,
+            true,
+            false,
+            None
+        );
+        testing_env!(context
+            .predecessor_account_id(user("user1"))
+            .attached_deposit(ONE_YOCTO)
+            .build()
+        );
+        contract.cancel_lottery(0, "".to_string());
+    }
+    #[test]
+    fn test_pause_entries() {
+        let (mut contract, mut context) = contract_context();
+        change_subs(&mut contract, &mut context);
+        owner_env(&mut context);
+        contract.pause(None, Some(BIG_LOTTERY.to_string()));
+        assert_eq!(contract.get_contract_params().pause_state.lottery_types, vec![LotteryType::BigLottery]);
+
+        // other lottery types are not paused
+        enter_lottery(
+            &mut contract, 
+            &mut context, 
+            &user("user1"), 
+            SIMPLE_LOTTERY.to_string(), 
+            U128(ONE_NEAR), 
+            5u32,
+            true,
+            false,
+            None
+        );
+
+        owner_env(&mut context);
+        contract.pause(None, None);
+        assert!(contract.get_contract_params().pause_state.all);
+
+        // refunds keep working while paused
+        testing_env!(context
+            .predecessor_account_id(user("user1"))
+            .attached_deposit(ONE_YOCTO)
+            .build()
+        );
+        contract.leave_lottery(0);
+        assert_eq!(near_transfers(), vec![(user("user1"), ONE_NEAR)]);
+
+        owner_env(&mut context);
+        contract.unpause(None, None);
+        contract.unpause(None, Some(BIG_LOTTERY.to_string()));
+        let pause_state = contract.get_contract_params().pause_state;
+        assert!(!pause_state.all && pause_state.lottery_types.is_empty());
+    }
+
+    #[test]
+    #[should_panic(expected = "Lottery entries are paused")]
+    fn test_paused_token_entry() {
+        let (mut contract, mut context) = contract_context();
+        change_subs(&mut contract, &mut context);
+        owner_env(&mut context);
+        contract.whitelist_token(token("usdt.near"));
+        contract.add_entry_fee(Some(token("usdt.near")), U128(3 * ONE_USN));
+        contract.pause(Some(token("usdt.near")), None);
+
+        enter_lottery_ft(
+            "usdt.near",
+            &mut contract, 
+            &mut context, 
+            &user("user1"), 
+            SIMPLE_LOTTERY.to_string(), 
+            U128(ONE_USN * 3), 
+            6u32,
+            true,
+            false,
+            None
+        );
+    }
+    #[test]
+    fn test_ownership_transfer() {
+        let (mut contract, mut context) = contract_context();
+        owner_env(&mut context);
+        contract.propose_owner(user("dao"));
+        assert_eq!(contract.get_contract_params().config.pending_owner_id, Some(user("dao")));
+
+        testing_env!(context
+            .predecessor_account_id(user("dao"))
+            .attached_deposit(ONE_YOCTO)
+            .build()
+        );
+        contract.accept_ownership();
+
+        let config = contract.get_contract_params().config;
+        assert_eq!(config.owner_id, user("dao"));
+        assert_eq!(config.pending_owner_id, None);
+        assert_eq!(config.ownership_transfers.len(), 1);
+        assert_eq!(config.ownership_transfers[0].previous_owner_id, owner());
+
+        // new owner can use admin methods
+        contract.add_entry_fee(None, U128(ONE_NEAR * 7));
+    }
+
+    #[test]
+    #[should_panic(expected = "Not a proposed owner")]
+    fn test_accept_cancelled_ownership() {
+        let (mut contract, mut context) = contract_context();
+        owner_env(&mut context);
+        contract.propose_owner(user("dao"));
+        contract.cancel_owner_proposal();
+
+        testing_env!(context
+            .predecessor_account_id(user("dao"))
+            .attached_deposit(ONE_YOCTO)
+            .build()
+        );
+        contract.accept_ownership();
+    }
+
+    #[test]
+    fn test_roles() {
+        let (mut contract, mut context) = contract_context();
+        owner_env(&mut context);
+        contract.grant_role(user("pauser"), Role::Pauser);
+        contract.grant_role(user("manager"), Role::TokenManager);
+        assert_eq!(contract.get_role_holders().len(), 2);
+
+        testing_env!(context
+            .predecessor_account_id(user("pauser"))
+            .attached_deposit(ONE_YOCTO)
+            .build()
+        );
+        contract.pause(None, None);
+        assert!(contract.get_contract_params().pause_state.all);
+
+        owner_env(&mut context);
+        contract.revoke_role(user("pauser"), Role::Pauser);
+        assert_eq!(contract.get_role_holders(), vec![(user("manager"), vec![Role::TokenManager])]);
+    }
+
+    #[test]
+    #[should_panic(expected = "Requires ConfigManager role")]
+    fn test_missing_role() {
+        let (mut contract, mut context) = contract_context();
+        owner_env(&mut context);
+        contract.grant_role(user("manager"), Role::TokenManager);
+
+        testing_env!(context
+            .predecessor_account_id(user("manager"))
+            .attached_deposit(ONE_YOCTO)
+            .build()
+        );
+        contract.whitelist_token(user("new_token"));
+        contract.add_entry_fee(Some(user("new_token")), U128(ONE_NEAR));
+    }
+
+    fn fees_update() -> FeesConfigUpdate {
+        FeesConfigUpdate {
+            contract_fee_ratio: None,
+            treasury_ratio: None,
+            investor_ratio: None,
+            referral_ratio: None,
+            treasury: None,
+            investor: None,
+            fees_timelock: None
+        }
+    }
+
+    #[test]
+    fn test_fees_config_timelock() {
+        let (mut contract, mut context) = contract_context();
+        owner_env(&mut context);
+        contract.propose_fees_config(FeesConfigUpdate { fees_timelock: Some(U64(100)), ..fees_update() });
+        contract.apply_fees_config();
+        assert_eq!(contract.get_contract_params().config.fees_timelock, U64(100));
+
+        testing_env!(context.block_timestamp(1000).build());
+        contract.propose_fees_config(FeesConfigUpdate {
+            contract_fee_ratio: Some(500),
+            treasury: Some(user("new_treasury")),
+            ..fees_update()
+        });
+        let params = contract.get_contract_params();
+        assert_eq!(params.pending_fees_config.unwrap().applicable_at, U64(1100));
+        assert_eq!(params.config.contract_fee_ratio, 1000);
+
+        testing_env!(context.block_timestamp(1100).build());
+        contract.apply_fees_config();
+        let params = contract.get_contract_params();
+        assert!(params.pending_fees_config.is_none());
+        assert_eq!(params.config.contract_fee_ratio, 500);
+        assert_eq!(params.config.treasury, user("new_treasury"));
+    }
+
+    #[test]
+    #[should_panic(expected = "Fees config timelock has not passed")]
+    fn test_fees_config_before_timelock() {
+        let (mut contract, mut context) = contract_context();
+        owner_env(&mut context);
+        contract.propose_fees_config(FeesConfigUpdate { fees_timelock: Some(U64(100)), ..fees_update() });
+        contract.apply_fees_config();
+        contract.propose_fees_config(FeesConfigUpdate { investor_ratio: Some(3000), ..fees_update() });
+
+        testing_env!(context.block_timestamp(99).build());
+        contract.apply_fees_config();
+    }
+
+    #[test]
+    #[should_panic(expected = "Contract fee ratio must be greater than referral ratio")]
+    fn test_fees_config_below_referral_ratio() {
+        let (mut contract, mut context) = contract_context();
+        owner_env(&mut context);
+        contract.propose_fees_config(FeesConfigUpdate { contract_fee_ratio: Some(ONE_PERCENT_RATIO), ..fees_update() });
+    }
+
+    #[test]
+    fn test_lottery_fees_snapshot() {
+        let (mut contract, mut context) = contract_context();
+        change_subs(&mut contract, &mut context);
+        for index in 1..5 {
+            enter_lottery(
+                &mut contract, 
+                &mut context, 
+                &user(&format!("user{}", index)), 
+                SIMPLE_LOTTERY.to_string(), 
+                U128(ONE_NEAR), 
+                5u32,
+                index == 1,
+                false,
+                None
+            );
+        }
+
+        owner_env(&mut context);
+        contract.propose_fees_config(FeesConfigUpdate {
+            contract_fee_ratio: Some(2000),
+            referral_ratio: Some(ONE_PERCENT_RATIO * 2),
+            investor: Some(user("new_investor")),
+            ..fees_update()
+        });
+        contract.apply_fees_config();
+        let lottery = contract.get_lottery(0).unwrap();
+        assert_eq!(lottery.fees.contract_fee_ratio, 1000);
+        assert_eq!(lottery.fees.referral_ratio, ONE_PERCENT_RATIO);
+        assert_eq!(lottery.fees.investor, user("investor"));
+        assert_eq!(contract.internal_lottery_fees().referral_ratio, ONE_PERCENT_RATIO * 2);
+
+        enter_lottery(
+            &mut contract, 
+            &mut context, 
+            &user("user5"), 
+            SIMPLE_LOTTERY.to_string(), 
+            U128(ONE_NEAR), 
+            5u32,
+            false,
+            true,
+            None
+        );
+        // 10% contract fees, 40% of them to investor
+        let transfers = near_transfers();
+        assert!(transfers.contains(&(user("investor"), ONE_NEAR * 5 / 10 * 4 / 10)));
+        assert!(transfers.iter().any(|(_, amount)| *amount == ONE_NEAR * 5 * 9 / 10));
+    }
+
+    #[test]
+    fn test_upgrade() {
+        let (contract, mut context) = contract_context();
+        let mut vm_context = context.predecessor_account_id(owner()).build();
+        vm_context.input = vec![0, 1, 2];
+        testing_env!(vm_context);
+        contract.upgrade();
+
+        let receipts = near_sdk::test_utils::get_created_receipts();
+        assert!(matches!(
+            receipts[0].actions.as_slice(),
+            [
+                near_sdk::mock::VmAction::DeployContract { code },
+                near_sdk::mock::VmAction::FunctionCall { function_name, .. }
+            ] if code == &vec![0, 1, 2] && function_name == "migrate"
+        ));
+    }
+
+    #[test]
+    #[should_panic(expected = "Not enough gas attached for upgrade")]
+    fn test_upgrade_without_gas() {
+        let (contract, mut context) = contract_context();
+        let mut vm_context = context
+            .predecessor_account_id(owner())
+            .prepaid_gas(near_sdk::Gas(near_sdk::Gas::ONE_TERA.0 * 10))
+            .build();
+        vm_context.input = vec![0, 1, 2];
+        testing_env!(vm_context);
+        contract.upgrade();
+    }
+
+    #[test]
+    fn test_migrate_from_v0() {
+        use crate::upgrade::*;
+        let mut context = get_context(accounts(0));
+        testing_env!(context.build());
+
+        let config = create_config();
+        let mut legacy = ContractV0 {
+            config: LazyOption::new(StorageKey::Config, Some(&ConfigV0 {
+                owner_id: config.owner_id,
+                contract_fee_ratio: config.contract_fee_ratio,
+                treasury_ratio: config.treasury_ratio,
+                investor_ratio: config.investor_ratio,
+                treasury: config.treasury,
+                investor: config.investor,
+                accepted_subs: "sub1.near".to_string()
+            })),
+            lotteries: UnorderedMap::new(StorageKey::Lotteries),
+            whitelisted_tokens: UnorderedSet::new(StorageKey::WhitelistedTokens),
+            fees: UnorderedMap::new(StorageKey::Fees),
+            next_lottery_id: 1,
+            cashback_accounts: UnorderedMap::new(StorageKey::Cashbacks),
+            lotteries_config: LazyOption::new(StorageKey::LotteryConfig, Some(&LotteryConfigV0 {
+                entry_fees: vec![(near(), vec![U128(ONE_NEAR)])].into_iter().collect(),
+                num_participants: vec![5],
+                big_lottery_num_participants: vec![50]
+            }))
+        };
+        legacy.lotteries.insert(&0, &LotteryV0::SimpleLottery(SimpleLotteryV0 {
+            id: 0,
+            lottery_token_id: near(),
+            lottery_status: LotteryStatus::Active,
+            entries: vec![user("user1"), user("user2")],
+            entry_fee: ONE_NEAR,
+            current_pool: ONE_NEAR * 2,
+            required_pool: ONE_NEAR * 5,
+            winner: None,
+            refferal_transfered: 0
+        }));
+        legacy.fees.insert(&near(), &ONE_NEAR);
+        legacy.cashback_accounts.insert(&near(), &StoredCashbackV0 { amount: ONE_NEAR / 2, accounts: vec![user("user3")] });
+        env::state_write(&legacy);
+
+        let mut contract = Contract::migrate();
+        let params = contract.get_contract_params();
+        assert_eq!(params.config.owner_id, owner());
+        assert_eq!(params.fees_collected, vec![(near(), U128(ONE_NEAR))]);
+        // not transfered legacy cashbacks are kept as claimable balances
+        assert_eq!(contract.get_claimable(user("user3")), vec![(near(), U128(ONE_NEAR / 2))]);
+
+        let lottery = contract.get_lottery(0).unwrap();
+        assert_eq!(lottery.entries, vec![user("user1"), user("user2")]);
+        assert_eq!(lottery.fees.contract_fee_ratio, 1000);
+
+        // migrated lottery accepts entries
+        let (entries_num, _) = enter_lottery(
+            &mut contract, 
+            &mut context, 
+            &user("user3"), 
+            SIMPLE_LOTTERY.to_string(), 
+            U128(ONE_NEAR), 
+            5u32,
+            false,
+            false,
+            None
+        );
+        assert_eq!(entries_num, 3);
+        assert_eq!(contract.get_lotteries_num(), 1);
+    }
+
+    #[test]
+    fn test_migrate_from_v1() {
+        use crate::upgrade::*;
+        let mut context = get_context(accounts(0));
+        testing_env!(context.build());
+
+        let config = create_config();
+        let fees = LotteryFees {
+            contract_fee_ratio: config.contract_fee_ratio,
+            treasury_ratio: config.treasury_ratio,
+            investor_ratio: config.investor_ratio,
+            referral_ratio: ONE_PERCENT_RATIO,
+            treasury: config.treasury.clone(),
+            investor: config.investor.clone()
+        };
+        let mut legacy = ContractV1 {
+            config: LazyOption::new(StorageKey::Config, Some(&VersionedConfig::V1(ConfigV1 {
+                owner_id: config.owner_id,
+                contract_fee_ratio: config.contract_fee_ratio,
+                treasury_ratio: config.treasury_ratio,
+                investor_ratio: config.investor_ratio,
+                treasury: config.treasury,
+                investor: config.investor,
+                accepted_subs: "sub1.near".to_string(),
+                pending_owner_id: None,
+                fees_timelock: U64(0)
+            }))),
+            lotteries: UnorderedMap::new(StorageKey::Lotteries),
+            whitelisted_tokens: UnorderedSet::new(StorageKey::WhitelistedTokens),
+            fees: UnorderedMap::new(StorageKey::Fees),
+            next_lottery_id: 2,
+            cashbacks: UnorderedMap::new(StorageKey::Cashbacks),
+            lotteries_config: LazyOption::new(StorageKey::LotteryConfig, Some(&LotteryConfigV1 {
+                entry_fees: vec![(near(), vec![U128(ONE_NEAR)])].into_iter().collect(),
+                num_participants: vec![5],
+                big_lottery_num_participants: vec![50],
+                claim_mode_tokens: vec![],
+                claim_mode_lottery_types: vec![LotteryType::BigLottery],
+                expiration: None,
+                big_lottery_expiration: Some(LotteryExpiration::BlockHeight(U64(100)))
+            })),
+            failed_payouts: UnorderedMap::new(StorageKey::FailedPayouts),
+            claimable: UnorderedMap::new(StorageKey::Claimable),
+            fees_locked: UnorderedMap::new(StorageKey::FeesLocked),
+            fees_withdrawals: Vector::new(StorageKey::FeesWithdrawals),
+            cancellations: UnorderedMap::new(StorageKey::Cancellations),
+            pause_state: LazyOption::new(StorageKey::PauseState, Some(&PauseState::default())),
+            ownership_transfers: Vector::new(StorageKey::OwnershipTransfers),
+            roles: UnorderedMap::new(StorageKey::Roles),
+            pending_fees_config: LazyOption::new(StorageKey::PendingFeesConfig, Some(&PendingFeesConfigV1 {
+                update: FeesConfigUpdateV1 {
+                    contract_fee_ratio: Some(500),
+                    treasury_ratio: None,
+                    investor_ratio: None,
+                    treasury: None,
+                    investor: None,
+                    fees_timelock: None
+                },
+                applicable_at: U64(0)
+            }))
+        };
+        legacy.lotteries.insert(&1, &VersionedLottery::V1(LotteryV1::Lottery(BigLotteryV1 {
+            id: 1,
+            lottery_token_id: near(),
+            lottery_status: LotteryStatus::Active,
+            entries: vec![user("user1"), user("user2")],
+            entry_fee: ONE_NEAR,
+            current_pool: ONE_NEAR * 2,
+            required_pool: ONE_NEAR * 50,
+            winners: HashMap::new(),
+            cashbacked_num: 0,
+            ten_percent_winners_num: 0,
+            fifty_percent_winners_num: 0,
+            refferal_transfered: 0,
+            referrals: HashMap::new(),
+            expires_at: Some(LotteryExpiration::BlockHeight(U64(100))),
+            fees
+        })));
+        legacy.cancellations.insert(&0, &LotteryCancellationV1 {
+            lottery_id: U64(0),
+            reason: "test".to_string(),
+            lottery_token_id: near(),
+            refunded_entries: vec![user("user3")],
+            entry_fee: U128(ONE_NEAR),
+            timestamp: U64(0)
+        });
+        env::state_write(&legacy);
+        env::storage_write(STATE_VERSION_KEY, &1u32.try_to_vec().unwrap());
+
+        let mut contract = Contract::migrate();
+        let params = contract.get_contract_params();
+        assert_eq!(params.config.owner_id, owner());
+        assert_eq!(params.config.referral_ratio, ONE_PERCENT_RATIO);
+        assert_eq!(params.pending_fees_config.unwrap().update.contract_fee_ratio, Some(500));
+        assert_eq!(
+            contract.get_cancellation(0).unwrap().entries_hash,
+            hash_entries(&[Entry { account_id: user("user3"), tickets: 1 }]).into()
+        );
+        let lottery_config = contract.internal_lottery_config();
+        assert_eq!(lottery_config.claim_mode_lottery_types, vec![LotteryType::BigLottery]);
+        assert_eq!(lottery_config.big_lottery_expiration, Some(LotteryExpiration::BlockHeight(U64(100))));
+
+        let lottery = contract.get_lottery(1).unwrap();
+        assert_eq!(lottery.entries, vec![user("user1"), user("user2")]);
+        assert_eq!(lottery.fees.contract_fee_ratio, 1000);
+
+        // migrated lottery accepts entries
+        let (entries_num, _) = enter_lottery(
+            &mut contract, 
+            &mut context, 
+            &user("user3"), 
+            BIG_LOTTERY.to_string(), 
+            U128(ONE_NEAR), 
+            50u32,
+            false,
+            false,
+            None
+        );
+        assert_eq!(entries_num, 3);
+
+        // migrated state is kept by the next upgrade
+        env::state_write(&contract);
+        let contract = Contract::migrate();
+        assert_eq!(contract.get_lottery_entries(1, None, None).len(), 3);
+    }
+
+    #[test]
+    fn test_migrate_keeps_current_state() {
+        let (mut contract, mut context) = contract_context();
+        change_subs(&mut contract, &mut context);
+        contract.set_lottery_expiration(SIMPLE_LOTTERY.to_string(), Some(LotteryExpiration::BlockHeight(U64(10))));
+        env::state_write(&contract);
+
+        let contract = Contract::migrate();
+        assert_eq!(contract.get_contract_params().config.accepted_subs, "sub1.near");
+        assert_eq!(
+            contract.internal_lottery_config().expiration,
+            Some(LotteryExpiration::BlockHeight(U64(10)))
+        );
+    }
+
+    fn fill_delayed_lottery(contract: &mut Contract, context: &mut VMContextBuilder) {
+        change_subs(contract, context);
+        contract.set_lottery_draw_mode(SIMPLE_LOTTERY.to_string(), DrawMode::Delayed);
+        for index in 1..=5 {
+            enter_lottery(
+                contract, 
+                context, 
+                &user(&format!("user{}", index)), 
+                SIMPLE_LOTTERY.to_string(), 
+                U128(ONE_NEAR), 
+                5u32,
+                index == 1,
+                false,
+                None
+            );
+        }
+    }
+
+    #[test]
+    fn test_delayed_draw() {
+        let (mut contract, mut context) = contract_context();
+        fill_delayed_lottery(&mut contract, &mut context);
+        let lottery = contract.get_lottery(0).unwrap();
+        assert_eq!(lottery.lottery_status, LotteryStatus::Drawing);
+        assert_eq!(lottery.filled_block_height, Some(0));
+
+        // filled lottery does not accept entries, new one is created
+        enter_lottery(
+            &mut contract, 
+            &mut context, 
+            &user("user6"), 
+            SIMPLE_LOTTERY.to_string(), 
+            U128(ONE_NEAR), 
+            5u32,
+            true,
+            false,
+            None
+        );
+
+        testing_env!(context
+            .predecessor_account_id(user("anyone"))
+            .attached_deposit(0)
+            .block_index(1)
+            .build()
+        );
+        match contract.draw(0) {
+            LotteryResult::SimpleLotteryResult(result) => {
+                assert_eq!(result.winning_amount, U128(ONE_NEAR * 5 * 9 / 10))
+            },
+            _ => panic!("Expected simple lottery result"),
+        }
+        assert!(contract.get_lottery(0).is_none());
+    }
+
+    #[test]
+    #[should_panic(expected = "Draw is available in a later block than the final entry")]
+    fn test_draw_in_final_entry_block() {
+        let (mut contract, mut context) = contract_context();
+        fill_delayed_lottery(&mut contract, &mut context);
+        contract.draw(0);
+    }
+
+    fn fill_commit_reveal_lottery(contract: &mut Contract, context: &mut VMContextBuilder) {
+        change_subs(contract, context);
+        contract.set_lottery_draw_mode(SIMPLE_LOTTERY.to_string(), DrawMode::CommitReveal { reveal_window: U64(10) });
+        for index in 1..=5 {
+            testing_env!(context
+                .predecessor_account_id(user(&format!("user{}", index)))
+                .attached_deposit(ONE_NEAR)
+                .build()
+            );
+            let secret_hash = env::sha256_array(format!("secret{}", index).as_bytes());
+            contract.draw_near_enter(SIMPLE_LOTTERY.to_string(), 5, None, Some(secret_hash.into()), None);
+        }
+        assert_eq!(contract.get_lottery(0).unwrap().lottery_status, LotteryStatus::Drawing);
+    }
+
+    #[test]
+    fn test_commit_reveal_draw() {
+        let (mut contract, mut context) = contract_context();
+        fill_commit_reveal_lottery(&mut contract, &mut context);
+
+        for index in [2, 4] {
+            testing_env!(context
+                .predecessor_account_id(user(&format!("user{}", index)))
+                .attached_deposit(0)
+                .block_index(5)
+                .build()
+            );
+            contract.reveal_secret(0, format!("secret{}", index));
+        }
+
+        // entrants not revealed secrets cannot win
+        testing_env!(context.block_index(11).build());
+        match contract.draw(0) {
+            LotteryResult::SimpleLotteryResult(result) => {
+                assert!([user("user2"), user("user4")].contains(&result.winner))
+            },
+            _ => panic!("Expected simple lottery result"),
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "Reveal window is not over")]
+    fn test_commit_reveal_draw_before_reveal_window_end() {
+        let (mut contract, mut context) = contract_context();
+        fill_commit_reveal_lottery(&mut contract, &mut context);
+
+        testing_env!(context
+            .predecessor_account_id(user("user1"))
+            .attached_deposit(0)
+            .block_index(5)
+            .build()
+        );
+        contract.reveal_secret(0, "secret1".to_string());
+        contract.draw(0);
+    }
+
+    #[test]
+    fn test_verify_draw() {
+        let (mut contract, mut context) = contract_context();
+        fill_commit_reveal_lottery(&mut contract, &mut context);
+        testing_env!(context
+            .predecessor_account_id(user("user3"))
+            .attached_deposit(0)
+            .block_index(5)
+            .build()
+        );
+        contract.reveal_secret(0, "secret3".to_string());
+
+        testing_env!(context.block_index(11).build());
+        let winner = match contract.draw(0) {
+            LotteryResult::SimpleLotteryResult(result) => result.winner,
+            _ => panic!("Expected simple lottery result"),
+        };
+        assert_eq!(winner, user("user3"));
+
+        let record = contract.get_draw(0).unwrap();
+        assert_eq!(record.block_height, 11);
+        // excluded entries met before the winner
+        assert_eq!(record.excluded_indices, vec![0, 3]);
+        assert_eq!(record.winner_indices, vec![2]);
+
+        let entries = (1..=5).map(|index| user(&format!("user{}", index))).collect::<Vec<_>>();
+        let verification = contract.verify_draw(0, entries.clone());
+        assert!(verification.entries_match && verification.winners_match);
+        assert_eq!(verification.winners, vec![winner]);
+
+        let verification = contract.verify_draw(0, entries[1..].to_vec());
+        assert!(!verification.entries_match && verification.winners_match);
+        assert!(verification.winners.is_empty());
+    }
+
+    #[test]
+    fn test_big_lottery_with_many_entries() {
+        let entry_fee = ONE_NEAR;
+        let (mut contract, mut context) = contract_context();
+        change_subs(&mut contract, &mut context);
+        contract.add_num_participants(255, BIG_LOTTERY.to_string());
+        contract.set_lottery_type_claim_mode(BIG_LOTTERY.to_string(), true);
+
+        let enter = |contract: &mut Contract, context: &mut VMContextBuilder, index: u32| {
+            testing_env!(context
+                .predecessor_account_id(user(&format!("user{}", index)))
+                .attached_deposit(entry_fee)
+                .build()
+            );
+            contract.draw_near_enter(BIG_LOTTERY.to_string(), 255, None, None, None);
+        };
+        for index in 0..100 {
+            enter(&mut contract, &mut context, index);
+        }
+        let lottery = contract.get_lottery(0).unwrap();
+        let params = lottery.big_lottery_params.unwrap();
+        assert_eq!(params.tier_winners_nums, vec![51, 76, 128]);
+
+        // the last entry takes place of the left one
+        testing_env!(context
+            .predecessor_account_id(user("user5"))
+            .attached_deposit(ONE_YOCTO)
+            .build()
+        );
+        contract.leave_lottery(0);
+        assert_eq!(
+            contract.get_lottery_entries(0, Some(4), Some(2)).into_iter().map(|entry| entry.account_id).collect::<Vec<_>>(),
+            vec![user("user4"), user("user99")]
+        );
+
+        for index in 100..256 {
+            enter(&mut contract, &mut context, index);
+        }
+        assert!(contract.get_lottery(0).is_none());
+
+        let record = contract.get_draw(0).unwrap();
+        assert_eq!(record.entries_num, 255);
+        assert_eq!(record.winner_indices.len(), 127);
+        let entries = (0..5).chain(99..100).chain(6..99).chain(100..256)
+            .map(|index| user(&format!("user{}", index)))
+            .collect::<Vec<_>>();
+        let verification = contract.verify_draw(0, entries.clone());
+        assert!(verification.entries_match && verification.winners_match);
+        assert_eq!(verification.winners.len(), 127);
+
+        // the rest tier is credited in batches
+        while !contract.get_pending_settlements(None, None).is_empty() {
+            testing_env!(context.predecessor_account_id(user("keeper")).build());
+            contract.process_cashbacks(None);
+        }
+
+        let mut claimable = 0;
+        for account_id in entries {
+            testing_env!(context.build());
+            claimable += contract.get_claimable(account_id).iter().map(|(_, amount)| amount.0).sum::<Balance>();
+        }
+        assert_eq!(claimable, 51 * entry_fee * 3 / 2 + 76 * entry_fee * 11 / 10 + 128 * entry_fee / 2);
+    }
+
+    fn jackpot_prize_table() -> PrizeTable {
+        PrizeTable {
+            tiers: vec![
+                PrizeTier { name: "Jackpot".to_string(), participants_ratio: 1000, payout_ratio: 50000 },
+                PrizeTier { name: "Refund".to_string(), participants_ratio: 4000, payout_ratio: 10000 },
+            ]
+        }
+    }
+
+    #[test]
+    fn test_prize_table() {
+        let entry_fee = ONE_NEAR;
+        let (mut contract, mut context) = contract_context();
+        change_subs(&mut contract, &mut context);
+        contract.add_num_participants(10, BIG_LOTTERY.to_string());
+        contract.set_lottery_draw_mode(BIG_LOTTERY.to_string(), DrawMode::Delayed);
+        contract.set_prize_table("jackpot".to_string(), jackpot_prize_table());
+        contract.set_big_lottery_prize_table("jackpot".to_string());
+
+        for index in 0..10 {
+            testing_env!(context
+                .predecessor_account_id(user(&format!("user{}", index)))
+                .attached_deposit(entry_fee)
+                .build()
+            );
+            contract.draw_near_enter(BIG_LOTTERY.to_string(), 10, None, None, None);
+        }
+        let params = contract.get_lottery(0).unwrap().big_lottery_params.unwrap();
+        assert_eq!(params.prize_table_id, "jackpot");
+        assert_eq!(params.tier_winners_nums, vec![1, 4]);
+
+        // lottery keeps its prize table
+        owner_env(&mut context);
+        contract.set_big_lottery_prize_table(DEFAULT_PRIZE_TABLE.to_string());
+        contract.remove_prize_table("jackpot".to_string());
+
+        testing_env!(context
+            .predecessor_account_id(user("anyone"))
+            .attached_deposit(0)
+            .block_index(1)
+            .build()
+        );
+        let result = match contract.draw(0) {
+            LotteryResult::BigLotteryResult(result) => result,
+            _ => panic!("Expected big lottery result"),
+        };
+        assert_eq!(result.prize_table_id, "jackpot");
+        assert_eq!(
+            result.tiers.iter().map(|tier| (tier.name.as_str(), tier.amount, tier.winners.len())).collect::<Vec<_>>(),
+            vec![("Jackpot", U128(5 * entry_fee), 1), ("Refund", U128(entry_fee), 4)]
+        );
+        assert!(contract.get_pending_cashbacks(None, None).is_empty());
+        // 10% of contract fees go to investor
+        assert_eq!(contract.get_contract_params().fees_collected, vec![(near(), U128(entry_fee * 6 / 10))]);
+    }
+
+    #[test]
+    #[should_panic(expected = "Prize table payouts exceed pool minus fees")]
+    fn test_prize_table_exceeding_pool() {
+        let (mut contract, mut context) = contract_context();
+        owner_env(&mut context);
+        let mut prize_table = jackpot_prize_table();
+        prize_table.tiers[0].payout_ratio = 60000;
+        contract.set_prize_table("jackpot".to_string(), prize_table);
+    }
+
+    #[test]
+    #[should_panic(expected = "Prize table payouts exceed pool minus fees")]
+    fn test_prize_table_exceeding_pool_with_referrals() {
+        let (mut contract, mut context) = contract_context();
+        owner_env(&mut context);
+        // payouts take 99.2% of pool, so 1% referral rewards do not fit
+        let mut prize_table = jackpot_prize_table();
+        prize_table.tiers[1].payout_ratio = 12300;
+        contract.set_prize_table("jackpot".to_string(), prize_table);
+    }
+
+    #[test]
+    #[should_panic(expected = "Prize table payouts exceed pool minus fees")]
+    fn test_fees_config_referral_ratio_exceeding_prize_tables() {
+        let (mut contract, mut context) = contract_context();
+        owner_env(&mut context);
+        contract.propose_fees_config(FeesConfigUpdate {
+            contract_fee_ratio: Some(3000),
+            referral_ratio: Some(1500),
+            ..fees_update()
+        });
+    }
+
+    #[test]
+    #[should_panic(expected = "Prize table is used for new big lotteries")]
+    fn test_remove_used_prize_table() {
+        let (mut contract, mut context) = contract_context();
+        owner_env(&mut context);
+        contract.remove_prize_table(DEFAULT_PRIZE_TABLE.to_string());
+    }
+
+    #[test]
+    fn test_multi_winner_simple_lottery() {
+        let (mut contract, mut context) = contract_context();
+        change_subs(&mut contract, &mut context);
+        contract.set_prize_split(vec![6000, 3000, 1000]);
+        fill_delayed_lottery(&mut contract, &mut context);
+
+        // 10% contract fees
+        let reward = ONE_NEAR * 5 * 9 / 10;
+        let params = contract.get_lottery(0).unwrap().simple_lottery_params.unwrap();
+        assert_eq!(params.prize_split, vec![6000, 3000, 1000]);
+        assert_eq!(params.prizes, vec![U128(reward * 6 / 10), U128(reward * 3 / 10), U128(reward / 10)]);
+        assert!(params.winners.is_empty());
+
+        testing_env!(context
+            .predecessor_account_id(user("anyone"))
+            .attached_deposit(0)
+            .block_index(1)
+            .build()
+        );
+        let result = match contract.draw(0) {
+            LotteryResult::SimpleLotteryResult(result) => result,
+            _ => panic!("Expected simple lottery result"),
+        };
+        assert_eq!(result.winning_amount, U128(reward));
+        assert_eq!(
+            result.winners.iter().map(|winner| (winner.rank, winner.amount)).collect::<Vec<_>>(),
+            vec![(1, U128(reward * 6 / 10)), (2, U128(reward * 3 / 10)), (3, U128(reward / 10))]
+        );
+        assert_eq!(result.winner, result.winners[0].account_id);
+        let mut winners = result.winners.iter().map(|winner| winner.account_id.clone()).collect::<Vec<_>>();
+        winners.dedup();
+        assert_eq!(winners.len(), 3);
+
+        let transfers = near_transfers();
+        for winner in &result.winners {
+            assert!(transfers.contains(&(winner.account_id.clone(), winner.amount.0)));
+        }
+        assert!(contract.verify_draw(0, result.participants).winners_match);
+    }
+
+    #[test]
+    #[should_panic(expected = "Prize split must sum up to 100% in Basis Points")]
+    fn test_invalid_prize_split() {
+        let (mut contract, mut context) = contract_context();
+        owner_env(&mut context);
+        contract.set_prize_split(vec![6000, 3000]);
+    }
+
+    fn enter_timed_lottery(
+        contract: &mut Contract,
+        context: &mut VMContextBuilder,
+        lottery_type: &str,
+        user: &AccountId,
+        timestamp: u64
+    ) -> LotteryId {
+        testing_env!(context
+            .predecessor_account_id(user.clone())
+            .attached_deposit(ONE_NEAR)
+            .block_timestamp(timestamp)
+            .build()
+        );
+        contract.draw_near_enter(lottery_type.to_string(), 0, None, None, None)
+    }
+
+    #[test]
+    fn test_timed_lottery() {
+        let (mut contract, mut context) = contract_context();
+        change_subs(&mut contract, &mut context);
+        contract.set_timed_lottery_duration(Some(U64(1000)));
+
+        for name in ["user1", "user2", "user3"] {
+            assert_eq!(enter_timed_lottery(&mut contract, &mut context, TIMED_LOTTERY, &user(name), 100), 0);
+        }
+        let lottery = contract.get_lottery(0).unwrap();
+        assert_eq!(lottery.lottery_status, LotteryStatus::Active);
+        assert_eq!(lottery.current_pool, U128(3 * ONE_NEAR));
+        assert_eq!(lottery.timed_lottery_params.unwrap().draw_at, U64(1100));
+
+        // entries are closed at draw time, next entry opens a new lottery
+        assert_eq!(enter_timed_lottery(&mut contract, &mut context, TIMED_LOTTERY, &user("user4"), 1100), 1);
+        assert_eq!(contract.get_lottery(0).unwrap().lottery_status, LotteryStatus::Drawing);
+
+        testing_env!(context
+            .predecessor_account_id(user("anyone"))
+            .attached_deposit(0)
+            .block_index(1)
+            .build()
+        );
+        let result = match contract.draw(0) {
+            LotteryResult::TimedLotteryResult(result) => result,
+            _ => panic!("Expected timed lottery result"),
+        };
+        // 10% contract fees
+        let reward = ONE_NEAR * 3 * 9 / 10;
+        assert_eq!(result.winning_amount, U128(reward));
+        assert_eq!(result.participants_num, 3);
+        assert!(result.participants.contains(&result.winner));
+        assert!(near_transfers().contains(&(result.winner.clone(), reward)));
+        assert!(contract.verify_draw(0, result.participants).winners_match);
+        assert!(contract.get_lottery(0).is_none());
+        assert_eq!(contract.get_lottery(1).unwrap().entries, vec![user("user4")]);
+
+        // entries are released in batches
+        assert_eq!(contract.get_pending_settlements(None, None)[0].entries_num, 3);
+        assert_eq!(contract.process_cashbacks(None), 3);
+        assert!(contract.get_pending_settlements(None, None).is_empty());
+    }
+
+    #[test]
+    fn test_cancel_timed_lottery_waiting_for_draw() {
+        let (mut contract, mut context) = contract_context();
+        change_subs(&mut contract, &mut context);
+        contract.set_timed_lottery_duration(Some(U64(1000)));
+        for name in ["user1", "user2", "user3"] {
+            enter_timed_lottery(&mut contract, &mut context, TIMED_LOTTERY, &user(name), 100);
+        }
+
+        testing_env!(context.block_timestamp(1100).build());
+        assert_eq!(contract.get_lottery(0).unwrap().lottery_status, LotteryStatus::Drawing);
+        owner_env(&mut context);
+        contract.cancel_lottery(0, "draw failed".to_string());
+        assert!(contract.get_lottery(0).is_none());
+        assert_eq!(contract.get_cashback_liabilities(), vec![(near(), U128(3 * ONE_NEAR))]);
+
+        testing_env!(context.predecessor_account_id(user("keeper")).attached_deposit(0).build());
+        assert_eq!(contract.process_cashbacks(None), 3);
+        let transfers = near_transfers();
+        for name in ["user1", "user2", "user3"] {
+            assert!(transfers.contains(&(user(name), ONE_NEAR)));
+        }
+        assert!(contract.get_cashback_liabilities().is_empty());
+    }
+
+    #[test]
+    fn test_pick_winners_skips_excluded() {
+        let excluded_indices = [0, 3, 4, 9];
+        for lottery_id in 0..20 {
+            let candidates = (0..10).filter(|index| !excluded_indices.contains(index)).collect::<Vec<u32>>();
+            let expected = LotteryRng::new([8; 32], lottery_id, RandomPurpose::SimpleLotteryWinner)
+                .sample(candidates.len() as _, 6)
+                .into_iter()
+                .map(|index| candidates[index as usize])
+                .collect::<Vec<_>>();
+            let winners = pick_winners([8; 32], lottery_id, RandomPurpose::SimpleLotteryWinner, 10, &excluded_indices, 8);
+            assert_eq!(winners[..6], expected[..]);
+            assert!(winners[6..].iter().all(|index| excluded_indices.contains(index)));
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "Draw is available after draw time")]
+    fn test_timed_lottery_draw_before_draw_time() {
+        let (mut contract, mut context) = contract_context();
+        change_subs(&mut contract, &mut context);
+        contract.set_timed_lottery_duration(Some(U64(1000)));
+        enter_timed_lottery(&mut contract, &mut context, TIMED_LOTTERY, &user("user1"), 100);
+
+        testing_env!(context.block_timestamp(1099).build());
+        contract.draw(0);
+    }
+
+    fn draw_jackpot(contract: &mut Contract, context: &mut VMContextBuilder, lottery_id: LotteryId, timestamp: u64) -> JackpotLotteryResult {
+        testing_env!(context
+            .predecessor_account_id(user("anyone"))
+            .attached_deposit(0)
+            .block_timestamp(timestamp)
+            .block_index(timestamp)
+            .build()
+        );
+        match contract.draw(lottery_id) {
+            LotteryResult::JackpotLotteryResult(result) => result,
+            _ => panic!("Expected jackpot lottery result"),
+        }
+    }
+
+    #[test]
+    fn test_jackpot_rolls_over() {
+        let (mut contract, mut context) = contract_context();
+        change_subs(&mut contract, &mut context);
+        contract.set_jackpot_config(Some(JackpotConfig { duration: U64(1000), ticket_range: 1_000_000 }));
+
+        enter_timed_lottery(&mut contract, &mut context, JACKPOT_LOTTERY, &user("user1"), 100);
+        enter_timed_lottery(&mut contract, &mut context, JACKPOT_LOTTERY, &user("user2"), 100);
+        // 10% contract fees
+        let round_pool = ONE_NEAR * 9 / 10;
+
+        let result = draw_jackpot(&mut contract, &mut context, 0, 1100);
+        assert_eq!(result.round, U64(1));
+        assert_eq!(result.participants_num, 2);
+        assert_eq!(result.participants, vec![user("user1"), user("user2")]);
+        assert!(result.drawn_number >= 2);
+        assert!(result.winner.is_none());
+        assert_eq!(result.winning_amount, U128(0));
+        assert_eq!(result.rolled_over, U128(2 * round_pool));
+        let jackpots = contract.get_jackpots();
+        assert_eq!(jackpots.len(), 1);
+        assert_eq!((jackpots[0].balance, jackpots[0].rounds_num, jackpots[0].lottery_id), (U128(2 * round_pool), U64(1), None));
+        // entries of drawn round are released in batches
+        assert_eq!(contract.get_pending_settlements(None, None)[0].entries_num, 2);
+        assert_eq!(contract.process_cashbacks(None), 2);
+        assert!(contract.get_pending_settlements(None, None).is_empty());
+
+        // the only ticket of next round always wins
+        owner_env(&mut context);
+        contract.set_jackpot_config(Some(JackpotConfig { duration: U64(1000), ticket_range: 1 }));
+        assert_eq!(enter_timed_lottery(&mut contract, &mut context, JACKPOT_LOTTERY, &user("user3"), 1200), 1);
+        let params = contract.get_lottery(1).unwrap().jackpot_lottery_params.unwrap();
+        assert_eq!((params.round, params.jackpot), (U64(2), U128(2 * round_pool)));
+        assert_eq!(contract.get_jackpots()[0].lottery_id, Some(1));
+
+        let result = draw_jackpot(&mut contract, &mut context, 1, 2200);
+        assert_eq!(result.winner, Some(user("user3")));
+        assert_eq!(result.winning_amount, U128(3 * round_pool));
+        assert_eq!(result.rolled_over, U128(0));
+        assert!(near_transfers().contains(&(user("user3"), 3 * round_pool)));
+        assert!(contract.verify_draw(1, vec![user("user3")]).winners_match);
+
+        let history = contract.get_jackpot_history(None, U128(ONE_NEAR), None, None);
+        assert_eq!(
+            history.iter().map(|round| (round.round, round.winner.clone())).collect::<Vec<_>>(),
+            vec![(U64(1), None), (U64(2), Some(user("user3")))]
+        );
+        assert_eq!(contract.get_jackpot_history(None, U128(ONE_NEAR), Some(U64(2)), Some(U64(5))).len(), 1);
+    }
+
+    #[test]
+    #[should_panic(expected = "Jackpot round is waiting for draw")]
+    fn test_jackpot_round_waiting_for_draw() {
+        let (mut contract, mut context) = contract_context();
+        change_subs(&mut contract, &mut context);
+        contract.set_jackpot_config(Some(JackpotConfig { duration: U64(1000), ticket_range: 100 }));
+
+        enter_timed_lottery(&mut contract, &mut context, JACKPOT_LOTTERY, &user("user1"), 100);
+        enter_timed_lottery(&mut contract, &mut context, JACKPOT_LOTTERY, &user("user2"), 1100);
+    }
+
+    #[test]
+    fn test_cancel_jackpot_round_waiting_for_draw() {
+        let (mut contract, mut context) = contract_context();
+        change_subs(&mut contract, &mut context);
+        contract.set_jackpot_config(Some(JackpotConfig { duration: U64(1000), ticket_range: 100 }));
+        enter_timed_lottery(&mut contract, &mut context, JACKPOT_LOTTERY, &user("user1"), 100);
+        enter_timed_lottery(&mut contract, &mut context, JACKPOT_LOTTERY, &user("user2"), 100);
+
+        testing_env!(context.block_timestamp(1100).build());
+        assert_eq!(contract.get_lottery(0).unwrap().lottery_status, LotteryStatus::Drawing);
+        owner_env(&mut context);
+        contract.cancel_lottery(0, "draw failed".to_string());
+        assert!(contract.get_lottery(0).is_none());
+
+        testing_env!(context.predecessor_account_id(user("keeper")).attached_deposit(0).build());
+        assert_eq!(contract.process_cashbacks(None), 2);
+        let transfers = near_transfers();
+        for name in ["user1", "user2"] {
+            assert!(transfers.contains(&(user(name), ONE_NEAR)));
+        }
+
+        // cancelled round doesn't block its series
+        assert_eq!(enter_timed_lottery(&mut contract, &mut context, JACKPOT_LOTTERY, &user("user3"), 1200), 1);
+        assert_eq!(contract.get_jackpots()[0].lottery_id, Some(1));
+    }
+
+    fn enter_with_tickets(
+        contract: &mut Contract,
+        context: &mut VMContextBuilder,
+        user: &AccountId,
+        tickets: u32
+    ) -> LotteryId {
+        testing_env!(context
+            .predecessor_account_id(user.clone())
+            .attached_deposit(ONE_NEAR * tickets as u128)
+            .build()
+        );
+        contract.draw_near_enter(SIMPLE_LOTTERY.to_string(), 5, None, None, Some(tickets))
+    }
+
+    #[test]
+    fn test_multiple_tickets() {
+        let (mut contract, mut context) = contract_context();
+        change_subs(&mut contract, &mut context);
+        contract.set_lottery_max_tickets(SIMPLE_LOTTERY.to_string(), 3);
+
+        enter_with_tickets(&mut contract, &mut context, &user("user1"), 2);
+        enter_with_tickets(&mut contract, &mut context, &user("user2"), 1);
+        enter_with_tickets(&mut contract, &mut context, &user("user1"), 1);
+        let lottery = contract.get_lottery(0).unwrap();
+        assert_eq!(lottery.entries, vec![user("user1"), user("user2")]);
+        assert_eq!(lottery.tickets_num, 4);
+        assert_eq!(lottery.max_tickets, 3);
+        assert_eq!(lottery.current_pool, U128(ONE_NEAR * 4));
+        assert_eq!(
+            contract.get_lottery_entries(0, None, None),
+            vec![
+                Entry { account_id: user("user1"), tickets: 3 },
+                Entry { account_id: user("user2"), tickets: 1 }
+            ]
+        );
+
+        // all tickets of account are refunded
+        testing_env!(context
+            .predecessor_account_id(user("user1"))
+            .attached_deposit(ONE_YOCTO)
+            .build()
+        );
+        contract.leave_lottery(0);
+        assert_eq!(near_transfers(), vec![(user("user1"), ONE_NEAR * 3)]);
+        assert_eq!(contract.get_lottery(0).unwrap().tickets_num, 1);
+
+        enter_with_tickets(&mut contract, &mut context, &user("user1"), 3);
+        let lottery_id = enter_with_tickets(&mut contract, &mut context, &user("user3"), 1);
+        assert_eq!(lottery_id, 0);
+        assert!(contract.get_lottery(0).is_none());
+
+        // every ticket is a place in the draw
+        let entries = vec![user("user2"), user("user1"), user("user1"), user("user1"), user("user3")];
+        let verification = contract.verify_draw(0, entries);
+        assert!(verification.entries_match && verification.winners_match);
+    }
+
+    #[test]
+    #[should_panic(expected = "Account can hold at most 3 tickets")]
+    fn test_tickets_over_max() {
+        let (mut contract, mut context) = contract_context();
+        change_subs(&mut contract, &mut context);
+        contract.set_lottery_max_tickets(SIMPLE_LOTTERY.to_string(), 3);
+
+        enter_with_tickets(&mut contract, &mut context, &user("user1"), 2);
+        enter_with_tickets(&mut contract, &mut context, &user("user1"), 2);
+    }
+
+    #[test]
+    fn test_account_wins_one_rank() {
+        let (mut contract, mut context) = contract_context();
+        change_subs(&mut contract, &mut context);
+        contract.set_lottery_max_tickets(SIMPLE_LOTTERY.to_string(), 3);
+        contract.set_prize_split(vec![6000, 3000, 1000]);
+
+        enter_with_tickets(&mut contract, &mut context, &user("user1"), 3);
+        enter_with_tickets(&mut contract, &mut context, &user("user2"), 1);
+        enter_with_tickets(&mut contract, &mut context, &user("user3"), 1);
+
+        let entries = vec![user("user1"), user("user1"), user("user1"), user("user2"), user("user3")];
+        let verification = contract.verify_draw(0, entries);
+        assert!(verification.entries_match && verification.winners_match);
+        let mut winners = verification.winners;
+        winners.sort();
+        assert_eq!(winners, vec![user("user1"), user("user2"), user("user3")]);
+
+        // prize of rank without winner goes to the first rank
+        let reward = ONE_NEAR * 5 * 9 / 10;
+        enter_with_tickets(&mut contract, &mut context, &user("user1"), 3);
+        enter_with_tickets(&mut contract, &mut context, &user("user2"), 2);
+        let record = contract.get_draw(1).unwrap();
+        assert_eq!(record.winner_indices.len(), 2);
+        let first = [user("user1"), user("user2")][record.winner_indices[0] as usize].clone();
+        let second = [user("user1"), user("user2")][record.winner_indices[1] as usize].clone();
+        let transfers = near_transfers();
+        assert!(transfers.contains(&(first, reward * 6 / 10 + reward / 10)));
+        assert!(transfers.contains(&(second, reward * 3 / 10)));
+    }
+
+    #[test]
+    fn test_account_wins_one_tier() {
+        let (mut contract, mut context) = contract_context();
+        change_subs(&mut contract, &mut context);
+        contract.add_num_participants(10, BIG_LOTTERY.to_string());
+        contract.set_lottery_max_tickets(BIG_LOTTERY.to_string(), 3);
+        contract.set_prize_table("jackpot".to_string(), jackpot_prize_table());
+        contract.set_big_lottery_prize_table("jackpot".to_string());
+
+        let tickets = [3, 3, 1, 1, 1, 1];
+        for (index, tickets) in tickets.iter().enumerate() {
+            testing_env!(context
+                .predecessor_account_id(user(&format!("user{}", index)))
+                .attached_deposit(ONE_NEAR * *tickets as u128)
+                .build()
+            );
+            contract.draw_near_enter(BIG_LOTTERY.to_string(), 10, None, None, Some(*tickets));
+        }
+        assert!(contract.get_lottery(0).is_none());
+
+        let entries = tickets
+            .iter()
+            .enumerate()
+            .flat_map(|(index, tickets)| std::iter::repeat(user(&format!("user{}", index))).take(*tickets as usize))
+            .collect::<Vec<_>>();
+        let verification = contract.verify_draw(0, entries);
+        assert!(verification.entries_match && verification.winners_match);
+        // 1 jackpot and 4 refund winners out of 6 accounts
+        let mut winners = verification.winners;
+        winners.sort();
+        winners.dedup();
+        assert_eq!(winners.len(), 5);
+
+        // entries are released through rest tiers queue
+        assert_eq!(contract.get_cashback_liabilities(), vec![]);
+        testing_env!(context.predecessor_account_id(user("keeper")).build());
+        assert_eq!(contract.process_cashbacks(None), 6);
+        assert!(contract.get_pending_settlements(None, None).is_empty());
+    }
+
+    #[test]
+    fn test_rest_tier_pays_other_tickets_of_winner() {
+        let (mut contract, mut context) = contract_context();
+        change_subs(&mut contract, &mut context);
+        contract.add_num_participants(10, BIG_LOTTERY.to_string());
+        contract.set_lottery_max_tickets(BIG_LOTTERY.to_string(), 3);
+        contract.set_lottery_type_claim_mode(BIG_LOTTERY.to_string(), true);
+        contract.set_prize_table("top".to_string(), PrizeTable {
+            tiers: vec![
+                PrizeTier { name: "Top".to_string(), participants_ratio: 1000, payout_ratio: 50000 },
+                PrizeTier { name: "Rest".to_string(), participants_ratio: 9000, payout_ratio: 5000 },
+            ]
+        });
+        contract.set_big_lottery_prize_table("top".to_string());
+
+        let tickets = [3, 3, 3, 1];
+        for (index, tickets) in tickets.iter().enumerate() {
+            testing_env!(context
+                .predecessor_account_id(user(&format!("user{}", index)))
+                .attached_deposit(ONE_NEAR * *tickets as u128)
+                .build()
+            );
+            contract.draw_near_enter(BIG_LOTTERY.to_string(), 10, None, None, Some(*tickets));
+        }
+        assert!(contract.get_lottery(0).is_none());
+        let winner_index = contract.get_draw(0).unwrap().winner_indices[0] as usize;
+
+        testing_env!(context.predecessor_account_id(user("keeper")).build());
+        assert_eq!(contract.process_cashbacks(None), 4);
+        let claimable = (0..tickets.len())
+            .map(|index| {
+                contract.get_claimable(user(&format!("user{}", index))).iter().map(|(_, amount)| amount.0).sum::<Balance>()
+            })
+            .collect::<Vec<_>>();
+        // winner of top tier is paid by the rest tier for its other tickets
+        for (index, tickets) in tickets.iter().enumerate() {
+            let won_tickets = (index == winner_index) as u128;
+            let top_prize = if index == winner_index { 5 * ONE_NEAR } else { 0 };
+            assert_eq!(claimable[index], top_prize + (*tickets as u128 - won_tickets) * ONE_NEAR / 2);
+        }
+        assert_eq!(claimable.iter().sum::<Balance>(), 5 * ONE_NEAR + 9 * ONE_NEAR / 2);
+    }
+
+    #[test]
+    fn test_ticket_index() {
+        let (_, _) = contract_context();
+        let mut entries = LotteryEntries::new(0);
+        let assert_ticket_index = |entries: &LotteryEntries| {
+            let tickets_ends = tickets_ends(&entries.to_vec());
+            assert_eq!(tickets_ends.last().copied().unwrap_or_default(), entries.tickets_num());
+            for ticket in 0..entries.tickets_num() {
+                assert_eq!(entries.entry_of_ticket(ticket), entry_of_ticket(&tickets_ends, ticket));
+            }
+        };
+        for (index, tickets) in [3, 1, 2, 5, 1, 4, 1, 2, 3].iter().enumerate() {
+            entries.push(&user(&format!("user{}", index)), *tickets);
+            assert_ticket_index(&entries);
+        }
+        entries.add_tickets(&user("user1"), 2);
+        entries.add_tickets(&user("user8"), 1);
+        assert_ticket_index(&entries);
+
+        entries.remove(&user("user2"));
+        assert_ticket_index(&entries);
+        entries.remove(&user("user7"));
+        assert_ticket_index(&entries);
+        while entries.pop().is_some() {
+            assert_ticket_index(&entries);
+        }
+        entries.push(&user("user0"), 2);
+        assert_ticket_index(&entries);
+    }
+
+    fn enter_lotto(
+        contract: &mut Contract,
+        context: &mut VMContextBuilder,
+        user: &AccountId,
+        numbers: Vec<u32>,
+        timestamp: u64
+    ) -> LotteryId {
+        testing_env!(context
+            .predecessor_account_id(user.clone())
+            .attached_deposit(ONE_NEAR)
+            .block_timestamp(timestamp)
+            .build()
+        );
+        contract.draw_near_lotto_enter(numbers, None)
+    }
+
+    fn draw_lotto(contract: &mut Contract, context: &mut VMContextBuilder, lottery_id: LotteryId, timestamp: u64) -> LottoResult {
+        testing_env!(context
+            .predecessor_account_id(user("anyone"))
+            .attached_deposit(0)
+            .block_timestamp(timestamp)
+            .block_index(timestamp)
+            .build()
+        );
+        match contract.draw(lottery_id) {
+            LotteryResult::LottoResult(result) => result,
+            _ => panic!("Expected lotto result"),
+        }
+    }
+
+    /// Every entrant matches all numbers when all of them are picked
+    fn full_range_lotto_config() -> LottoConfig {
+        LottoConfig {
+            duration: U64(1000),
+            pick_count: 3,
+            max_number: 3,
+            paytable: vec![
+                LottoTier { matches: 3, pool_ratio: 7000 },
+                LottoTier { matches: 2, pool_ratio: 3000 }
+            ]
+        }
+    }
+
+    #[test]
+    fn test_lotto_rolls_over_unwon_tiers() {
+        let (mut contract, mut context) = contract_context();
+        change_subs(&mut contract, &mut context);
+        contract.set_lotto_config(Some(full_range_lotto_config()));
+
+        enter_lotto(&mut contract, &mut context, &user("user1"), vec![1, 2, 3], 100);
+        enter_lotto(&mut contract, &mut context, &user("user2"), vec![3, 2, 1], 100);
+        assert_eq!(
+            contract.get_lotto_tickets(0, None, None),
+            vec![
+                LottoTicket { account_id: user("user1"), numbers: vec![1, 2, 3] },
+                LottoTicket { account_id: user("user2"), numbers: vec![1, 2, 3] }
+            ]
+        );
+        // 10% contract fees
+        let round_pool = ONE_NEAR * 9 / 10;
+
+        let result = draw_lotto(&mut contract, &mut context, 0, 1100);
+        let prize_pool = 2 * round_pool;
+        let prize = ratio(prize_pool, 7000) / 2;
+        assert_eq!(result.drawn_numbers, vec![1, 2, 3]);
+        assert_eq!(result.prize_pool, U128(prize_pool));
+        assert_eq!(result.participants_num, 2);
+        assert_eq!(
+            result.tiers.iter().map(|tier| (tier.matches, tier.pool)).collect::<Vec<_>>(),
+            vec![(3, U128(ratio(prize_pool, 7000))), (2, U128(ratio(prize_pool, 3000)))]
+        );
+        assert_eq!(contract.get_cashback_liabilities(), vec![(near(), U128(ratio(prize_pool, 7000) + ratio(prize_pool, 3000)))]);
+
+        // entries are counted, then paid and released
+        assert_eq!(contract.process_cashbacks(Some(2)), 2);
+        // tier without winners rolls over
+        let rolled_over = prize_pool - 2 * prize;
+        assert_eq!(contract.get_lotto_rollover(None, U128(ONE_NEAR)), U128(rolled_over));
+        assert_eq!(contract.get_draw(0).unwrap().tier_winners_nums, vec![2, 0]);
+        assert_eq!(contract.process_cashbacks(None), 2);
+        assert!(contract.get_pending_settlements(None, None).is_empty());
+        assert!(contract.get_cashback_liabilities().is_empty());
+        let transfers = near_transfers();
+        assert!(transfers.contains(&(user("user1"), prize)));
+        assert!(transfers.contains(&(user("user2"), prize)));
+        let tickets = |user2_numbers: Vec<u32>| vec![
+            LottoTicket { account_id: user("user1"), numbers: vec![1, 2, 3] },
+            LottoTicket { account_id: user("user2"), numbers: user2_numbers }
+        ];
+        let verification = contract.verify_lotto_draw(0, tickets(vec![1, 2, 3]));
+        assert!(verification.entries_match);
+        assert!(verification.winners_match);
+        assert_eq!(verification.winners, vec![user("user1"), user("user2")]);
+        // other picked numbers give other winners
+        let verification = contract.verify_lotto_draw(0, tickets(vec![4, 5, 6]));
+        assert!(!verification.entries_match);
+        assert!(!verification.winners_match);
+
+        assert_eq!(enter_lotto(&mut contract, &mut context, &user("user3"), vec![2, 3, 1], 1200), 1);
+        assert_eq!(contract.get_lottery(1).unwrap().lotto_params.unwrap().rollover, U128(rolled_over));
+        let result = draw_lotto(&mut contract, &mut context, 1, 2200);
+        assert_eq!(result.prize_pool, U128(rolled_over + round_pool));
+        assert_eq!(result.tiers[0].pool, U128(ratio(rolled_over + round_pool, 7000)));
+        assert_eq!(contract.process_cashbacks(None), 2);
+        assert!(near_transfers().contains(&(user("user3"), ratio(rolled_over + round_pool, 7000))));
+    }
+
+    #[test]
+    fn test_cancel_lotto_round_waiting_for_draw() {
+        let (mut contract, mut context) = contract_context();
+        change_subs(&mut contract, &mut context);
+        contract.set_lotto_config(Some(full_range_lotto_config()));
+        enter_lotto(&mut contract, &mut context, &user("user1"), vec![1, 2, 3], 100);
+        enter_lotto(&mut contract, &mut context, &user("user2"), vec![1, 2, 3], 100);
+
+        testing_env!(context.block_timestamp(1100).build());
+        assert_eq!(contract.get_lottery(0).unwrap().lottery_status, LotteryStatus::Drawing);
+        owner_env(&mut context);
+        contract.cancel_lottery(0, "draw failed".to_string());
+        assert!(contract.get_lottery(0).is_none());
+
+        testing_env!(context.predecessor_account_id(user("keeper")).attached_deposit(0).build());
+        assert_eq!(contract.process_cashbacks(None), 2);
+        let transfers = near_transfers();
+        for name in ["user1", "user2"] {
+            assert!(transfers.contains(&(user(name), ONE_NEAR)));
+        }
+
+        // cancelled round doesn't block its series
+        assert_eq!(enter_lotto(&mut contract, &mut context, &user("user3"), vec![1, 2, 3], 1200), 1);
+        assert_eq!(contract.get_lotto_tickets(1, None, None).len(), 1);
+    }
+
+    #[test]
+    fn test_lotto_picks_hash() {
+        let (mut contract, mut context) = contract_context();
+        change_subs(&mut contract, &mut context);
+        contract.set_lotto_config(Some(full_range_lotto_config()));
+        for name in ["user1", "user2", "user3"] {
+            enter_lotto(&mut contract, &mut context, &user(name), vec![1, 2, 3], 100);
+        }
+        // the last entry takes place of the left one
+        testing_env!(context.predecessor_account_id(user("user1")).attached_deposit(ONE_YOCTO).build());
+        contract.leave_lottery(0);
+
+        let tickets = || contract.get_lotto_tickets(0, None, None);
+        let (accounts, picks): (Vec<_>, Vec<_>) = tickets().into_iter().map(|ticket| (ticket.account_id, ticket.numbers)).unzip();
+        assert_eq!(accounts, vec![user("user3"), user("user2")]);
+        let tickets = tickets();
+
+        draw_lotto(&mut contract, &mut context, 0, 1100);
+        let record = contract.get_draw(0).unwrap();
+        assert_eq!(record.picks_hash, Some(hash_picks(&accounts, &picks).into()));
+
+        // winners are verified after lotto entries are counted
+        let verification = contract.verify_lotto_draw(0, tickets);
+        assert!(verification.entries_match && !verification.winners_match);
+        contract.process_cashbacks(None);
+        let tickets = accounts
+            .into_iter()
+            .zip(picks)
+            .map(|(account_id, numbers)| LottoTicket { account_id, numbers })
+            .collect();
+        let verification = contract.verify_lotto_draw(0, tickets);
+        assert!(verification.entries_match && verification.winners_match);
+    }
+
+    #[test]
+    #[should_panic(expected = "Numbers must be distinct")]
+    fn test_lotto_repeated_numbers() {
+        let (mut contract, mut context) = contract_context();
+        change_subs(&mut contract, &mut context);
+        contract.set_lotto_config(Some(full_range_lotto_config()));
+
+        enter_lotto(&mut contract, &mut context, &user("user1"), vec![1, 1, 2], 100);
+    }
+
+    /// NFT transfers created in current context as ( nft contract, receiver, token id )
+    fn nft_transfers() -> Vec<(AccountId, AccountId, String)> {
+        near_sdk::test_utils::get_created_receipts()
+            .into_iter()
+            .filter_map(|receipt| match receipt.actions.as_slice() {
+                [near_sdk::mock::VmAction::FunctionCall { function_name, args, .. }] if function_name == "nft_transfer" => {
+                    let args: near_sdk::serde_json::Value = near_sdk::serde_json::from_slice(args).unwrap();
+                    Some((
+                        receipt.receiver_id,
+                        args["receiver_id"].as_str().unwrap().parse().unwrap(),
+                        args["token_id"].as_str().unwrap().to_string()
+                    ))
+                },
+                _ => None,
+            })
+            .collect()
+    }
+
+    fn deposit_raffle_prize(contract: &mut Contract, context: &mut VMContextBuilder, nft_token_id: &str, timestamp: u64) {
+        owner_env(context);
+        contract.set_raffle_config(Some(RaffleConfig {
+            duration: U64(1000),
+            sponsor_ratio: 5000,
+            nft_contracts: vec![token("nft.near")]
+        }));
+        testing_env!(context
+            .predecessor_account_id(token("nft.near"))
+            .attached_deposit(0)
+            .block_timestamp(timestamp)
+            .build()
+        );
+        let msg = near_sdk::serde_json::to_string(&NftReceiverMsg::Raffle {
+            lottery_token_id: None,
+            entry_fee: U128(ONE_NEAR),
+            num_participants: 5
+        }).unwrap();
+        contract.nft_on_transfer(user("sponsor"), user("sponsor"), nft_token_id.to_string(), msg);
+    }
+
+    #[test]
+    fn test_nft_raffle() {
+        let (mut contract, mut context) = contract_context();
+        change_subs(&mut contract, &mut context);
+        deposit_raffle_prize(&mut contract, &mut context, "nft1", 100);
+
+        let lottery = contract.get_lottery(0).unwrap();
+        assert_eq!(lottery.required_pool, U128(5 * ONE_NEAR));
+        assert_eq!(lottery.expires_at, Some(LotteryExpiration::Timestamp(U64(1100))));
+        assert_eq!(lottery.raffle_params.unwrap().sponsor, user("sponsor"));
+
+        for name in ["user1", "user2", "user3", "user4", "user5"] {
+            testing_env!(context
+                .predecessor_account_id(user(name))
+                .attached_deposit(ONE_NEAR)
+                .build()
+            );
+            contract.draw_near_raffle_enter(0, None);
+        }
+        assert_eq!(contract.get_lottery(0).unwrap().lottery_status, LotteryStatus::Drawing);
+
+        testing_env!(context
+            .predecessor_account_id(user("anyone"))
+            .attached_deposit(0)
+            .block_index(1)
+            .build()
+        );
+        let result = match contract.draw(0) {
+            LotteryResult::RaffleResult(result) => result,
+            _ => panic!("Expected raffle result"),
+        };
+        assert!(result.participants.contains(&result.winner));
+        assert_eq!(nft_transfers(), vec![(token("nft.near"), result.winner.clone(), "nft1".to_string())]);
+        // half of entry fees to sponsor, the rest to contract fees
+        assert_eq!(result.sponsor_amount, U128(5 * ONE_NEAR / 2));
+        assert!(near_transfers().contains(&(user("sponsor"), 5 * ONE_NEAR / 2)));
+        assert!(contract.get_lottery(0).is_none());
+        assert!(contract.verify_draw(0, result.participants).winners_match);
+    }
+
+    #[test]
+    fn test_expired_raffle_returns_prize() {
+        let (mut contract, mut context) = contract_context();
+        change_subs(&mut contract, &mut context);
+        deposit_raffle_prize(&mut contract, &mut context, "nft1", 100);
+
+        testing_env!(context
+            .predecessor_account_id(user("user1"))
+            .attached_deposit(ONE_NEAR)
+            .block_timestamp(200)
+            .build()
+        );
+        contract.draw_near_raffle_enter(0, None);
+        testing_env!(context.attached_deposit(ONE_YOCTO).build());
+        contract.leave_lottery(0);
+        // raffle is kept without entries
+        assert_eq!(contract.get_lottery(0).unwrap().lottery_status, LotteryStatus::Active);
+
+        testing_env!(context
+            .predecessor_account_id(user("anyone"))
+            .attached_deposit(0)
+            .block_timestamp(1100)
+            .build()
+        );
+        contract.close_expired_lottery(0);
+        assert_eq!(nft_transfers(), vec![(token("nft.near"), user("sponsor"), "nft1".to_string())]);
+        assert!(contract.get_lottery(0).is_none());
+    }
+
     // TESTS HERE
 }
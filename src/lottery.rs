@@ -1,32 +1,112 @@
-use near_sdk::{require, json_types::U64};
+use near_sdk::{require, json_types::{Base58CryptoHash, U64}, Timestamp};
 
-use crate::{*, views::{LotteryResult, SimpleLotteryResult, BigLotteryResult}};
+use crate::{*, upgrade::LotteryV1, views::{MAX_LISTED_PARTICIPANTS, LotteryResult, SimpleLotteryResult, BigLotteryResult, TimedLotteryResult, JackpotLotteryResult, LottoResult, RaffleResult, TierWinners}};
 
 pub const ONE_PERCENT_RATIO:u32 = MAX_RATIO / 100;
 
 pub const SIMPLE_LOTTERY:&str = "SIMPLE_LOTTERY";
 pub const BIG_LOTTERY:&str = "BIG_LOTTERY";
+pub const TIMED_LOTTERY:&str = "TIMED_LOTTERY";
+pub const JACKPOT_LOTTERY:&str = "JACKPOT_LOTTERY";
+pub const LOTTO:&str = "LOTTO";
+pub const RAFFLE:&str = "RAFFLE";
 
 #[derive(BorshSerialize, BorshDeserialize, Serialize)]
 #[serde(crate = "near_sdk::serde")]
+#[allow(clippy::enum_variant_names)]
 pub enum Lottery {
     SimpleLottery(SimpleLottery),
-    Lottery(BigLottery)
+    Lottery(BigLottery),
+    TimedLottery(TimedLottery),
+    JackpotLottery(JackpotLottery),
+    Lotto(Lotto),
+    Raffle(Raffle)
 }  
 
-// #[derive(BorshSerialize, BorshDeserialize, Serialize, Clone)]
-// //#[cfg_attr(not(target_arch = "wasm32"), derive(Clone))]
-// #[serde(crate = "near_sdk::serde")]
-// pub struct Entry{
-//     pub account_id: AccountId,
-//     pub referrer_id: Option<AccountId>
-// }
+/// Lottery as it is kept in the contract state.
+/// New layouts are added as new variants, so stored lotteries survive upgrades
+#[derive(BorshSerialize, BorshDeserialize)]
+#[allow(clippy::large_enum_variant)]
+pub enum VersionedLottery {
+    V1(LotteryV1),
+    V2(Lottery)
+}
+
+impl From<VersionedLottery> for Lottery {
+    fn from(lottery: VersionedLottery) -> Self {
+        match lottery {
+            VersionedLottery::V1(lottery) => lottery.into(),
+            VersionedLottery::V2(lottery) => lottery,
+        }
+    }
+}
+
+impl From<Lottery> for VersionedLottery {
+    fn from(lottery: Lottery) -> Self {
+        VersionedLottery::V2(lottery)
+    }
+}
+
+/// Tickets of account in lottery
+#[derive(BorshSerialize, BorshDeserialize, Serialize, Deserialize, PartialEq, Clone, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct Entry {
+    pub account_id: AccountId,
+    pub tickets: u32
+}
+
+/// Holder of every ticket in ticket order, tickets of an entry go in a row.
+/// Winners are picked from this list, so accounts are weighted by tickets num
+pub fn ticket_holders<'a>(entries: impl IntoIterator<Item = &'a Entry>) -> Vec<AccountId> {
+    entries
+        .into_iter()
+        .flat_map(|entry| std::iter::repeat(entry.account_id.clone()).take(entry.tickets as usize))
+        .collect()
+}
+
+/// Checks that account holding `held` tickets can buy `tickets` more
+pub fn assert_tickets_allowed(held: u32, tickets: u32, max_tickets: u32, tickets_left: u32) {
+    if max_tickets == 1 {
+        assert_eq!(held, 0, "Already entered");
+    }
+    assert!(held + tickets <= max_tickets, "Account can hold at most {} tickets", max_tickets);
+    assert!(tickets <= tickets_left, "Only {} tickets left", tickets_left);
+}
 
 #[derive(BorshSerialize, BorshDeserialize, Serialize, PartialEq, Copy, Clone, Debug)]
 #[serde(crate = "near_sdk::serde")]
 pub enum LotteryStatus {
     Active,
-    Finished
+    Finished,
+    Cancelled,
+    /// pool is filled, waiting for `draw` in a later block
+    Drawing
+}
+
+/// Entrant choices passed along with entry
+pub struct EntryOptions {
+    pub referrer_id: Option<AccountId>,
+    /// sha256 of entrant secret for `CommitReveal` draw mode
+    pub secret_hash: Option<CryptoHash>,
+    /// tickets bought by entry, attached amount is split between them
+    pub tickets: u32,
+    /// numbers picked for lotto entry
+    pub numbers: Option<Vec<u32>>
+}
+
+/// Record of lottery cancelled by owner
+#[derive(BorshSerialize, BorshDeserialize, Serialize, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct LotteryCancellation {
+    pub lottery_id: U64,
+    pub reason: String,
+    pub lottery_token_id: AccountId,
+    /// refunded entries, see `hash_entries`
+    pub entries_num: u32,
+    pub tickets_num: u32,
+    pub entries_hash: Base58CryptoHash,
+    pub entry_fee: U128,
+    pub timestamp: U64
 }
 
 impl Lottery {
@@ -34,6 +114,21 @@ impl Lottery {
         match self {
             Lottery::SimpleLottery(_) => SIMPLE_LOTTERY.into(),
             Lottery::Lottery(_) => BIG_LOTTERY.into(),
+            Lottery::TimedLottery(_) => TIMED_LOTTERY.into(),
+            Lottery::JackpotLottery(_) => JACKPOT_LOTTERY.into(),
+            Lottery::Lotto(_) => LOTTO.into(),
+            Lottery::Raffle(_) => RAFFLE.into(),
+        }
+    }
+
+    pub fn lottery_type(&self) -> LotteryType {
+        match self {
+            Lottery::SimpleLottery(_) => LotteryType::SimpleLottery,
+            Lottery::Lottery(_) => LotteryType::BigLottery,
+            Lottery::TimedLottery(_) => LotteryType::TimedLottery,
+            Lottery::JackpotLottery(_) => LotteryType::JackpotLottery,
+            Lottery::Lotto(_) => LotteryType::Lotto,
+            Lottery::Raffle(_) => LotteryType::Raffle,
         }
     }
 
@@ -41,6 +136,10 @@ impl Lottery {
         match self {
             Lottery::SimpleLottery(lottery) => lottery.id,
             Lottery::Lottery(lottery) => lottery.id,
+            Lottery::TimedLottery(lottery) => lottery.id,
+            Lottery::JackpotLottery(lottery) => lottery.id,
+            Lottery::Lotto(lottery) => lottery.id,
+            Lottery::Raffle(lottery) => lottery.id,
         }
     }
 
@@ -48,13 +147,22 @@ impl Lottery {
         match self {
             Lottery::SimpleLottery(lottery) => lottery.lottery_status,
             Lottery::Lottery(lottery) => lottery.lottery_status,
+            Lottery::TimedLottery(lottery) => lottery.lottery_status,
+            Lottery::JackpotLottery(lottery) => lottery.lottery_status,
+            Lottery::Lotto(lottery) => lottery.lottery_status,
+            Lottery::Raffle(lottery) => lottery.lottery_status,
         }
     }
 
-    pub fn num_participants(&self) -> u32 {
+    /// Required num of participants, timed lotteries have no such limit
+    pub fn num_participants(&self) -> Option<u32> {
         match self {
-            Lottery::SimpleLottery(lottery) => (lottery.required_pool / lottery.entry_fee) as _,
-            Lottery::Lottery(lottery) => (lottery.required_pool / lottery.entry_fee) as _,
+            Lottery::SimpleLottery(lottery) => Some((lottery.required_pool / lottery.entry_fee) as _),
+            Lottery::Lottery(lottery) => Some((lottery.required_pool / lottery.entry_fee) as _),
+            Lottery::TimedLottery(_) => None,
+            Lottery::JackpotLottery(_) => None,
+            Lottery::Lotto(_) => None,
+            Lottery::Raffle(lottery) => Some((lottery.required_pool / lottery.entry_fee) as _),
         }
     }
 
@@ -62,6 +170,10 @@ impl Lottery {
         match self {
             Lottery::SimpleLottery(lottery) => lottery.entry_fee,
             Lottery::Lottery(lottery) => lottery.entry_fee,
+            Lottery::TimedLottery(lottery) => lottery.entry_fee,
+            Lottery::JackpotLottery(lottery) => lottery.entry_fee,
+            Lottery::Lotto(lottery) => lottery.entry_fee,
+            Lottery::Raffle(lottery) => lottery.entry_fee,
         }
     }
 
@@ -69,6 +181,141 @@ impl Lottery {
         match self {
             Lottery::SimpleLottery(lottery) => &lottery.lottery_token_id,
             Lottery::Lottery(lottery) => &lottery.lottery_token_id,
+            Lottery::TimedLottery(lottery) => &lottery.lottery_token_id,
+            Lottery::JackpotLottery(lottery) => &lottery.lottery_token_id,
+            Lottery::Lotto(lottery) => &lottery.lottery_token_id,
+            Lottery::Raffle(lottery) => &lottery.lottery_token_id,
+        }
+    }
+
+    pub fn entries_num(&self) -> u32 {
+        match self {
+            Lottery::SimpleLottery(lottery) => lottery.entries.len() as _,
+            Lottery::Lottery(lottery) => lottery.entries.len(),
+            Lottery::TimedLottery(lottery) => lottery.entries.len(),
+            Lottery::JackpotLottery(lottery) => lottery.entries.len(),
+            Lottery::Lotto(lottery) => lottery.entries.len(),
+            Lottery::Raffle(lottery) => lottery.entries.len(),
+        }
+    }
+
+    pub fn tickets_num(&self) -> u32 {
+        match self {
+            Lottery::SimpleLottery(lottery) => lottery.entries.iter().map(|entry| entry.tickets).sum(),
+            Lottery::Lottery(lottery) => lottery.entries.tickets_num(),
+            Lottery::TimedLottery(lottery) => lottery.entries.tickets_num(),
+            Lottery::JackpotLottery(lottery) => lottery.entries.tickets_num(),
+            Lottery::Lotto(lottery) => lottery.entries.tickets_num(),
+            Lottery::Raffle(lottery) => lottery.entries.tickets_num(),
+        }
+    }
+
+    pub fn entries_hash(&self) -> CryptoHash {
+        match self {
+            Lottery::SimpleLottery(lottery) => hash_entries(&lottery.entries),
+            Lottery::Lottery(lottery) => lottery.entries.hash(),
+            Lottery::TimedLottery(lottery) => lottery.entries.hash(),
+            Lottery::JackpotLottery(lottery) => lottery.entries.hash(),
+            Lottery::Lotto(lottery) => lottery.entries.hash(),
+            Lottery::Raffle(lottery) => lottery.entries.hash(),
+        }
+    }
+
+    pub fn fees(&self) -> &LotteryFees {
+        match self {
+            Lottery::SimpleLottery(lottery) => &lottery.fees,
+            Lottery::Lottery(lottery) => &lottery.fees,
+            Lottery::TimedLottery(lottery) => &lottery.fees,
+            Lottery::JackpotLottery(lottery) => &lottery.fees,
+            Lottery::Lotto(lottery) => &lottery.fees,
+            Lottery::Raffle(lottery) => &lottery.fees,
+        }
+    }
+
+    pub fn referrals(&self) -> &HashMap<AccountId, Balance> {
+        match self {
+            Lottery::SimpleLottery(lottery) => &lottery.referrals,
+            Lottery::Lottery(lottery) => &lottery.referrals,
+            Lottery::TimedLottery(lottery) => &lottery.referrals,
+            Lottery::JackpotLottery(lottery) => &lottery.referrals,
+            Lottery::Lotto(lottery) => &lottery.referrals,
+            Lottery::Raffle(lottery) => &lottery.referrals,
+        }
+    }
+
+    pub fn is_expired(&self) -> bool {
+        match self {
+            Lottery::SimpleLottery(lottery) => lottery.is_expired(),
+            Lottery::Lottery(lottery) => lottery.is_expired(),
+            Lottery::TimedLottery(_) => false,
+            Lottery::JackpotLottery(_) => false,
+            Lottery::Lotto(_) => false,
+            Lottery::Raffle(lottery) => lottery.is_expired(),
+        }
+    }
+
+    pub fn cancel(&mut self) {
+        match self {
+            Lottery::SimpleLottery(lottery) => lottery.cancel(),
+            Lottery::Lottery(lottery) => lottery.cancel(),
+            Lottery::TimedLottery(lottery) => lottery.cancel(),
+            Lottery::JackpotLottery(lottery) => lottery.cancel(),
+            Lottery::Lotto(lottery) => lottery.cancel(),
+            Lottery::Raffle(lottery) => lottery.cancel(),
+        }
+    }
+
+    pub fn leave(&mut self, account_id: &AccountId) -> Balance {
+        match self {
+            Lottery::SimpleLottery(lottery) => lottery.leave(account_id),
+            Lottery::Lottery(lottery) => lottery.leave(account_id),
+            Lottery::TimedLottery(lottery) => lottery.leave(account_id),
+            Lottery::JackpotLottery(lottery) => lottery.leave(account_id),
+            Lottery::Lotto(lottery) => lottery.leave(account_id),
+            Lottery::Raffle(lottery) => lottery.leave(account_id),
+        }
+    }
+
+    /// Entries are closed at draw time of timed, jackpot and lotto lotteries
+    pub fn draw_at(&self) -> Option<Timestamp> {
+        match self {
+            Lottery::TimedLottery(lottery) => Some(lottery.draw_at),
+            Lottery::JackpotLottery(lottery) => Some(lottery.draw_at),
+            Lottery::Lotto(lottery) => Some(lottery.draw_at),
+            _ => None,
+        }
+    }
+
+    pub fn draw_record(&self) -> Option<&DrawRecord> {
+        match self {
+            Lottery::SimpleLottery(lottery) => lottery.draw_record.as_ref(),
+            Lottery::Lottery(lottery) => lottery.draw_record.as_ref(),
+            Lottery::TimedLottery(lottery) => lottery.draw_record.as_ref(),
+            Lottery::JackpotLottery(lottery) => lottery.draw_record.as_ref(),
+            Lottery::Lotto(lottery) => lottery.draw_record.as_ref(),
+            Lottery::Raffle(lottery) => lottery.draw_record.as_ref(),
+        }
+    }
+
+    pub fn reveal_secret(&mut self, account_id: &AccountId, secret: &str) {
+        match self {
+            Lottery::SimpleLottery(lottery) => lottery.reveal_secret(account_id, secret),
+            Lottery::Lottery(lottery) => lottery.reveal_secret(account_id, secret),
+            Lottery::TimedLottery(_) => panic!("Timed lottery has no reveal window"),
+            Lottery::JackpotLottery(_) => panic!("Jackpot lottery has no reveal window"),
+            Lottery::Lotto(_) => panic!("Lotto has no reveal window"),
+            Lottery::Raffle(_) => panic!("Raffle has no reveal window"),
+        }
+    }
+
+    pub fn draw(&mut self) -> LotteryStatus {
+        match self {
+            Lottery::SimpleLottery(lottery) => lottery.draw(),
+            Lottery::Lottery(lottery) => lottery.draw(),
+            Lottery::TimedLottery(lottery) => lottery.draw(),
+            Lottery::JackpotLottery(lottery) => lottery.draw(),
+            Lottery::Lotto(lottery) => lottery.draw(),
+            Lottery::Raffle(lottery) => lottery.draw(),
         }
     }
 
@@ -80,6 +327,18 @@ impl Lottery {
             Lottery::Lottery(lottery) => {
                 lottery.update()
             },
+            Lottery::TimedLottery(lottery) => {
+                lottery.update()
+            },
+            Lottery::JackpotLottery(lottery) => {
+                lottery.update()
+            },
+            Lottery::Lotto(lottery) => {
+                lottery.update()
+            },
+            Lottery::Raffle(lottery) => {
+                lottery.update()
+            },
         }
     }
 }
@@ -101,24 +360,33 @@ impl Contract {
             format!("Incorrect subaccount. Accepted subs is {}", accepted_subs)
         );
     }
+    /// Active lottery accepting entries with given parameters.
+    /// `num_participants` is ignored for timed lotteries
     pub (crate) fn internal_get_lottery_by_parameters(
         &self,
         token_id: &AccountId,
+        lottery_type: LotteryType,
         num_participants: u32,
         entry_fee: Balance
     ) -> Option<Lottery> {
         self.lotteries
             .values()
+            .map(Lottery::from)
             .find(|lottery| {
-                lottery.entry_fee() == entry_fee 
-                    && lottery.num_participants() == num_participants
+                lottery.lottery_type() == lottery_type
+                    && lottery.status() == LotteryStatus::Active
+                    && lottery.entry_fee() == entry_fee 
+                    && lottery.num_participants().map_or(true, |num| num == num_participants)
                         && lottery.lottery_token_id() == token_id
+                            && !lottery.is_expired()
+                            && lottery.draw_at().map_or(true, |draw_at| env::block_timestamp() < draw_at)
             })
     }
 
     pub (crate) fn internal_get_lottery(&self, lottery_id: LotteryId) -> Option<Lottery> {
         self.lotteries
             .get(&lottery_id)
+            .map(Lottery::from)
             .map(|mut lottery| {
                 //always updated status
                 lottery.update();
@@ -130,28 +398,25 @@ impl Contract {
     //     self.internal_get_lottery(lottery_id).expect("Lottery was not found")
     // }
 
-    pub (crate) fn update_cashback_storage(&mut self) {
-        let cashback_accounts = self.cashback_accounts.to_vec();
-        for (token_id, stored_cashback) in cashback_accounts.iter() {
-            if token_id == &near() {
-                for account in &stored_cashback.accounts {
-                    //log!("Cashback transfered ( {} yoctoNEAR ) to @{}", stored_cashback.amount, account);
-                    Promise::new(account.clone()).transfer(stored_cashback.amount);
-                }
-            } else {
-                for account in &stored_cashback.accounts {
-                    //log!("Cashback transfered ( {} yocto {} ) to @{}", token_id, stored_cashback.amount, account);
-                    self.internal_ft_transfer(account, token_id, stored_cashback.amount);
-                }
-            }
-        }
-    }
-
     pub (crate) fn internal_set_lottery(&mut self, lottery_id: &LotteryId, lottery: Lottery) {
-        if !self.cashback_accounts.is_empty() {
-            self.update_cashback_storage();
+        if !self.cashbacks.is_empty() || !self.settlements.is_empty() {
+            self.internal_process_cashbacks(CASHBACK_BATCH_ON_ENTER);
         }    
-        self.lotteries.insert(lottery_id, &lottery);
+        self.lotteries.insert(lottery_id, &lottery.into());
+    }
+
+    /// Removes drawn or closed lottery, next round of its series can be opened
+    pub (crate) fn internal_remove_lottery(&mut self, lottery: &Lottery) {
+        match lottery {
+            Lottery::JackpotLottery(lottery) => {
+                self.open_jackpot_rounds.remove(&lottery.series());
+            },
+            Lottery::Lotto(lottery) => {
+                self.open_lotto_rounds.remove(&lottery.series());
+            },
+            _ => {},
+        }
+        self.lotteries.remove(&lottery.get_id());
     }
 
     pub fn draw_enter(
@@ -160,11 +425,15 @@ impl Contract {
         lottery_token_id: AccountId,
         lottery_type: LotteryType,
         num_participants: u32,
-        entry_fee: Balance,
-        referrer_id: Option<AccountId>
+        amount: Balance,
+        options: EntryOptions
     ) -> LotteryId {
+        let EntryOptions { referrer_id, secret_hash, tickets, numbers } = options;
+        assert!(tickets > 0, "Tickets num cannot be zero");
+        assert_eq!(amount % tickets as u128, 0, "Amount must be split equally between tickets");
+        let entry_fee = amount / tickets as u128;
 
-        let lottery = match self.internal_get_lottery_by_parameters(&lottery_token_id, num_participants, entry_fee) {
+        let lottery = match self.internal_get_lottery_by_parameters(&lottery_token_id, lottery_type, num_participants, entry_fee) {
             Some(lottery) => lottery,
             None => {
                 self.add_new_lottery(
@@ -179,60 +448,159 @@ impl Contract {
         let lottery_id = lottery.get_id();
         match lottery {
             Lottery::SimpleLottery(mut simple_lottery) => {
-                let lottery_status = simple_lottery.draw_enter(entry_account_id, entry_fee);
+                let lottery_status = simple_lottery.draw_enter(entry_account_id, amount, tickets, secret_hash);
                 
                 if let Some(refferer) = referrer_id {
-                    let referrer_reward = ratio(entry_fee, ONE_PERCENT_RATIO);
-                    if lottery_token_id == near() {
-                        Promise::new(refferer).transfer(referrer_reward);
-                    } else {
-                        self.internal_ft_transfer(&refferer, &lottery_token_id, referrer_reward);
-                    }
-                    simple_lottery.add_refferal_transfered(referrer_reward);
+                    let referrer_reward = ratio(amount, simple_lottery.fees.referral_ratio);
+                    self.internal_payout(&refferer, &lottery_token_id, referrer_reward);
+                    simple_lottery.add_refferal_transfered(entry_account_id, referrer_reward);
                 }
 
                 match lottery_status {
                     // user was last for that lottery. Need to distribute reward                   
                     LotteryStatus::Finished => {
                         let lottery_result = self.distribute(Lottery::SimpleLottery(simple_lottery));
-                        log!("{:#?}", lottery_result);
+                        lottery_result.log();
                         self.lotteries.remove(&lottery_id);
                     },
                     // user just created entry for that lottery
                     LotteryStatus::Active => {
                         self.internal_set_lottery(&lottery_id, Lottery::SimpleLottery(simple_lottery))
-                    }
+                    },
+                    // user was last for that lottery. Winners are picked by `draw` later
+                    LotteryStatus::Drawing => {
+                        log!("Lottery {} is filled and waiting for draw", lottery_id);
+                        self.internal_set_lottery(&lottery_id, Lottery::SimpleLottery(simple_lottery))
+                    },
+                    LotteryStatus::Cancelled => panic!("Lottery is cancelled")
                 } 
                 lottery_id
             },
             Lottery::Lottery(mut big_lottery) => {
-                let lottery_status = big_lottery.draw_enter(entry_account_id, entry_fee);
+                let lottery_status = big_lottery.draw_enter(entry_account_id, amount, tickets, secret_hash);
                 
                 if let Some(refferer) = referrer_id {
-                    let referrer_reward = ratio(entry_fee, ONE_PERCENT_RATIO);
-                    if lottery_token_id == near() {
-                        Promise::new(refferer).transfer(referrer_reward);
-                    } else {
-                        self.internal_ft_transfer(&refferer, &lottery_token_id, referrer_reward);
-                    }
-                    big_lottery.add_refferal_transfered(referrer_reward);
+                    let referrer_reward = ratio(amount, big_lottery.fees.referral_ratio);
+                    self.internal_payout(&refferer, &lottery_token_id, referrer_reward);
+                    big_lottery.add_refferal_transfered(entry_account_id, referrer_reward);
                 }
 
                 match lottery_status {
                     // user was last for that lottery. Need to distribute reward                   
                     LotteryStatus::Finished => {
                         let lottery_result = self.distribute(Lottery::Lottery(big_lottery));
-                        log!("{:#?}", lottery_result);
+                        lottery_result.log();
                         self.lotteries.remove(&lottery_id);
                     },
                     // user just created entry for that lottery
                     LotteryStatus::Active => {
                         self.internal_set_lottery(&lottery_id, Lottery::Lottery(big_lottery))
-                    }
+                    },
+                    // user was last for that lottery. Winners are picked by `draw` later
+                    LotteryStatus::Drawing => {
+                        log!("Lottery {} is filled and waiting for draw", lottery_id);
+                        self.internal_set_lottery(&lottery_id, Lottery::Lottery(big_lottery))
+                    },
+                    LotteryStatus::Cancelled => panic!("Lottery is cancelled")
                 } 
                 lottery_id
             },
+            Lottery::TimedLottery(mut timed_lottery) => {
+                assert_eq!(tickets, 1, "Timed lottery entry is one ticket");
+                timed_lottery.draw_enter(entry_account_id, entry_fee);
+
+                if let Some(refferer) = referrer_id {
+                    let referrer_reward = ratio(entry_fee, timed_lottery.fees.referral_ratio);
+                    self.internal_payout(&refferer, &lottery_token_id, referrer_reward);
+                    timed_lottery.add_refferal_transfered(entry_account_id, referrer_reward);
+                }
+
+                // winner is picked by `draw` after draw time
+                self.internal_set_lottery(&lottery_id, Lottery::TimedLottery(timed_lottery));
+                lottery_id
+            },
+            Lottery::JackpotLottery(mut jackpot_lottery) => {
+                assert_eq!(tickets, 1, "Jackpot lottery entry is one ticket");
+                jackpot_lottery.draw_enter(entry_account_id, entry_fee);
+
+                if let Some(refferer) = referrer_id {
+                    let referrer_reward = ratio(entry_fee, jackpot_lottery.fees.referral_ratio);
+                    self.internal_payout(&refferer, &lottery_token_id, referrer_reward);
+                    jackpot_lottery.add_refferal_transfered(entry_account_id, referrer_reward);
+                }
+
+                // ticket number is drawn by `draw` after draw time
+                self.internal_set_lottery(&lottery_id, Lottery::JackpotLottery(jackpot_lottery));
+                lottery_id
+            },
+            Lottery::Lotto(mut lotto) => {
+                assert_eq!(tickets, 1, "Lotto entry is one ticket");
+                lotto.draw_enter(entry_account_id, entry_fee, numbers.expect("Lotto numbers are required"));
+
+                if let Some(refferer) = referrer_id {
+                    let referrer_reward = ratio(entry_fee, lotto.fees.referral_ratio);
+                    self.internal_payout(&refferer, &lottery_token_id, referrer_reward);
+                    lotto.add_refferal_transfered(entry_account_id, referrer_reward);
+                }
+
+                // numbers are drawn by `draw` after draw time
+                self.internal_set_lottery(&lottery_id, Lottery::Lotto(lotto));
+                lottery_id
+            },
+            Lottery::Raffle(_) => panic!("Raffle is entered by lottery id"),
+        }
+    }
+
+    /// Refunds entry fees of not finished lottery.
+    /// Referral rewards already paid from entries are covered by collected contract fees if possible.
+    /// Otherwise rewards are deducted from refunds of referred entrants.
+    /// Entries kept outside the lottery are refunded and released in batches, see `StoredSettlement`
+    pub (crate) fn internal_refund_entries(&mut self, lottery: Lottery) {
+        let lottery_id = lottery.get_id();
+        let lottery_token_id = lottery.lottery_token_id().clone();
+        let entry_fee = lottery.entry_fee();
+        let tickets_num = lottery.tickets_num();
+
+        let refferal_transfered: Balance = lottery.referrals().values().sum();
+        let referrals_covered = refferal_transfered > 0 
+            && self.internal_available_fees(&lottery_token_id) >= refferal_transfered;
+        if referrals_covered {
+            let collected = self.fees.get(&lottery_token_id).unwrap_or_default();
+            self.fees.insert(&lottery_token_id, &(collected - refferal_transfered));
+            log!("Referral rewards ( {} yocto{} ) covered by contract fees", refferal_transfered, match_token_id(&lottery_token_id));
+        }
+        let (referrals, deducted) = if referrals_covered {
+            (HashMap::new(), 0)
+        } else {
+            (lottery.referrals().clone(), refferal_transfered)
+        };
+        let payout = EntriesPayout::Refund { entry_fee, referrals };
+        let pending_amount = (entry_fee * tickets_num as u128).saturating_sub(deducted);
+
+        let (entries, picks) = match lottery {
+            Lottery::SimpleLottery(lottery) => {
+                // entries of simple lottery are bounded by its participants num, they are refunded at once
+                for (index, entry) in lottery.entries.iter().enumerate() {
+                    let (refund, _) = payout.entry_payout(index as _, entry, None);
+                    if refund > 0 {
+                        self.internal_payout(&entry.account_id, &lottery_token_id, refund);
+                    }
+                }
+                return;
+            },
+            Lottery::Lottery(lottery) => (lottery.entries, None),
+            Lottery::TimedLottery(lottery) => (lottery.entries, None),
+            Lottery::JackpotLottery(lottery) => (lottery.entries, None),
+            Lottery::Lotto(lottery) => (lottery.entries, Some(lottery.picks)),
+            Lottery::Raffle(lottery) => (lottery.entries, None),
+        };
+        if entries.is_empty() {
+            return;
         }
+        self.settlements.insert(
+            &lottery_id,
+            &StoredSettlement::new(lottery_token_id, entries, picks, payout, pending_amount)
+        );
     }
 
     pub fn deposit_fees(&mut self, token_id: &AccountId, amount: Balance) {
@@ -241,66 +609,82 @@ impl Contract {
         self.fees.insert(token_id, &fee_amount);
     }
 
+    /// Takes fees of finished lottery, `reward` of `pool` goes to winners and the rest is contract fees.
+    /// Referral rewards paid on entries are deducted from contract fees,
+    /// then treasury and investor take their shares of contract fees.
+    /// Returns contract fees deposited
+    pub (crate) fn internal_distribute_fees(
+        &mut self,
+        fees: &LotteryFees,
+        lottery_token_id: &AccountId,
+        pool: Balance,
+        reward: Balance,
+        refferal_transfered: Balance
+    ) -> Balance {
+        let mut contract_fees = pool - reward;
+        if refferal_transfered > 0 {
+            assert!(contract_fees >= refferal_transfered, "Refferal's reward cannot be greater than contract fees");
+            contract_fees -= refferal_transfered;
+        }
+        let treasury_fees = fees.get_treasury_taken_amount(contract_fees);
+        let investor_fees = fees.get_investor_taken_amount(contract_fees);
+        assert!(
+            contract_fees >= treasury_fees + investor_fees, 
+            "Contract fees cannot be less than treasury & investor fees"
+        );
+        contract_fees -= treasury_fees + investor_fees;
+
+        if treasury_fees > 0 {
+            self.internal_payout(&fees.treasury, lottery_token_id, treasury_fees);
+        }
+
+        if investor_fees > 0 {
+            self.internal_payout(&fees.investor, lottery_token_id, investor_fees);
+        }
+
+        if contract_fees > 0 {
+            self.deposit_fees(lottery_token_id, contract_fees);
+        }
+        contract_fees
+    }
+
     pub fn distribute(&mut self, lottery: Lottery) -> LotteryResult {
+        let claim_mode = self.is_claim_mode(lottery.lottery_token_id(), lottery.lottery_type());
+        if let Some(draw_record) = lottery.draw_record() {
+            self.draws.insert(&lottery.get_id(), draw_record);
+        }
         match lottery {
             Lottery::SimpleLottery(lottery) => {
                 lottery.assert_is_finished();
                 let lottery_token_id = lottery.lottery_token_id.clone();
 
-                let winner_id = lottery.get_winner_unwrap();
-
                 let reward = lottery.current_pool;
-                let mut contract_fees = ratio(reward, self.get_contract_fee_ratio());
+                let contract_fees = ratio(reward, lottery.fees.contract_fee_ratio);
                 assert!(reward > contract_fees, "Reward cannot be less than contract fees");
                 // take contract fees from reward
                 let reward_fees_taken = reward - contract_fees;
-                if lottery.refferal_transfered > 0 {
-                    assert!(contract_fees > lottery.refferal_transfered, "Refferal's reward cannot be greater than contract fees");
-                    contract_fees -= lottery.refferal_transfered;
-                }
-                let treasury_fees = self.get_treasury_taken_amount(contract_fees);
-                let investor_fees = self.get_investor_taken_amount(contract_fees);
-                assert!(
-                    contract_fees >= treasury_fees + investor_fees, 
-                    "Contract fees cannot be less than treasury & investor fees"
-                );
-                contract_fees -= treasury_fees + investor_fees;
 
                 // transfer all fees & reward
-                if lottery_token_id == near() {
-                    //todo - add callback here
-                    Promise::new(winner_id.clone()).transfer(reward_fees_taken);
-
-                    if treasury_fees > 0 {
-                        Promise::new(self.treasury()).transfer(treasury_fees);
-                    }
-    
-                    if investor_fees > 0 {
-                        Promise::new(self.investor()).transfer(investor_fees);
-                    } 
-                } else {
-                    //todo - add callback here
-                    self.internal_ft_transfer(&winner_id, &lottery_token_id, reward_fees_taken);
-
-                    if treasury_fees > 0 {
-                        self.internal_ft_transfer(&winner_id, &lottery_token_id, treasury_fees);
-                    }
-    
-                    if investor_fees > 0 {
-                        self.internal_ft_transfer(&winner_id, &lottery_token_id, investor_fees);
-                    } 
-                }  
-
-                if contract_fees > 0 {
-                    self.deposit_fees(&lottery_token_id, contract_fees);
+                let winners = lottery.ranked_winners(reward_fees_taken);
+                for winner in &winners {
+                    self.internal_reward(&winner.account_id, &lottery_token_id, winner.amount.0, claim_mode);
                 }
+
+                let contract_fees = self.internal_distribute_fees(
+                    &lottery.fees,
+                    &lottery_token_id,
+                    reward,
+                    reward_fees_taken,
+                    lottery.refferal_transfered
+                );
                 
                 LotteryResult::SimpleLotteryResult( 
                     SimpleLotteryResult {
                         lottery_id: U64(lottery.id),
                         lottery_token_id,
-                        participants: lottery.entries,
-                        winner: winner_id,
+                        participants: ticket_holders(&lottery.entries),
+                        winner: winners[0].account_id.clone(),
+                        winners,
                         winning_amount: U128(reward_fees_taken),
                         contract_fee: U128(contract_fees),
                     }
@@ -310,100 +694,238 @@ impl Contract {
                 lottery.assert_is_finished();
                 let lottery_token_id = lottery.lottery_token_id.clone();
 
-                let reward_fifty_percents_up = lottery.entry_fee + lottery.entry_fee / 2;
-                let reward_ten_percents_up = lottery.entry_fee + lottery.entry_fee / 10;
-                let cashback = lottery.entry_fee / 2;
+                let exact_reward = lottery.prize_table.payout(lottery.entry_fee, &lottery.tier_winners_nums);
+                assert!(lottery.current_pool > exact_reward, "Current pool amount must be greater than exact transfered reward");
 
-                let exact_reward = 
-                    reward_fifty_percents_up * lottery.fifty_percent_winners_num as u128
-                        + reward_ten_percents_up * lottery.ten_percent_winners_num as u128
-                            + cashback * lottery.cashbacked_num as u128;
 
-                assert!(lottery.current_pool > exact_reward, "Current pool amount must be greater than exact transfered reward");
+                let cashback_tier = lottery.prize_table.cashback_tier();
+                let rest_tier = lottery.prize_table.covers_all().then(|| lottery.prize_table.tiers.len() - 1);
+                let mut tiers = vec![];
+                for (tier_index, tier) in lottery.prize_table.tiers.iter().enumerate() {
+                    let amount = lottery.prize_table.tier_payout(tier_index, lottery.entry_fee);
+                    let tier_winners = &lottery.winners[tier_index];
+                    let winners_num = lottery.tier_winners_nums[tier_index];
 
-                let mut contract_fees = lottery.current_pool - exact_reward;
+                    if rest_tier == Some(tier_index) {
+                        log!(
+                            "Reward {} ( {} yocto{} ) to {} accounts is paid in batches",
+                            tier.name, amount, match_token_id(&lottery_token_id), winners_num
+                        );
+                    } else if cashback_tier == Some(tier_index) && !claim_mode {
+                        log!("total cashbacked accounts: {}", tier_winners.len());
+                        self.cashbacks.insert(
+                            &lottery.id, 
+                            &StoredCashback::new(lottery_token_id.clone(), amount, tier_winners)
+                        );
+                    } else {
+                        for account in tier_winners {
+                            self.internal_reward(account, &lottery_token_id, amount, claim_mode);
+                        }
+                        log!(
+                            "Reward {} ( {} yocto{} ) to {} accounts",
+                            tier.name, amount, match_token_id(&lottery_token_id), tier_winners.len()
+                        );
+                    }
 
-                if lottery.refferal_transfered > 0 {
-                    assert!(contract_fees > lottery.refferal_transfered, "Refferal's reward cannot be greater than contract fees");
-                    contract_fees -= lottery.refferal_transfered;
+                    tiers.push(TierWinners {
+                        name: tier.name.clone(),
+                        amount: U128(amount),
+                        winners_num,
+                        winners: tier_winners.clone()
+                    });
                 }
-                
-                let treasury_fees = self.get_treasury_taken_amount(contract_fees);
-                let investor_fees = self.get_investor_taken_amount(contract_fees);
 
-                assert!(exact_reward > contract_fees, "Exact Reward cannot be less than contract fees");
-                assert!(
-                    contract_fees > treasury_fees + investor_fees, 
-                    "Contract fees cannot be less than treasury & investor fees"
-                );
+                // entries are released in batches, along with payouts of the rest tier if there is one
+                let (payout, pending_amount) = match rest_tier {
+                    Some(tier_index) => {
+                        let amount = lottery.prize_table.tier_payout(tier_index, lottery.entry_fee);
+                        let winner_indices = lottery.draw_record
+                            .as_ref()
+                            .map(|draw_record| draw_record.winner_indices.clone())
+                            .unwrap_or_default();
+                        (
+                            EntriesPayout::RestTier { amount, claim_mode, winner_indices },
+                            amount * lottery.tier_winners_nums[tier_index] as u128
+                        )
+                    },
+                    None => (EntriesPayout::Release, 0),
+                };
 
-                contract_fees -= treasury_fees + investor_fees;
+                let contract_fees = self.internal_distribute_fees(
+                    &lottery.fees,
+                    &lottery_token_id,
+                    lottery.current_pool,
+                    exact_reward,
+                    lottery.refferal_transfered
+                );
 
-                let cashbacked_accounts = lottery.get_winners(WinnerType::Cashback);
-                log!("total cashbacked accounts: {}", cashbacked_accounts.len());
-                self.cashback_accounts.insert(
-                    &lottery_token_id, 
-                    &StoredCashback { 
-                        amount: cashback, 
-                        accounts: cashbacked_accounts.to_vec() 
-                    });
-                
-                let up_to_fifty_winners = lottery.get_winners(WinnerType::UpToFiftyPercent);
-                let up_to_ten_winners = lottery.get_winners(WinnerType::UpToTenPercent);
-
-                if lottery_token_id == near() {
-                    //transfers NEAR
-                    for account in up_to_fifty_winners {
-                        Promise::new(account.clone()).transfer(reward_fifty_percents_up);
-                        log!("Reward up to 50% transfered ( {} yoctoNEAR ) to @{} ", reward_fifty_percents_up, account);
+                let lottery_result = LotteryResult::BigLotteryResult( 
+                    BigLotteryResult {
+                        lottery_id: U64(lottery.id),
+                        lottery_token_id,
+                        participants_num: lottery.entries.tickets_num(),
+                        prize_table_id: lottery.prize_table_id.clone(),
+                        tiers,
+                        total_winning_amount: U128(lottery.current_pool),
+                        contract_fee: U128(contract_fees),
                     }
+                );
+                self.settlements.insert(
+                    &lottery.id,
+                    &StoredSettlement::new(lottery.lottery_token_id, lottery.entries, None, payout, pending_amount)
+                );
+                lottery_result
+            },
+            Lottery::TimedLottery(lottery) => {
+                lottery.assert_is_finished();
+                let lottery_token_id = lottery.lottery_token_id.clone();
+                let winner = lottery.winner.clone().unwrap();
 
-                    for account in up_to_ten_winners {
-                        Promise::new(account.clone()).transfer(reward_ten_percents_up);
-                        log!("Reward up to 10% transfered ( {} yoctoNEAR ) to @{} ", reward_ten_percents_up, account);
-                    }
+                let reward = lottery.current_pool;
+                let contract_fees = ratio(reward, lottery.fees.contract_fee_ratio);
+                assert!(reward > contract_fees, "Reward cannot be less than contract fees");
+                // take contract fees from reward
+                let reward_fees_taken = reward - contract_fees;
 
-                    if treasury_fees > 0 {
-                        Promise::new(self.treasury()).transfer(treasury_fees);
-                    }
-    
-                    if investor_fees > 0 {
-                        Promise::new(self.investor()).transfer(investor_fees);
-                    }
-                } else {
-                    //transfers FT
-                    for account in up_to_fifty_winners {
-                        self.internal_ft_transfer(account, &lottery_token_id, reward_fifty_percents_up);
-                        log!("Reward up to 50% transfered ( {} yocto{}) to @{} ", reward_fifty_percents_up, match_token_id(&lottery_token_id), account);
-                    }
+                self.internal_reward(&winner, &lottery_token_id, reward_fees_taken, claim_mode);
+
+                let contract_fees = self.internal_distribute_fees(
+                    &lottery.fees,
+                    &lottery_token_id,
+                    reward,
+                    reward_fees_taken,
+                    lottery.refferal_transfered
+                );
 
-                    for account in up_to_ten_winners {
-                        self.internal_ft_transfer(account, &lottery_token_id, reward_ten_percents_up);
-                        log!("Reward up to 10% transfered ( {} yocto{}) to @{} ", reward_ten_percents_up, match_token_id(&lottery_token_id), account);
+                let lottery_result = LotteryResult::TimedLotteryResult(
+                    TimedLotteryResult {
+                        lottery_id: U64(lottery.id),
+                        lottery_token_id,
+                        participants_num: lottery.entries.tickets_num(),
+                        participants: lottery.entries.ticket_holders_up_to(MAX_LISTED_PARTICIPANTS),
+                        draw_at: U64(lottery.draw_at),
+                        winner,
+                        winning_amount: U128(reward_fees_taken),
+                        contract_fee: U128(contract_fees),
                     }
+                );
+                self.settlements.insert(&lottery.id, &StoredSettlement::release(lottery.lottery_token_id, lottery.entries, None));
+                lottery_result
+            },
+            Lottery::JackpotLottery(lottery) => {
+                lottery.assert_is_finished();
+                let lottery_token_id = lottery.lottery_token_id.clone();
 
-                    if treasury_fees > 0 {
-                        self.internal_ft_transfer(&self.treasury(), &lottery_token_id, treasury_fees);
+                let round_pool = lottery.current_pool;
+                let contract_fees = ratio(round_pool, lottery.fees.contract_fee_ratio);
+                assert!(round_pool > contract_fees, "Reward cannot be less than contract fees");
+                // take contract fees from round pool, the rest goes to jackpot
+                let round_pool_fees_taken = round_pool - contract_fees;
+
+                let round = self.internal_settle_jackpot(&lottery, round_pool_fees_taken, claim_mode);
+
+                let contract_fees = self.internal_distribute_fees(
+                    &lottery.fees,
+                    &lottery_token_id,
+                    round_pool,
+                    round_pool_fees_taken,
+                    lottery.refferal_transfered
+                );
+
+                let lottery_result = LotteryResult::JackpotLotteryResult(
+                    JackpotLotteryResult {
+                        lottery_id: U64(lottery.id),
+                        lottery_token_id,
+                        participants_num: lottery.entries.tickets_num(),
+                        participants: lottery.entries.ticket_holders_up_to(MAX_LISTED_PARTICIPANTS),
+                        round: round.round,
+                        drawn_number: round.drawn_number,
+                        winning_amount: U128(if round.winner.is_some() { round.jackpot.0 } else { 0 }),
+                        winner: round.winner,
+                        rolled_over: round.rolled_over,
+                        contract_fee: U128(contract_fees),
                     }
-    
-                    if investor_fees > 0 {
-                        self.internal_ft_transfer(&self.investor(), &lottery_token_id, investor_fees);
+                );
+                self.settlements.insert(&lottery.id, &StoredSettlement::release(lottery.lottery_token_id, lottery.entries, None));
+                lottery_result
+            },
+            Lottery::Lotto(lottery) => {
+                lottery.assert_is_finished();
+                let lottery_id = lottery.id;
+                let lottery_token_id = lottery.lottery_token_id.clone();
+                let participants_num = lottery.entries.tickets_num();
+                let participants = lottery.entries.ticket_holders_up_to(MAX_LISTED_PARTICIPANTS);
+                let drawn_numbers = lottery.drawn_numbers.clone();
+
+                let round_pool = lottery.current_pool;
+                let contract_fees = ratio(round_pool, lottery.fees.contract_fee_ratio);
+                assert!(round_pool > contract_fees, "Reward cannot be less than contract fees");
+                // take contract fees from round pool, the rest goes to prize pool
+                let round_pool_fees_taken = round_pool - contract_fees;
+
+                let contract_fees = self.internal_distribute_fees(
+                    &lottery.fees,
+                    &lottery_token_id,
+                    round_pool,
+                    round_pool_fees_taken,
+                    lottery.refferal_transfered
+                );
+                let (tiers, prize_pool) = self.internal_settle_lotto(lottery, round_pool_fees_taken, claim_mode);
+
+                LotteryResult::LottoResult(
+                    LottoResult {
+                        lottery_id: U64(lottery_id),
+                        lottery_token_id,
+                        participants_num,
+                        participants,
+                        drawn_numbers,
+                        tiers,
+                        prize_pool: U128(prize_pool),
+                        contract_fee: U128(contract_fees),
                     }
-                }  
-                
-                self.deposit_fees(&lottery_token_id, contract_fees);
+                )
+            },
+            Lottery::Raffle(lottery) => {
+                lottery.assert_is_finished();
+                let lottery_token_id = lottery.lottery_token_id.clone();
+                let winner = lottery.winner.clone().unwrap();
 
-                LotteryResult::BigLotteryResult( 
-                    BigLotteryResult {
+                let collected = lottery.current_pool;
+                let mut sponsor_amount = ratio(collected, lottery.sponsor_ratio);
+                // referral rewards exceeding contract share are taken from sponsor share
+                let contract_share = collected - sponsor_amount;
+                if lottery.refferal_transfered > contract_share {
+                    sponsor_amount -= lottery.refferal_transfered - contract_share;
+                }
+
+                self.internal_nft_payout(&winner, &lottery.prize);
+
+                if sponsor_amount > 0 {
+                    self.internal_payout(&lottery.sponsor, &lottery_token_id, sponsor_amount);
+                }
+
+                let contract_fees = self.internal_distribute_fees(
+                    &lottery.fees,
+                    &lottery_token_id,
+                    collected,
+                    sponsor_amount,
+                    lottery.refferal_transfered
+                );
+
+                let lottery_result = LotteryResult::RaffleResult(
+                    RaffleResult {
                         lottery_id: U64(lottery.id),
                         lottery_token_id,
-                        participants: lottery.entries.clone(),
-                        winners_up_to_50: up_to_fifty_winners.to_vec(),
-                        winners_up_to_10: up_to_ten_winners.to_vec(),
-                        total_winning_amount: U128(lottery.current_pool),
+                        participants: lottery.entries.ticket_holders(),
+                        prize: lottery.prize.clone(),
+                        winner,
+                        sponsor: lottery.sponsor.clone(),
+                        sponsor_amount: U128(sponsor_amount),
                         contract_fee: U128(contract_fees),
                     }
-                )
+                );
+                self.settlements.insert(&lottery.id, &StoredSettlement::release(lottery.lottery_token_id, lottery.entries, None));
+                lottery_result
             },
         }
     }
@@ -419,6 +941,13 @@ impl Contract {
         self.assert_required_num_participants(num_participants, lottery_type);
 
         let lottery_id = self.next_lottery_id;
+        let expires_at = self
+            .internal_lottery_config()
+            .get_expiration(lottery_type)
+            .map(|expiration| expiration.deadline_from_now());
+        let fees = self.internal_lottery_fees();
+        let lottery_config = self.internal_lottery_config();
+        let draw_mode = lottery_config.get_draw_mode(lottery_type);
         let lottery = match lottery_type {
             LotteryType::SimpleLottery => {
                 Lottery::SimpleLottery(
@@ -426,7 +955,12 @@ impl Contract {
                         lottery_id,
                         lottery_token_id,
                         num_participants,
-                        entry_fee
+                        entry_fee,
+                        expires_at,
+                        fees,
+                        draw_mode,
+                        lottery_config.prize_split.clone(),
+                        lottery_config.get_max_tickets(lottery_type)
                     )
                 )
             },
@@ -436,10 +970,69 @@ impl Contract {
                         lottery_id,
                         lottery_token_id,
                         num_participants,
-                        entry_fee
+                        entry_fee,
+                        expires_at,
+                        fees,
+                        draw_mode,
+                        lottery_config.big_lottery_prize_table.clone(),
+                        lottery_config.get_prize_table(&lottery_config.big_lottery_prize_table),
+                        lottery_config.get_max_tickets(lottery_type)
                     )
                 )
             },
+            LotteryType::TimedLottery => {
+                let duration = lottery_config.timed_lottery_duration.expect("Timed lotteries are disabled");
+                Lottery::TimedLottery(
+                    TimedLottery::new(
+                        lottery_id,
+                        lottery_token_id,
+                        entry_fee,
+                        env::block_timestamp() + duration.0,
+                        fees
+                    )
+                )
+            },
+            LotteryType::JackpotLottery => {
+                let jackpot_config = lottery_config.jackpot.expect("Jackpot lotteries are disabled");
+                self.internal_open_jackpot_round(
+                    &JackpotSeries {
+                        lottery_token_id: lottery_token_id.clone(),
+                        entry_fee: U128(entry_fee)
+                    },
+                    lottery_id
+                );
+                Lottery::JackpotLottery(
+                    JackpotLottery::new(
+                        lottery_id,
+                        lottery_token_id,
+                        entry_fee,
+                        env::block_timestamp() + jackpot_config.duration.0,
+                        jackpot_config.ticket_range,
+                        fees
+                    )
+                )
+            },
+            LotteryType::Lotto => {
+                let lotto_config = lottery_config.lotto.clone().expect("Lotto is disabled");
+                self.internal_open_lotto_round(
+                    &LottoSeries {
+                        lottery_token_id: lottery_token_id.clone(),
+                        entry_fee: U128(entry_fee)
+                    },
+                    lottery_id
+                );
+                Lottery::Lotto(
+                    Lotto::new(
+                        lottery_id,
+                        lottery_token_id,
+                        entry_fee,
+                        env::block_timestamp() + lotto_config.duration.0,
+                        lotto_config,
+                        fees
+                    )
+                )
+            },
+            LotteryType::Raffle => panic!("Raffles are created by NFT deposit"),
         };
         self.next_lottery_id += 1;
         lottery
@@ -448,25 +1041,164 @@ impl Contract {
     
 #[near_bindgen]
 impl Contract {
+    /// Closes lottery which was not filled before expiration and refunds its entries.
+    /// Prize of raffle without entries is returned to sponsor
+    /// - Can be called by anyone
+    /// - Panics if lottery is not expired
+    pub fn close_expired_lottery(&mut self, lottery_id: LotteryId) {
+        let lottery = self.internal_get_lottery(lottery_id).expect("Lottery was not found");
+        assert!(lottery.is_expired(), "Lottery is not expired");
+
+        let entries_num = lottery.entries_num();
+        self.internal_return_raffle_prize(&lottery);
+        self.internal_remove_lottery(&lottery);
+        self.internal_refund_entries(lottery);
+        log!("Expired lottery {} closed. Refunded entries: {}", lottery_id, entries_num);
+    }
+
+    /// Picks winners of lottery in `Drawing` status and distributes rewards.
+    /// - Can be called by anyone
+    /// - Requires a later block than the final entry, winners are picked from that block's seed
+    /// - Timed and jackpot lotteries are drawn after draw time
+    /// - Raffle is drawn when it is filled or expires with entries
+    pub fn draw(&mut self, lottery_id: LotteryId) -> LotteryResult {
+        let mut lottery = self.internal_get_lottery(lottery_id).expect("Lottery was not found");
+        lottery.draw();
+
+        self.internal_remove_lottery(&lottery);
+        let lottery_result = self.distribute(lottery);
+        lottery_result.log();
+        lottery_result
+    }
+
+    /// Cancels active lottery or timed, jackpot or lotto round waiting for draw and refunds all its entries.
+    /// - Requires one yoctoNEAR.
+    /// - Requires to be called by the contract owner or `ConfigManager`.
+    /// - Lottery is removed, cancellation reason with number and hash of refunded entries is stored
+    /// - Entries kept outside the lottery are refunded in batches by `process_cashbacks`
+    /// - Prize of raffle is returned to sponsor
+    #[payable]
+    pub fn cancel_lottery(&mut self, lottery_id: LotteryId, reason: String) {
+        assert_one_yocto();
+        self.assert_role(Role::ConfigManager);
+
+        let mut lottery = self.internal_get_lottery(lottery_id).expect("Lottery was not found");
+        lottery.cancel();
+        self.internal_return_raffle_prize(&lottery);
+
+        let cancellation = LotteryCancellation {
+            lottery_id: U64(lottery_id),
+            reason,
+            lottery_token_id: lottery.lottery_token_id().clone(),
+            entries_num: lottery.entries_num(),
+            tickets_num: lottery.tickets_num(),
+            entries_hash: lottery.entries_hash().into(),
+            entry_fee: U128(lottery.entry_fee()),
+            timestamp: U64(env::block_timestamp()),
+        };
+        log!("Lottery {} cancelled: {}", lottery_id, cancellation.reason);
+        self.cancellations.insert(&lottery_id, &cancellation);
+        self.internal_remove_lottery(&lottery);
+        self.internal_refund_entries(lottery);
+    }
+
+    /// Returns cancellation record of lottery
+    pub fn get_cancellation(&self, lottery_id: LotteryId) -> Option<LotteryCancellation> {
+        self.cancellations.get(&lottery_id)
+    }
+
+    /// Leaves active lottery and refunds entry fees of all caller tickets.
+    /// - Requires one yoctoNEAR.
+    /// - Referral reward paid for that entry is deducted from refund
+    /// - Panics if caller has no entry in lottery or lottery is not active
+    #[payable]
+    pub fn leave_lottery(&mut self, lottery_id: LotteryId) {
+        assert_one_yocto();
+        let account_id = env::predecessor_account_id();
+        let mut lottery = self.internal_get_lottery(lottery_id).expect("Lottery was not found");
+
+        let refund = lottery.leave(&account_id);
+        // referral rewards can cover the whole refund, zero FT transfers are rejected
+        if refund > 0 {
+            self.internal_payout(&account_id, lottery.lottery_token_id(), refund);
+        }
+        log!("@{} left lottery {}. Refunded: {}", account_id, lottery_id, refund);
+
+        // raffle is kept without entries until it expires, its prize is returned on close
+        if lottery.entries_num() == 0 && lottery.lottery_type() != LotteryType::Raffle {
+            self.internal_remove_lottery(&lottery);
+        } else {
+            self.internal_set_lottery(&lottery_id, lottery);
+        }
+    }
+
+    /// Reveals secret committed on entry to lottery in `CommitReveal` draw mode.
+    /// - Requires to be called by the entrant
+    /// - Available while lottery is in `Drawing` status and reveal window is open
+    pub fn reveal_secret(&mut self, lottery_id: LotteryId, secret: String) {
+        let account_id = env::predecessor_account_id();
+        let mut lottery = self.internal_get_lottery(lottery_id).expect("Lottery was not found");
+        lottery.reveal_secret(&account_id, &secret);
+        self.lotteries.insert(&lottery_id, &lottery.into());
+    }
+
+    /// - `secret_hash` - sha256 of entrant secret, required in `CommitReveal` draw mode
+    /// - `tickets` - tickets to buy, attached deposit is split between them. One by default
     #[payable]
     pub fn draw_near_enter(
         &mut self, 
         lottery_type: String,
         num_participants: u32,
-        referrer_id: Option<AccountId>
+        referrer_id: Option<AccountId>,
+        secret_hash: Option<Base58CryptoHash>,
+        tickets: Option<u32>
     ) -> LotteryId {
         let account_id = env::predecessor_account_id();
         let attached_deposit = env::attached_deposit();
 
         self.check_accepted_subs(&account_id);
+        let lottery_type = LotteryType::from(lottery_type);
+        self.assert_entries_not_paused(&near(), lottery_type);
         
         self.draw_enter(
             &account_id, 
             near(), 
-            LotteryType::from(lottery_type), 
+            lottery_type, 
             num_participants, 
             attached_deposit,
-            referrer_id
+            EntryOptions {
+                referrer_id,
+                secret_hash: secret_hash.map(CryptoHash::from),
+                tickets: tickets.unwrap_or(1),
+                numbers: None
+            }
+        )
+    }
+
+    /// Enters lotto with picked numbers.
+    /// - Called from potential player account
+    /// - Required attached deposit equals to one from near entry fees
+    /// - `numbers` - distinct numbers from `1..=max_number` of lotto config, as many as `pick_count`
+    #[payable]
+    pub fn draw_near_lotto_enter(&mut self, numbers: Vec<u32>, referrer_id: Option<AccountId>) -> LotteryId {
+        let account_id = env::predecessor_account_id();
+        let attached_deposit = env::attached_deposit();
+
+        self.check_accepted_subs(&account_id);
+        self.assert_entries_not_paused(&near(), LotteryType::Lotto);
+
+        self.draw_enter(
+            &account_id,
+            near(),
+            LotteryType::Lotto,
+            0,
+            attached_deposit,
+            EntryOptions {
+                referrer_id,
+                secret_hash: None,
+                tickets: 1,
+                numbers: Some(numbers)
+            }
         )
     }
 }
\ No newline at end of file
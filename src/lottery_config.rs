@@ -1,19 +1,167 @@
 use std::collections::HashMap;
 
+use near_sdk::json_types::U64;
+
 use crate::*;
+use crate::upgrade::LotteryConfigV1;
 
 #[derive(BorshSerialize, BorshDeserialize, Serialize, Deserialize, PartialEq, Copy, Clone, Debug)]
 #[serde(crate = "near_sdk::serde")]
+#[allow(clippy::enum_variant_names)]
 pub enum LotteryType {
     SimpleLottery,
-    BigLottery
+    BigLottery,
+    TimedLottery,
+    JackpotLottery,
+    Lotto,
+    Raffle
+}
+
+/// Lottery expiration.
+/// In `LotteryConfig` it is a duration from lottery creation (nanoseconds or blocks),
+/// in lottery it is an absolute deadline (block timestamp or block height)
+#[derive(BorshSerialize, BorshDeserialize, Serialize, Deserialize, PartialEq, Copy, Clone, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub enum LotteryExpiration {
+    Timestamp(U64),
+    BlockHeight(U64)
+}
+
+impl LotteryExpiration {
+    /// Absolute deadline for lottery created now
+    pub fn deadline_from_now(&self) -> Self {
+        match self {
+            LotteryExpiration::Timestamp(duration) => {
+                LotteryExpiration::Timestamp(U64(env::block_timestamp() + duration.0))
+            },
+            LotteryExpiration::BlockHeight(blocks) => {
+                LotteryExpiration::BlockHeight(U64(env::block_height() + blocks.0))
+            },
+        }
+    }
+
+    pub fn is_reached(&self) -> bool {
+        match self {
+            LotteryExpiration::Timestamp(timestamp) => env::block_timestamp() >= timestamp.0,
+            LotteryExpiration::BlockHeight(height) => env::block_height() >= height.0,
+        }
+    }
+
+    fn assert_valid(&self) {
+        match self {
+            LotteryExpiration::Timestamp(duration) => assert!(duration.0 > 0, "Expiration cannot be zero"),
+            LotteryExpiration::BlockHeight(blocks) => assert!(blocks.0 > 0, "Expiration cannot be zero"),
+        }
+    }
 }
 
-#[derive(BorshSerialize, BorshDeserialize, Serialize)]
+/// How winners are picked when lottery pool is filled
+#[derive(BorshSerialize, BorshDeserialize, Serialize, Deserialize, PartialEq, Copy, Clone, Debug)]
 #[serde(crate = "near_sdk::serde")]
-pub struct StoredCashback {
-    pub amount: Balance, 
-    pub accounts: Vec<AccountId>
+pub enum DrawMode {
+    /// winners are picked in the final entry
+    Instant,
+    /// lottery moves to `Drawing` status, winners are picked by `draw` call in a later block
+    Delayed,
+    /// as `Delayed`, but entrants commit secret hash on entry and reveal secrets
+    /// during `reveal_window` blocks after the final entry. Revealed secrets are mixed with block seed
+    CommitReveal { reveal_window: U64 }
+}
+
+impl DrawMode {
+    pub fn is_delayed(&self) -> bool {
+        self != &DrawMode::Instant
+    }
+
+    fn assert_valid(&self) {
+        if let DrawMode::CommitReveal { reveal_window } = self {
+            assert!(reveal_window.0 > 0, "Reveal window cannot be zero");
+        }
+    }
+}
+
+/// Options of new jackpot lotteries
+#[derive(BorshSerialize, BorshDeserialize, Serialize, Deserialize, PartialEq, Copy, Clone, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct JackpotConfig {
+    /// nanoseconds from creation of jackpot round to its draw
+    pub duration: U64,
+    /// ticket number is drawn from `[0, ticket_range)`, E.g 10 entrants of 100 tickets win with 10% chance
+    pub ticket_range: u32
+}
+
+impl JackpotConfig {
+    fn assert_valid(&self) {
+        assert!(self.duration.0 > 0, "Jackpot duration cannot be zero");
+        assert!(self.ticket_range > 0, "Jackpot ticket range cannot be zero");
+    }
+}
+
+/// Lotto paytable tier
+#[derive(BorshSerialize, BorshDeserialize, Serialize, Deserialize, PartialEq, Clone, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct LottoTier {
+    /// matched numbers of tier winners
+    pub matches: u32,
+    /// share of prize pool split between tier winners in Basis Points
+    pub pool_ratio: u32
+}
+
+/// Options of new lotto lotteries
+#[derive(BorshSerialize, BorshDeserialize, Serialize, Deserialize, PartialEq, Clone, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct LottoConfig {
+    /// nanoseconds from creation of lotto round to its draw
+    pub duration: U64,
+    /// numbers picked by every entrant and drawn, E.g 6
+    pub pick_count: u32,
+    /// numbers are picked from `1..=max_number`, E.g 49
+    pub max_number: u32,
+    /// prize tiers by matched numbers
+    pub paytable: Vec<LottoTier>
+}
+
+impl LottoConfig {
+    fn assert_valid(&self) {
+        assert!(self.duration.0 > 0, "Lotto duration cannot be zero");
+        assert!(self.pick_count > 0, "Lotto pick count cannot be zero");
+        assert!(self.pick_count <= self.max_number, "Lotto pick count cannot be greater than max number");
+        assert!(!self.paytable.is_empty(), "Lotto paytable has no tiers");
+        for (index, tier) in self.paytable.iter().enumerate() {
+            assert!(
+                tier.matches > 0 && tier.matches <= self.pick_count,
+                "Lotto tier matches must be from 1 to {}", self.pick_count
+            );
+            assert!(tier.pool_ratio > 0, "Lotto tier share cannot be zero");
+            assert!(
+                self.paytable[..index].iter().all(|other| other.matches != tier.matches),
+                "Lotto tier matches must be distinct"
+            );
+        }
+        assert_eq!(
+            self.paytable.iter().map(|tier| tier.pool_ratio).sum::<u32>(), MAX_RATIO,
+            "Lotto paytable must sum up to 100% in Basis Points"
+        );
+    }
+}
+
+/// Options of raffles for NFT prizes
+#[derive(BorshSerialize, BorshDeserialize, Serialize, Deserialize, PartialEq, Clone, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct RaffleConfig {
+    /// nanoseconds from NFT deposit to raffle expiration
+    pub duration: U64,
+    /// share of collected entry fees paid to sponsor in Basis Points, the rest goes to contract fees
+    pub sponsor_ratio: u32,
+    /// NFT contracts accepted as raffle prizes
+    pub nft_contracts: Vec<AccountId>
+}
+
+impl RaffleConfig {
+    fn assert_valid(&self) {
+        assert!(self.duration.0 > 0, "Raffle duration cannot be zero");
+        assert!(self.sponsor_ratio <= MAX_RATIO, "Sponsor share cannot be greater than 100%");
+    }
 }
 
 impl From<String> for LotteryType {
@@ -22,6 +170,14 @@ impl From<String> for LotteryType {
             LotteryType::SimpleLottery
         } else if s == *BIG_LOTTERY {
             LotteryType::BigLottery
+        } else if s == *TIMED_LOTTERY {
+            LotteryType::TimedLottery
+        } else if s == *JACKPOT_LOTTERY {
+            LotteryType::JackpotLottery
+        } else if s == *LOTTO {
+            LotteryType::Lotto
+        } else if s == *RAFFLE {
+            LotteryType::Raffle
         } else {
             panic!("Unknown lottery type")
         }
@@ -34,7 +190,61 @@ impl From<String> for LotteryType {
 pub struct LotteryConfig {
     pub entry_fees: HashMap<AccountId, Vec<U128>>,
     pub num_participants: Vec<u32>,
-    pub big_lottery_num_participants: Vec<u32>
+    pub big_lottery_num_participants: Vec<u32>,
+    /// tokens which lotteries rewards are credited to claimable balances instead of transfer
+    pub claim_mode_tokens: Vec<AccountId>,
+    /// lottery types which rewards are credited to claimable balances instead of transfer
+    pub claim_mode_lottery_types: Vec<LotteryType>,
+    /// simple lotteries not filled before expiration are refunded
+    pub expiration: Option<LotteryExpiration>,
+    /// big lotteries not filled before expiration are refunded
+    pub big_lottery_expiration: Option<LotteryExpiration>,
+    /// draw mode of new simple lotteries
+    pub draw_mode: DrawMode,
+    /// draw mode of new big lotteries
+    pub big_lottery_draw_mode: DrawMode,
+    /// big lottery prize tables by id
+    pub prize_tables: HashMap<String, PrizeTable>,
+    /// prize table id of new big lotteries
+    pub big_lottery_prize_table: String,
+    /// share of prize for every winner rank of new simple lotteries in Basis Points
+    pub prize_split: Vec<u32>,
+    /// nanoseconds from creation of timed lottery to its draw, timed lotteries are disabled if not set
+    pub timed_lottery_duration: Option<U64>,
+    /// options of jackpot lotteries, jackpot lotteries are disabled if not set
+    pub jackpot: Option<JackpotConfig>,
+    /// options of lotto lotteries, lotto is disabled if not set
+    pub lotto: Option<LottoConfig>,
+    /// options of raffles for NFT prizes, raffles are disabled if not set
+    pub raffle: Option<RaffleConfig>,
+    /// tickets one account can hold in new simple lotteries
+    pub max_tickets: u32,
+    /// tickets one account can hold in new big lotteries
+    pub big_lottery_max_tickets: u32
+}
+
+/// Lotteries config as it is kept in the contract state.
+/// New layouts are added as new variants, so stored config survives upgrades
+#[derive(BorshSerialize, BorshDeserialize)]
+#[allow(clippy::large_enum_variant)]
+pub enum VersionedLotteryConfig {
+    V1(LotteryConfigV1),
+    V2(LotteryConfig)
+}
+
+impl From<VersionedLotteryConfig> for LotteryConfig {
+    fn from(config: VersionedLotteryConfig) -> Self {
+        match config {
+            VersionedLotteryConfig::V1(config) => config.into(),
+            VersionedLotteryConfig::V2(config) => config,
+        }
+    }
+}
+
+impl From<LotteryConfig> for VersionedLotteryConfig {
+    fn from(config: LotteryConfig) -> Self {
+        VersionedLotteryConfig::V2(config)
+    }
 }
 
 impl LotteryConfig {
@@ -47,12 +257,144 @@ impl LotteryConfig {
             entry_fees: entry_fees.iter().cloned().collect(),
             num_participants,
             big_lottery_num_participants,
+            claim_mode_tokens: vec![],
+            claim_mode_lottery_types: vec![],
+            expiration: None,
+            big_lottery_expiration: None,
+            draw_mode: DrawMode::Instant,
+            big_lottery_draw_mode: DrawMode::Instant,
+            prize_tables: HashMap::from([(DEFAULT_PRIZE_TABLE.to_string(), PrizeTable::default())]),
+            big_lottery_prize_table: DEFAULT_PRIZE_TABLE.to_string(),
+            prize_split: vec![MAX_RATIO],
+            timed_lottery_duration: None,
+            jackpot: None,
+            lotto: None,
+            raffle: None,
+            max_tickets: 1,
+            big_lottery_max_tickets: 1,
         }
     }
     pub fn assert_valid(&self) {
         assert!(!self.entry_fees.is_empty());
         assert!(!self.num_participants.is_empty());
         assert!(!self.big_lottery_num_participants.is_empty());
+        if let Some(expiration) = self.expiration {
+            expiration.assert_valid();
+        }
+        if let Some(expiration) = self.big_lottery_expiration {
+            expiration.assert_valid();
+        }
+        self.draw_mode.assert_valid();
+        self.big_lottery_draw_mode.assert_valid();
+        assert!(
+            self.prize_tables.contains_key(&self.big_lottery_prize_table),
+            "Prize table was not found"
+        );
+        self.prize_tables.values().for_each(PrizeTable::assert_valid);
+        assert!(!self.prize_split.is_empty(), "Prize split has no winners");
+        assert!(self.prize_split.iter().all(|share| *share > 0), "Prize split share cannot be zero");
+        assert_eq!(
+            self.prize_split.iter().sum::<u32>(), MAX_RATIO,
+            "Prize split must sum up to 100% in Basis Points"
+        );
+        if let Some(duration) = self.timed_lottery_duration {
+            assert!(duration.0 > 0, "Timed lottery duration cannot be zero");
+        }
+        if let Some(jackpot) = self.jackpot {
+            jackpot.assert_valid();
+        }
+        if let Some(lotto) = &self.lotto {
+            lotto.assert_valid();
+        }
+        if let Some(raffle) = &self.raffle {
+            raffle.assert_valid();
+        }
+        assert!(self.max_tickets > 0, "Max tickets cannot be zero");
+        assert!(self.big_lottery_max_tickets > 0, "Max tickets cannot be zero");
+    }
+
+    pub fn set_expiration(&mut self, lottery_type: LotteryType, expiration: Option<LotteryExpiration>) {
+        match lottery_type {
+            LotteryType::SimpleLottery => self.expiration = expiration,
+            LotteryType::BigLottery => self.big_lottery_expiration = expiration,
+            LotteryType::TimedLottery => panic!("Timed lotteries do not expire"),
+            LotteryType::JackpotLottery => panic!("Jackpot lotteries do not expire"),
+            LotteryType::Lotto => panic!("Lotto lotteries do not expire"),
+            LotteryType::Raffle => panic!("Raffle expiration is set by raffle config"),
+        }
+    }
+
+    pub fn get_expiration(&self, lottery_type: LotteryType) -> Option<LotteryExpiration> {
+        match lottery_type {
+            LotteryType::SimpleLottery => self.expiration,
+            LotteryType::BigLottery => self.big_lottery_expiration,
+            LotteryType::TimedLottery | LotteryType::JackpotLottery | LotteryType::Lotto | LotteryType::Raffle => None,
+        }
+    }
+
+    pub fn set_draw_mode(&mut self, lottery_type: LotteryType, draw_mode: DrawMode) {
+        match lottery_type {
+            LotteryType::SimpleLottery => self.draw_mode = draw_mode,
+            LotteryType::BigLottery => self.big_lottery_draw_mode = draw_mode,
+            LotteryType::TimedLottery | LotteryType::JackpotLottery | LotteryType::Lotto => {
+                panic!("Timed lotteries are drawn after draw time")
+            },
+            LotteryType::Raffle => panic!("Raffles are drawn in a later block than the final entry"),
+        }
+    }
+
+    pub fn get_draw_mode(&self, lottery_type: LotteryType) -> DrawMode {
+        match lottery_type {
+            LotteryType::SimpleLottery => self.draw_mode,
+            LotteryType::BigLottery => self.big_lottery_draw_mode,
+            LotteryType::TimedLottery | LotteryType::JackpotLottery | LotteryType::Lotto | LotteryType::Raffle => {
+                DrawMode::Delayed
+            },
+        }
+    }
+
+    pub fn set_max_tickets(&mut self, lottery_type: LotteryType, max_tickets: u32) {
+        match lottery_type {
+            LotteryType::SimpleLottery => self.max_tickets = max_tickets,
+            LotteryType::BigLottery => self.big_lottery_max_tickets = max_tickets,
+            LotteryType::TimedLottery | LotteryType::JackpotLottery | LotteryType::Lotto => {
+                panic!("Timed lottery entry is one ticket")
+            },
+            LotteryType::Raffle => panic!("Raffle entry is one ticket"),
+        }
+    }
+
+    pub fn get_max_tickets(&self, lottery_type: LotteryType) -> u32 {
+        match lottery_type {
+            LotteryType::SimpleLottery => self.max_tickets,
+            LotteryType::BigLottery => self.big_lottery_max_tickets,
+            LotteryType::TimedLottery | LotteryType::JackpotLottery | LotteryType::Lotto | LotteryType::Raffle => 1,
+        }
+    }
+
+    pub fn get_prize_table(&self, prize_table_id: &str) -> PrizeTable {
+        self.prize_tables.get(prize_table_id).cloned().expect("Prize table was not found")
+    }
+
+    pub fn set_prize_table(&mut self, prize_table_id: String, prize_table: PrizeTable) {
+        self.prize_tables.insert(prize_table_id, prize_table);
+    }
+
+    /// Checks every prize table against every big lottery participants num and referral ratio of contract config
+    pub fn assert_valid_prize_tables(&self, referral_ratio: u32) {
+        for prize_table in self.prize_tables.values() {
+            for num_participants in &self.big_lottery_num_participants {
+                prize_table.assert_payouts_within_pool(*num_participants, referral_ratio);
+            }
+        }
+    }
+
+    pub fn remove_prize_table(&mut self, prize_table_id: &str) {
+        assert_ne!(
+            self.big_lottery_prize_table, prize_table_id,
+            "Prize table is used for new big lotteries"
+        );
+        self.prize_tables.remove(prize_table_id).expect("Prize table was not found");
     }
 
     pub fn add_num_participants(&mut self, num: u32) {
@@ -75,6 +417,25 @@ impl LotteryConfig {
         self.big_lottery_num_participants.remove(index);
     }
 
+    pub fn set_token_claim_mode(&mut self, token_id: AccountId, enabled: bool) {
+        self.claim_mode_tokens.retain(|token| token != &token_id);
+        if enabled {
+            self.claim_mode_tokens.push(token_id);
+        }
+    }
+
+    pub fn set_lottery_type_claim_mode(&mut self, lottery_type: LotteryType, enabled: bool) {
+        self.claim_mode_lottery_types.retain(|kind| kind != &lottery_type);
+        if enabled {
+            self.claim_mode_lottery_types.push(lottery_type);
+        }
+    }
+
+    pub fn is_claim_mode(&self, token_id: &AccountId, lottery_type: LotteryType) -> bool {
+        self.claim_mode_tokens.contains(token_id) 
+            || self.claim_mode_lottery_types.contains(&lottery_type)
+    }
+
     pub fn add_entry_fee(&mut self, token_id: Option<AccountId>, fee: U128) {
         if let Some(token_id) = token_id {
             self
@@ -120,12 +481,25 @@ impl LotteryConfig {
 
 impl Contract {
     pub (crate) fn internal_lottery_config(&self) -> LotteryConfig {
-        self.lotteries_config.get().unwrap()
+        self.lotteries_config.get().unwrap().into()
+    }
+
+    /// Checks lotteries config and its prize tables against fee ratios of contract config
+    pub (crate) fn assert_valid_lottery_config(&self, config: &LotteryConfig) {
+        config.assert_valid();
+        config.assert_valid_prize_tables(self.internal_config().referral_ratio);
+    }
+
+    pub (crate) fn is_claim_mode(&self, token_id: &AccountId, lottery_type: LotteryType) -> bool {
+        self.internal_lottery_config().is_claim_mode(token_id, lottery_type)
     }
 
     pub (crate) fn assert_required_num_participants(&self, num: u32, lottery_type: LotteryType) {
         let required_num_participants = match lottery_type {
-            LotteryType::SimpleLottery => {
+            // timed lotteries have no fixed number of participants
+            LotteryType::TimedLottery | LotteryType::JackpotLottery | LotteryType::Lotto => return,
+            // raffles accept the same numbers of participants as simple lotteries
+            LotteryType::SimpleLottery | LotteryType::Raffle => {
                 self
                     .internal_lottery_config()
                     .num_participants
@@ -152,7 +526,11 @@ impl Contract {
                     .get(token_id)
                     .expect("No required fees for token")
             },
-            LotteryType::BigLottery => {
+            LotteryType::BigLottery
+                | LotteryType::TimedLottery
+                | LotteryType::JackpotLottery
+                | LotteryType::Lotto
+                | LotteryType::Raffle => {
                 lottery_config
                     .entry_fees
                     .get(token_id)
@@ -171,7 +549,7 @@ impl Contract {
 impl Contract {
         /// Added the lottery config new num_participants required.
     /// - Requires one yoctoNEAR.
-    /// - Requires to be called by the contract owner.
+    /// - Requires to be called by the contract owner or `ConfigManager`.
     #[payable]
     pub fn add_num_participants(
         &mut self, 
@@ -179,7 +557,7 @@ impl Contract {
         lottery_type: String
     ) {
         assert_one_yocto();
-        self.assert_owner();
+        self.assert_role(Role::ConfigManager);
 
         let mut config = self.internal_lottery_config();
 
@@ -189,12 +567,12 @@ impl Contract {
             config.add_big_lottery_num_participants(num);
         }
 
-        config.assert_valid();
-        self.lotteries_config.set(&config);
+        self.assert_valid_lottery_config(&config);
+        self.lotteries_config.set(&config.into());
     }
     /// Removes the lottery config given num_participants.
     /// - Requires one yoctoNEAR.
-    /// - Requires to be called by the contract owner.
+    /// - Requires to be called by the contract owner or `ConfigManager`.
     #[payable]
     pub fn remove_num_participants (      
         &mut self, 
@@ -202,7 +580,7 @@ impl Contract {
         lottery_type: String
     ) {
         assert_one_yocto();
-        self.assert_owner();
+        self.assert_role(Role::ConfigManager);
 
         let lottery_type = LotteryType::from(lottery_type);
 
@@ -215,37 +593,244 @@ impl Contract {
             LotteryType::BigLottery => {
                 config.remove_big_lottery_num_participants(num);
             },
+            LotteryType::TimedLottery | LotteryType::JackpotLottery | LotteryType::Lotto => {
+                panic!("Timed lotteries have no fixed number of participants")
+            },
+            LotteryType::Raffle => panic!("Raffles use num participants of simple lotteries"),
         }
         
         config.assert_valid();
-        self.lotteries_config.set(&config);
+        self.lotteries_config.set(&config.into());
     }
     /// Added the lottery config new entry_fee required.
     /// - Requires one yoctoNEAR.
-    /// - Requires to be called by the contract owner.
+    /// - Requires to be called by the contract owner or `ConfigManager`.
     #[payable]
     pub fn add_entry_fee(&mut self, token_id: Option<AccountId>, entry_fee: U128) {
         assert_one_yocto();
-        self.assert_owner();
+        self.assert_role(Role::ConfigManager);
 
         let mut config = self.internal_lottery_config();
         config.add_entry_fee(token_id, entry_fee);
         config.assert_valid();
 
-        self.lotteries_config.set(&config);
+        self.lotteries_config.set(&config.into());
     }
     /// Removes the lottery config given entry_fee.
     /// - Requires one yoctoNEAR.
-    /// - Requires to be called by the contract owner.
+    /// - Requires to be called by the contract owner or `ConfigManager`.
     #[payable]
     pub fn remove_entry_fee(&mut self, token_id: Option<AccountId>, entry_fee: U128) {
         assert_one_yocto();
-        self.assert_owner();
+        self.assert_role(Role::ConfigManager);
 
         let mut config = self.internal_lottery_config();
         config.remove_entry_fee(token_id, entry_fee);
         config.assert_valid();
 
-        self.lotteries_config.set(&config);
+        self.lotteries_config.set(&config.into());
+    }
+    /// Enables or disables claim mode for lotteries in given token.
+    /// In claim mode rewards are credited to claimable balances and winners `claim` it by themselves
+    /// - Requires one yoctoNEAR.
+    /// - Requires to be called by the contract owner or `ConfigManager`.
+    /// - If `token_id` was not set - changes claim mode for NEAR
+    #[payable]
+    pub fn set_token_claim_mode(&mut self, token_id: Option<AccountId>, enabled: bool) {
+        assert_one_yocto();
+        self.assert_role(Role::ConfigManager);
+
+        let mut config = self.internal_lottery_config();
+        config.set_token_claim_mode(token_id.unwrap_or_else(near), enabled);
+        config.assert_valid();
+
+        self.lotteries_config.set(&config.into());
+    }
+    /// Enables or disables claim mode for lotteries of given type.
+    /// - Requires one yoctoNEAR.
+    /// - Requires to be called by the contract owner or `ConfigManager`.
+    #[payable]
+    pub fn set_lottery_type_claim_mode(&mut self, lottery_type: String, enabled: bool) {
+        assert_one_yocto();
+        self.assert_role(Role::ConfigManager);
+
+        let mut config = self.internal_lottery_config();
+        config.set_lottery_type_claim_mode(LotteryType::from(lottery_type), enabled);
+        config.assert_valid();
+
+        self.lotteries_config.set(&config.into());
     }
-}
\ No newline at end of file
+    /// Sets expiration for new lotteries of given type. `None` disables expiration
+    /// - Requires one yoctoNEAR.
+    /// - Requires to be called by the contract owner or `ConfigManager`.
+    #[payable]
+    pub fn set_lottery_expiration(&mut self, lottery_type: String, expiration: Option<LotteryExpiration>) {
+        assert_one_yocto();
+        self.assert_role(Role::ConfigManager);
+
+        let mut config = self.internal_lottery_config();
+        config.set_expiration(LotteryType::from(lottery_type), expiration);
+        config.assert_valid();
+
+        self.lotteries_config.set(&config.into());
+    }
+
+    /// Sets draw mode for new lotteries of given type.
+    /// - Requires one yoctoNEAR.
+    /// - Requires to be called by the contract owner or `ConfigManager`.
+    #[payable]
+    pub fn set_lottery_draw_mode(&mut self, lottery_type: String, draw_mode: DrawMode) {
+        assert_one_yocto();
+        self.assert_role(Role::ConfigManager);
+
+        let mut config = self.internal_lottery_config();
+        config.set_draw_mode(LotteryType::from(lottery_type), draw_mode);
+        config.assert_valid();
+
+        self.lotteries_config.set(&config.into());
+    }
+
+    /// Sets how many tickets one account can hold in new lotteries of given type.
+    /// One call can buy several tickets paying entry fee for every ticket, odds of winning grow with tickets
+    /// - Requires one yoctoNEAR.
+    /// - Requires to be called by the contract owner or `ConfigManager`.
+    /// - `1` keeps one entry per account
+    #[payable]
+    pub fn set_lottery_max_tickets(&mut self, lottery_type: String, max_tickets: u32) {
+        assert_one_yocto();
+        self.assert_role(Role::ConfigManager);
+
+        let mut config = self.internal_lottery_config();
+        config.set_max_tickets(LotteryType::from(lottery_type), max_tickets);
+        config.assert_valid();
+
+        self.lotteries_config.set(&config.into());
+    }
+
+    /// Adds big lottery prize table or replaces prize table with the same id.
+    /// - Requires one yoctoNEAR.
+    /// - Requires to be called by the contract owner or `ConfigManager`.
+    /// - Already created lotteries keep their prize table
+    #[payable]
+    pub fn set_prize_table(&mut self, prize_table_id: String, prize_table: PrizeTable) {
+        assert_one_yocto();
+        self.assert_role(Role::ConfigManager);
+
+        let mut config = self.internal_lottery_config();
+        config.set_prize_table(prize_table_id, prize_table);
+        self.assert_valid_lottery_config(&config);
+
+        self.lotteries_config.set(&config.into());
+    }
+
+    /// Removes big lottery prize table.
+    /// - Requires one yoctoNEAR.
+    /// - Requires to be called by the contract owner or `ConfigManager`.
+    /// - Panics if table is used for new big lotteries
+    #[payable]
+    pub fn remove_prize_table(&mut self, prize_table_id: String) {
+        assert_one_yocto();
+        self.assert_role(Role::ConfigManager);
+
+        let mut config = self.internal_lottery_config();
+        config.remove_prize_table(&prize_table_id);
+        self.assert_valid_lottery_config(&config);
+
+        self.lotteries_config.set(&config.into());
+    }
+
+    /// Sets prize split of new simple lotteries. Every share is a prize of winner rank, E.g [6000, 3000, 1000]
+    /// - Requires one yoctoNEAR.
+    /// - Requires to be called by the contract owner or `ConfigManager`.
+    /// - Shares must sum up to 100% in Basis Points
+    #[payable]
+    pub fn set_prize_split(&mut self, prize_split: Vec<u32>) {
+        assert_one_yocto();
+        self.assert_role(Role::ConfigManager);
+
+        let mut config = self.internal_lottery_config();
+        config.prize_split = prize_split;
+        config.assert_valid();
+
+        self.lotteries_config.set(&config.into());
+    }
+
+    /// Sets time from creation of new timed lotteries to their draw in nanoseconds. `None` disables timed lotteries
+    /// - Requires one yoctoNEAR.
+    /// - Requires to be called by the contract owner or `ConfigManager`.
+    /// - Already created lotteries keep their draw time
+    #[payable]
+    pub fn set_timed_lottery_duration(&mut self, duration: Option<U64>) {
+        assert_one_yocto();
+        self.assert_role(Role::ConfigManager);
+
+        let mut config = self.internal_lottery_config();
+        config.timed_lottery_duration = duration;
+        config.assert_valid();
+
+        self.lotteries_config.set(&config.into());
+    }
+
+    /// Sets options of new jackpot lotteries. `None` disables jackpot lotteries
+    /// - Requires one yoctoNEAR.
+    /// - Requires to be called by the contract owner or `ConfigManager`.
+    /// - Jackpots already rolled over are kept and paid in next rounds
+    #[payable]
+    pub fn set_jackpot_config(&mut self, jackpot: Option<JackpotConfig>) {
+        assert_one_yocto();
+        self.assert_role(Role::ConfigManager);
+
+        let mut config = self.internal_lottery_config();
+        config.jackpot = jackpot;
+        config.assert_valid();
+
+        self.lotteries_config.set(&config.into());
+    }
+
+    /// Sets options of new lotto lotteries. `None` disables lotto
+    /// - Requires one yoctoNEAR.
+    /// - Requires to be called by the contract owner or `ConfigManager`.
+    /// - Already created rounds keep their numbers range and paytable, rolled over pool is kept
+    #[payable]
+    pub fn set_lotto_config(&mut self, lotto: Option<LottoConfig>) {
+        assert_one_yocto();
+        self.assert_role(Role::ConfigManager);
+
+        let mut config = self.internal_lottery_config();
+        config.lotto = lotto;
+        config.assert_valid();
+
+        self.lotteries_config.set(&config.into());
+    }
+
+    /// Sets options of raffles for NFT prizes. `None` disables raffles
+    /// - Requires one yoctoNEAR.
+    /// - Requires to be called by the contract owner or `ConfigManager`.
+    /// - Already created raffles keep their expiration and sponsor share
+    #[payable]
+    pub fn set_raffle_config(&mut self, raffle: Option<RaffleConfig>) {
+        assert_one_yocto();
+        self.assert_role(Role::ConfigManager);
+
+        let mut config = self.internal_lottery_config();
+        config.raffle = raffle;
+        config.assert_valid();
+
+        self.lotteries_config.set(&config.into());
+    }
+
+    /// Sets prize table of new big lotteries.
+    /// - Requires one yoctoNEAR.
+    /// - Requires to be called by the contract owner or `ConfigManager`.
+    #[payable]
+    pub fn set_big_lottery_prize_table(&mut self, prize_table_id: String) {
+        assert_one_yocto();
+        self.assert_role(Role::ConfigManager);
+
+        let mut config = self.internal_lottery_config();
+        config.big_lottery_prize_table = prize_table_id;
+        self.assert_valid_lottery_config(&config);
+
+        self.lotteries_config.set(&config.into());
+    }
+}
@@ -0,0 +1,199 @@
+use near_sdk::collections::LookupMap;
+use near_sdk::serde::Serializer;
+use near_sdk::CryptoHash;
+
+use crate::*;
+
+/// Entries of lottery kept in their own storage records,
+/// so a new entry does not rewrite all previous entries
+#[derive(BorshSerialize, BorshDeserialize)]
+pub struct LotteryEntries {
+    /// entries in entry order
+    list: Vector<Entry>,
+    /// account_id -> index in `list`
+    positions: LookupMap<AccountId, u32>,
+    /// cumulative tickets of entries as Fenwick tree: node `i` holds tickets of entries `(i - lowbit(i), i]`,
+    /// so entry holding a ticket is found without reading all entries
+    ticket_index: LookupMap<u32, u32>,
+    /// tickets of all entries
+    tickets_num: u32,
+    /// hash of `list`, updated on every change
+    entries_hash: CryptoHash
+}
+
+impl LotteryEntries {
+    pub fn new(lottery_id: LotteryId) -> Self {
+        Self {
+            list: Vector::new(StorageKey::LotteryEntries { lottery_id }),
+            positions: LookupMap::new(StorageKey::LotteryEntryPositions { lottery_id }),
+            ticket_index: LookupMap::new(StorageKey::LotteryTicketIndex { lottery_id }),
+            tickets_num: 0,
+            entries_hash: CryptoHash::default()
+        }
+    }
+
+    pub fn len(&self) -> u32 {
+        self.list.len() as _
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.list.is_empty()
+    }
+
+    pub fn tickets_num(&self) -> u32 {
+        self.tickets_num
+    }
+
+    pub fn hash(&self) -> CryptoHash {
+        self.entries_hash
+    }
+
+    pub fn contains(&self, account_id: &AccountId) -> bool {
+        self.positions.contains_key(account_id)
+    }
+
+    pub fn get(&self, index: u32) -> Entry {
+        self.list.get(index as _).expect("Entry was not found")
+    }
+
+    /// Index of entry holding `ticket`, tickets are indexed in entry order and tickets of an entry go in a row
+    pub fn entry_of_ticket(&self, ticket: u32) -> u32 {
+        assert!(ticket < self.tickets_num, "Ticket was not found");
+        let mut index = 0;
+        let mut rest = ticket;
+        let mut step = 1 << (31 - self.len().leading_zeros());
+        while step > 0 {
+            let node = index + step;
+            if node <= self.len() {
+                let tickets = self.ticket_index_node(node);
+                if tickets <= rest {
+                    index = node;
+                    rest -= tickets;
+                }
+            }
+            step >>= 1;
+        }
+        index
+    }
+
+    fn ticket_index_node(&self, node: u32) -> u32 {
+        self.ticket_index.get(&node).expect("Ticket index is corrupted")
+    }
+
+    /// Changes tickets of entry `index` in ticket index
+    fn index_tickets(&mut self, index: u32, added: u32, removed: u32) {
+        let mut node = index + 1;
+        while node <= self.len() {
+            let tickets = self.ticket_index_node(node) + added - removed;
+            self.ticket_index.insert(&node, &tickets);
+            node += node & node.wrapping_neg();
+        }
+    }
+
+    /// Adds node of entry pushed at `index`, it covers tickets of preceding entries up to its lowest bit
+    fn index_pushed_tickets(&mut self, index: u32, tickets: u32) {
+        let node = index + 1;
+        let mut covered = tickets;
+        let mut child = 1;
+        while child < node & node.wrapping_neg() {
+            covered += self.ticket_index_node(node - child);
+            child <<= 1;
+        }
+        self.ticket_index.insert(&node, &covered);
+    }
+
+    /// Tickets of account, zero if not entered
+    pub fn tickets_of(&self, account_id: &AccountId) -> u32 {
+        self.positions
+            .get(account_id)
+            .map(|index| self.get(index).tickets)
+            .unwrap_or_default()
+    }
+
+    pub fn push(&mut self, account_id: &AccountId, tickets: u32) {
+        assert!(!self.contains(account_id), "Already entered");
+        let entry = Entry { account_id: account_id.clone(), tickets };
+        self.entries_hash = add_entry_hash(self.entries_hash, self.len(), &entry);
+        self.index_pushed_tickets(self.len(), tickets);
+        self.positions.insert(account_id, &self.len());
+        self.list.push(&entry);
+        self.tickets_num += tickets;
+    }
+
+    /// Adds tickets to entry of account or creates new entry
+    pub fn add_tickets(&mut self, account_id: &AccountId, tickets: u32) {
+        match self.positions.get(account_id) {
+            Some(index) => {
+                let mut entry = self.get(index);
+                self.entries_hash = sub_entry_hash(self.entries_hash, index, &entry);
+                entry.tickets += tickets;
+                self.entries_hash = add_entry_hash(self.entries_hash, index, &entry);
+                self.list.replace(index as _, &entry);
+                self.index_tickets(index, tickets, 0);
+                self.tickets_num += tickets;
+            },
+            None => self.push(account_id, tickets),
+        }
+    }
+
+    /// Removes entry, the last entry takes its place.
+    /// Returns tickets of removed entry
+    pub fn remove(&mut self, account_id: &AccountId) -> Option<u32> {
+        let index = self.positions.remove(account_id)?;
+        let entry = self.list.swap_remove(index as _);
+        self.entries_hash = sub_entry_hash(self.entries_hash, index, &entry);
+        if index < self.len() {
+            let moved = self.get(index);
+            self.entries_hash = sub_entry_hash(self.entries_hash, self.len(), &moved);
+            self.entries_hash = add_entry_hash(self.entries_hash, index, &moved);
+            self.positions.insert(&moved.account_id, &index);
+            self.index_tickets(index, moved.tickets, entry.tickets);
+        }
+        self.ticket_index.remove(&(self.len() + 1));
+        self.tickets_num -= entry.tickets;
+        Some(entry.tickets)
+    }
+
+    /// Removes the last entry, so entries can be released in batches
+    pub fn pop(&mut self) -> Option<Entry> {
+        let entry = self.list.pop()?;
+        self.positions.remove(&entry.account_id);
+        self.entries_hash = sub_entry_hash(self.entries_hash, self.len(), &entry);
+        self.ticket_index.remove(&(self.len() + 1));
+        self.tickets_num -= entry.tickets;
+        Some(entry)
+    }
+
+    pub fn to_vec(&self) -> Vec<Entry> {
+        self.list.to_vec()
+    }
+
+    pub fn accounts(&self) -> Vec<AccountId> {
+        self.list.iter().map(|entry| entry.account_id).collect()
+    }
+
+    /// Holder of every ticket in ticket order
+    pub fn ticket_holders(&self) -> Vec<AccountId> {
+        ticket_holders(&self.to_vec())
+    }
+
+    /// Holders of every ticket if there are at most `limit` tickets, otherwise entries are not read
+    pub fn ticket_holders_up_to(&self, limit: u32) -> Vec<AccountId> {
+        if self.tickets_num > limit {
+            return vec![];
+        }
+        self.ticket_holders()
+    }
+
+    pub fn paged(&self, from_index: u32, limit: u32) -> Vec<Entry> {
+        (from_index..std::cmp::min(from_index.saturating_add(limit), self.len()))
+            .map(|index| self.get(index))
+            .collect()
+    }
+}
+
+impl Serialize for LotteryEntries {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(self.list.iter())
+    }
+}
@@ -0,0 +1,307 @@
+use std::collections::HashMap;
+
+use near_sdk::collections::LookupMap;
+use near_sdk::{CryptoHash, Timestamp};
+
+use crate::*;
+use crate::views::LottoTierPool;
+
+/// Numbers picked by lotto entrant
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+#[serde(crate = "near_sdk::serde")]
+pub struct LottoTicket {
+    pub account_id: AccountId,
+    /// picked numbers in ascending order
+    pub numbers: Vec<u32>
+}
+
+/// Rounds of lotto with the same token and entry fee
+#[derive(BorshSerialize, BorshDeserialize, PartialEq, Clone, Debug)]
+pub struct LottoSeries {
+    pub lottery_token_id: AccountId,
+    pub entry_fee: U128
+}
+
+/// Lotto paytable tier settled in batches: entries are counted among tier winners first,
+/// then tier pool is split between them
+#[derive(BorshSerialize, BorshDeserialize)]
+pub struct LottoTierPayout {
+    pub matches: u32,
+    /// share of prize pool
+    pub pool: Balance,
+    /// winners counted so far
+    pub winners_num: u32
+}
+
+/// Round of number-pick lotto.
+/// Every entrant picks `pick_count` distinct numbers from `1..=max_number`, the same amount of numbers is drawn
+/// after draw time. Prize pool is split between paytable tiers by matched numbers, tiers without winners roll over
+#[derive(BorshSerialize, BorshDeserialize, Serialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct Lotto {
+    pub id: LotteryId,
+    pub lottery_token_id: AccountId,
+    pub lottery_status: LotteryStatus,
+    /// A list of account_ids in this lottery
+    pub entries: LotteryEntries,
+    /// account_id -> picked numbers
+    #[serde(skip)]
+    pub picks: LookupMap<AccountId, Vec<u32>>,
+    /// hash of picked numbers of all entries, updated on every change, see `hash_picks`
+    #[serde(skip)]
+    pub picks_hash: CryptoHash,
+    /// Amount to participate a lottery
+    pub entry_fee: Balance,
+    /// Current amount deposited
+    pub current_pool: Balance,
+    /// Block timestamp after which entries are closed and lottery can be drawn
+    pub draw_at: Timestamp,
+    pub pick_count: u32,
+    pub max_number: u32,
+    /// Paytable in effect when lottery was created
+    pub paytable: Vec<LottoTier>,
+    /// Drawn numbers in ascending order, empty until lottery is drawn
+    pub drawn_numbers: Vec<u32>,
+    pub refferal_transfered: Balance,
+    /// Referral rewards paid on entry: entrant -> reward
+    pub referrals: HashMap<AccountId, Balance>,
+    /// Fee parameters in effect when lottery was created
+    pub fees: LotteryFees,
+    /// Inputs and result of the draw
+    pub draw_record: Option<DrawRecord>
+}
+
+impl Lotto {
+    pub fn new(
+        id: LotteryId,
+        lottery_token_id: AccountId,
+        entry_fee: Balance,
+        draw_at: Timestamp,
+        config: LottoConfig,
+        fees: LotteryFees
+    ) -> Self {
+        assert!(entry_fee > 0, "entry_fee cannot be zero");
+        assert!(draw_at > env::block_timestamp(), "Draw time must be in the future");
+        Self {
+            id,
+            lottery_token_id,
+            lottery_status: LotteryStatus::Active,
+            entries: LotteryEntries::new(id),
+            picks: LookupMap::new(StorageKey::LottoPicks { lottery_id: id }),
+            picks_hash: CryptoHash::default(),
+            entry_fee,
+            current_pool: 0,
+            draw_at,
+            pick_count: config.pick_count,
+            max_number: config.max_number,
+            paytable: config.paytable,
+            drawn_numbers: vec![],
+            refferal_transfered: 0,
+            referrals: HashMap::new(),
+            fees,
+            draw_record: None
+        }
+    }
+
+    pub fn series(&self) -> LottoSeries {
+        LottoSeries {
+            lottery_token_id: self.lottery_token_id.clone(),
+            entry_fee: U128(self.entry_fee)
+        }
+    }
+
+    pub fn is_draw_time_reached(&self) -> bool {
+        env::block_timestamp() >= self.draw_at
+    }
+
+    pub fn assert_is_finished(&self) {
+        assert_eq!(self.lottery_status, LotteryStatus::Finished, "Lottery is not finished");
+        assert!(!self.drawn_numbers.is_empty());
+    }
+
+    /// Entries are closed at draw time, lottery waits for `draw`
+    pub fn update(&mut self) -> LotteryStatus {
+        if self.lottery_status == LotteryStatus::Active && self.is_draw_time_reached() {
+            self.lottery_status = LotteryStatus::Drawing;
+        }
+        self.lottery_status
+    }
+
+    /// Draws numbers after draw time
+    pub fn draw(&mut self) -> LotteryStatus {
+        assert!(self.is_draw_time_reached(), "Draw is available after draw time");
+        assert_eq!(self.lottery_status, LotteryStatus::Drawing, "Lottery is not waiting for draw");
+        self.lottery_status = LotteryStatus::Finished;
+        self.set_winner(env::random_seed_array());
+        self.lottery_status
+    }
+
+    pub fn add_refferal_transfered(&mut self, account_id: &AccountId, amount: Balance) {
+        self.refferal_transfered += amount;
+        *self.referrals.entry(account_id.clone()).or_default() += amount;
+    }
+
+    /// Removes entry of active lottery with its numbers.
+    /// Returns refund: entry fee minus referral reward already paid for that entry
+    pub fn leave(&mut self, account_id: &AccountId) -> Balance {
+        assert_eq!(self.lottery_status, LotteryStatus::Active, "Lottery is not active");
+        self.entries.remove(account_id).expect("Not entered");
+        let numbers = self.picks.remove(account_id).expect("Numbers were not found");
+        self.picks_hash = sub_picks_hash(self.picks_hash, account_id, &numbers);
+        self.current_pool -= self.entry_fee;
+
+        let referral_reward = self.referrals.remove(account_id).unwrap_or_default();
+        self.refferal_transfered -= referral_reward;
+        self.entry_fee - referral_reward
+    }
+
+    /// Round waiting for draw can be cancelled too, so a round which cannot be drawn doesn't block its series
+    pub fn cancel(&mut self) {
+        assert!(
+            matches!(self.update(), LotteryStatus::Active | LotteryStatus::Drawing),
+            "Lottery is not active"
+        );
+        self.lottery_status = LotteryStatus::Cancelled;
+    }
+
+    /// Draw lottery entry with picked numbers
+    pub fn draw_enter(&mut self, account_id: &AccountId, amount: Balance, mut numbers: Vec<u32>) -> LotteryStatus {
+        assert_eq!(self.update(), LotteryStatus::Active, "Lottery entries are closed");
+        assert_eq!(
+            amount, self.entry_fee,
+            "Supplied: {}, but Required amount to paticipate is: {}",
+            self.entry_fee, amount
+        );
+        assert_eq!(numbers.len() as u32, self.pick_count, "Expected {} numbers", self.pick_count);
+        numbers.sort_unstable();
+        numbers.dedup();
+        assert_eq!(numbers.len() as u32, self.pick_count, "Numbers must be distinct");
+        assert!(
+            numbers[0] >= 1 && numbers[numbers.len() - 1] <= self.max_number,
+            "Numbers must be from 1 to {}", self.max_number
+        );
+        self.entries.push(account_id, 1);
+        self.picks_hash = add_picks_hash(self.picks_hash, account_id, &numbers);
+        self.picks.insert(account_id, &numbers);
+        self.current_pool += amount;
+        self.lottery_status
+    }
+
+    pub fn tickets(&self, from_index: u32, limit: u32) -> Vec<LottoTicket> {
+        self.entries
+            .paged(from_index, limit)
+            .into_iter()
+            .map(|entry| LottoTicket {
+                numbers: self.picks.get(&entry.account_id).unwrap_or_default(),
+                account_id: entry.account_id
+            })
+            .collect()
+    }
+
+    /// Draws numbers, entries are not read: winners are counted by matched numbers when entries are settled
+    fn set_winner(&mut self, random_seed: [u8; 32]) {
+        let drawn_numbers = draw_lotto_numbers(random_seed, self.id, self.pick_count, self.max_number);
+        let mut draw_record = DrawRecord::new(
+            self.id,
+            LotteryType::Lotto,
+            random_seed,
+            self.entries.hash(),
+            self.entries.len(),
+            self.entries.tickets_num(),
+            vec![],
+            vec![]
+        );
+        draw_record.ticket_range = Some(self.max_number);
+        draw_record.drawn_numbers = drawn_numbers.clone();
+        draw_record.picks_hash = Some(self.picks_hash.into());
+        draw_record.winning_matches = self.paytable.iter().map(|tier| tier.matches).collect();
+        self.drawn_numbers = drawn_numbers;
+        self.draw_record = Some(draw_record);
+    }
+}
+
+impl Contract {
+    pub (crate) fn internal_lotto_rollover(&self, series: &LottoSeries) -> Balance {
+        self.lotto_rollovers.get(series).unwrap_or_default()
+    }
+
+    /// Only one round of series is open at a time, so rollover pool is paid in round order
+    pub (crate) fn internal_open_lotto_round(&mut self, series: &LottoSeries, lottery_id: LotteryId) {
+        assert!(!self.open_lotto_rounds.contains_key(series), "Lotto round is waiting for draw");
+        self.open_lotto_rounds.insert(series, &lottery_id);
+    }
+
+    /// Splits round pool with rollover of series between paytable tiers.
+    /// Entries are queued for settlement, tier pools are paid to tier winners in batches, see `StoredSettlement`.
+    /// Share of prize pool not taken by tiers rolls over to the next round.
+    /// Returns pools of every tier and prize pool
+    pub (crate) fn internal_settle_lotto(
+        &mut self,
+        lottery: Lotto,
+        round_pool: Balance,
+        claim_mode: bool
+    ) -> (Vec<LottoTierPool>, Balance) {
+        let series = lottery.series();
+        let prize_pool = self.internal_lotto_rollover(&series) + round_pool;
+
+        let tiers = lottery.paytable
+            .iter()
+            .map(|tier| LottoTierPayout {
+                matches: tier.matches,
+                pool: ratio(prize_pool, tier.pool_ratio),
+                winners_num: 0
+            })
+            .collect::<Vec<_>>();
+        let tier_pools = tiers
+            .iter()
+            .map(|tier| LottoTierPool { matches: tier.matches, pool: U128(tier.pool) })
+            .collect();
+        let allocated: Balance = tiers.iter().map(|tier| tier.pool).sum();
+        self.lotto_rollovers.insert(&series, &(prize_pool - allocated));
+
+        let payout = EntriesPayout::LottoTiers {
+            series,
+            drawn_numbers: lottery.drawn_numbers,
+            tiers,
+            counted_num: 0,
+            claim_mode
+        };
+        self.settlements.insert(
+            &lottery.id,
+            &StoredSettlement::new(lottery.lottery_token_id, lottery.entries, Some(lottery.picks), payout, allocated)
+        );
+        (tier_pools, prize_pool)
+    }
+
+    /// All entries of lotto are counted: pools of tiers without winners and rounding remainders roll over
+    /// to the current round of series, winners nums are stored in draw record
+    pub (crate) fn internal_finish_lotto_count(&mut self, lottery_id: LotteryId, settlement: &mut StoredSettlement) {
+        let (series, tiers) = match &settlement.payout {
+            EntriesPayout::LottoTiers { series, tiers, .. } => (series, tiers),
+            _ => return,
+        };
+        let rolled_over: Balance = tiers
+            .iter()
+            .map(|tier| match tier.winners_num {
+                0 => tier.pool,
+                winners_num => tier.pool % winners_num as u128,
+            })
+            .sum();
+        let rollover = self.internal_lotto_rollover(series) + rolled_over;
+        self.lotto_rollovers.insert(series, &rollover);
+        settlement.pending_amount -= rolled_over;
+
+        if let Some(mut draw_record) = self.draws.get(&lottery_id) {
+            draw_record.tier_winners_nums = tiers.iter().map(|tier| tier.winners_num).collect();
+            self.draws.insert(&lottery_id, &draw_record);
+        }
+        let tiers = tiers
+            .iter()
+            .map(|tier| format!("{} matches: {}", tier.matches, tier.winners_num))
+            .collect::<Vec<_>>();
+        log!(
+            "Lotto {} winners {}, rolled over: {} yocto{}",
+            lottery_id, tiers.join(", "), rolled_over, match_token_id(&settlement.lottery_token_id)
+        );
+    }
+}
@@ -0,0 +1,142 @@
+use crate::*;
+use near_contract_standards::non_fungible_token::core::NonFungibleTokenReceiver;
+use near_contract_standards::non_fungible_token::TokenId;
+use near_sdk::json_types::U128;
+use near_sdk::{Gas, is_promise_success, serde_json, PromiseOrValue, ONE_YOCTO};
+
+const GAS_FOR_NFT_TRANSFER: Gas = Gas(Gas::ONE_TERA.0 * 15);
+const GAS_FOR_AFTER_NFT_TRANSFER: Gas = Gas(Gas::ONE_TERA.0 * 5);
+/// Estimated gas for checked NFT transfer including callback and data receipt
+pub const GAS_FOR_NFT_PAYOUT: Gas = Gas(Gas::ONE_TERA.0 * 30);
+
+/// Raffle options passed by sponsor along with NFT
+/// This is `msg` from non fungible token transfer
+#[derive(Deserialize)]
+#[cfg_attr(not(target_arch = "wasm32"), derive(Debug, Serialize))]
+#[serde(crate = "near_sdk::serde")]
+pub enum NftReceiverMsg {
+    Raffle {
+        /// token of entry fees, NEAR if not set
+        lottery_token_id: Option<AccountId>,
+        /// one from entry fees of lottery config
+        entry_fee: U128,
+        /// one from num participants of simple lotteries
+        num_participants: u32
+    }
+}
+
+#[near_bindgen]
+impl NonFungibleTokenReceiver for Contract {
+    /// Receives NFT from sponsor and creates raffle with NFT prize.
+    /// - Requires to be called by NFT contract accepted for raffles.
+    /// - NFT is returned to sponsor if raffle cannot be created
+    fn nft_on_transfer(
+        &mut self,
+        sender_id: AccountId,
+        previous_owner_id: AccountId,
+        token_id: TokenId,
+        msg: String,
+    ) -> PromiseOrValue<bool> {
+        let nft_contract_id = env::predecessor_account_id();
+
+        let nft_receiver_msg: NftReceiverMsg =
+            serde_json::from_str(&msg).expect("Can't parse NftReceiverMsg");
+
+        match nft_receiver_msg {
+            NftReceiverMsg::Raffle { lottery_token_id, entry_fee, num_participants } => {
+                let lottery_id = self.internal_create_raffle(
+                    previous_owner_id.clone(),
+                    RafflePrize { nft_contract_id, token_id },
+                    lottery_token_id.unwrap_or_else(near),
+                    num_participants,
+                    entry_fee.0
+                );
+                log!(
+                    "Raffle created. Lottery ID: {}, sponsor: @{}, sent by: @{}",
+                    lottery_id, previous_owner_id, sender_id
+                );
+            },
+        }
+
+        PromiseOrValue::Value(false)
+    }
+}
+
+impl Contract {
+    /// Transfers NFT to `account_id`.
+    /// - Transfer is followed by callback. Failed transfers are stored in `failed_nft_transfers`
+    /// - If there is not enough gas left to transfer, NFT is stored in `failed_nft_transfers` immediately
+    pub (crate) fn internal_nft_payout(&mut self, account_id: &AccountId, prize: &RafflePrize) {
+        if env::prepaid_gas() - env::used_gas() < GAS_FOR_NFT_PAYOUT {
+            self.internal_record_failed_nft_transfer(account_id, prize);
+            return;
+        }
+
+        Promise::new(prize.nft_contract_id.clone())
+            .function_call(
+                "nft_transfer".to_string(),
+                serde_json::json!({
+                    "receiver_id": account_id,
+                    "token_id": prize.token_id,
+                })
+                .to_string()
+                .into_bytes(),
+                ONE_YOCTO,
+                GAS_FOR_NFT_TRANSFER
+            )
+            .then(Self::ext(env::current_account_id())
+                .with_static_gas(GAS_FOR_AFTER_NFT_TRANSFER)
+                .after_nft_transfer(account_id.clone(), prize.clone())
+            );
+    }
+
+    pub (crate) fn internal_record_failed_nft_transfer(&mut self, account_id: &AccountId, prize: &RafflePrize) {
+        let mut failed = self.failed_nft_transfers.get(account_id).unwrap_or_default();
+        failed.push(prize.clone());
+        self.failed_nft_transfers.insert(account_id, &failed);
+        log!(
+            "NFT {} of {} to @{} was not transfered. Stored for retry",
+            prize.token_id, prize.nft_contract_id, account_id
+        );
+    }
+}
+
+#[near_bindgen]
+impl Contract {
+    /// Stores NFT to `failed_nft_transfers` if NFT transfer was not succeeded
+    #[private]
+    pub fn after_nft_transfer(&mut self, account_id: AccountId, prize: RafflePrize) -> bool {
+        let promise_success = is_promise_success();
+        if !promise_success {
+            self.internal_record_failed_nft_transfer(&account_id, &prize);
+        }
+        promise_success
+    }
+
+    /// Retries failed transfer of NFT to the caller.
+    /// - Requires one yoctoNEAR.
+    /// - Panics if caller has no failed transfer of that NFT
+    /// - If transfer fails again, NFT is stored back to failed transfers
+    #[payable]
+    pub fn retry_failed_nft_transfer(&mut self, nft_contract_id: AccountId, token_id: TokenId) {
+        assert_one_yocto();
+        let account_id = env::predecessor_account_id();
+        let mut failed = self.failed_nft_transfers.get(&account_id).expect("No failed NFT transfers found");
+        let index = failed
+            .iter()
+            .position(|prize| prize.nft_contract_id == nft_contract_id && prize.token_id == token_id)
+            .expect("No failed transfer found for NFT");
+        let prize = failed.remove(index);
+        if failed.is_empty() {
+            self.failed_nft_transfers.remove(&account_id);
+        } else {
+            self.failed_nft_transfers.insert(&account_id, &failed);
+        }
+        self.internal_nft_payout(&account_id, &prize);
+    }
+
+    /// Returns NFTs of account which were not transfered
+    pub fn get_failed_nft_transfers(&self, account_id: AccountId) -> Vec<RafflePrize> {
+        self.failed_nft_transfers.get(&account_id).unwrap_or_default()
+    }
+}
@@ -0,0 +1,83 @@
+use crate::*;
+
+/// Paused lottery entries.
+/// Refunds, claims and payouts are never paused
+#[derive(BorshSerialize, BorshDeserialize, Serialize, Deserialize, Default, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct PauseState {
+    /// all entries are paused
+    pub all: bool,
+    /// entries with these tokens are paused
+    pub tokens: Vec<AccountId>,
+    /// entries to these lottery types are paused
+    pub lottery_types: Vec<LotteryType>
+}
+
+impl PauseState {
+    pub fn is_paused(&self, token_id: &AccountId, lottery_type: LotteryType) -> bool {
+        self.all || self.tokens.contains(token_id) || self.lottery_types.contains(&lottery_type)
+    }
+
+    fn set_paused(&mut self, token_id: Option<AccountId>, lottery_type: Option<LotteryType>, paused: bool) {
+        if token_id.is_none() && lottery_type.is_none() {
+            self.all = paused;
+        }
+        if let Some(token_id) = token_id {
+            self.tokens.retain(|token| token != &token_id);
+            if paused {
+                self.tokens.push(token_id);
+            }
+        }
+        if let Some(lottery_type) = lottery_type {
+            self.lottery_types.retain(|kind| kind != &lottery_type);
+            if paused {
+                self.lottery_types.push(lottery_type);
+            }
+        }
+    }
+}
+
+impl Contract {
+    pub (crate) fn internal_pause_state(&self) -> PauseState {
+        self.pause_state.get().unwrap_or_default()
+    }
+
+    pub (crate) fn assert_entries_not_paused(&self, token_id: &AccountId, lottery_type: LotteryType) {
+        assert!(
+            !self.internal_pause_state().is_paused(token_id, lottery_type),
+            "Lottery entries are paused"
+        );
+    }
+
+    fn internal_set_paused(&mut self, token_id: Option<AccountId>, lottery_type: Option<String>, paused: bool) {
+        let mut pause_state = self.internal_pause_state();
+        pause_state.set_paused(token_id, lottery_type.map(LotteryType::from), paused);
+        self.pause_state.set(&pause_state);
+    }
+}
+
+#[near_bindgen]
+impl Contract {
+    /// Pauses lottery entries.
+    /// - Requires one yoctoNEAR.
+    /// - Requires to be called by the contract owner or `Pauser`.
+    /// - If `token_id` is given - pauses entries with that token ("near" for NEAR)
+    /// - If `lottery_type` is given - pauses entries to that lottery type
+    /// - If none of them given - pauses all entries
+    #[payable]
+    pub fn pause(&mut self, token_id: Option<AccountId>, lottery_type: Option<String>) {
+        assert_one_yocto();
+        self.assert_role(Role::Pauser);
+        self.internal_set_paused(token_id, lottery_type, true);
+    }
+
+    /// Unpauses lottery entries. Arguments are the same as for `pause`
+    /// - Requires one yoctoNEAR.
+    /// - Requires to be called by the contract owner or `Pauser`.
+    #[payable]
+    pub fn unpause(&mut self, token_id: Option<AccountId>, lottery_type: Option<String>) {
+        assert_one_yocto();
+        self.assert_role(Role::Pauser);
+        self.internal_set_paused(token_id, lottery_type, false);
+    }
+}
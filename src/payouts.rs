@@ -0,0 +1,164 @@
+use std::collections::HashMap;
+
+use near_sdk::Gas;
+
+use crate::*;
+use crate::fungible_token::{GAS_FOR_FT_PAYOUT, GAS_FOR_NEAR_PAYOUT};
+
+/// Gas kept for the rest of the call after payouts were sent
+const GAS_RESERVED_AFTER_PAYOUTS: Gas = Gas(Gas::ONE_TERA.0 * 50);
+
+/// Checks there is enough gas left to send checked transfer of `token_id`
+pub (crate) fn has_gas_for_payout(token_id: &AccountId) -> bool {
+    let payout_gas = if token_id == &near() {
+        GAS_FOR_NEAR_PAYOUT
+    } else {
+        GAS_FOR_FT_PAYOUT
+    };
+    env::prepaid_gas() - env::used_gas() >= payout_gas + GAS_RESERVED_AFTER_PAYOUTS
+}
+
+impl Contract {
+    /// Transfers `amount` of `token_id` (NEAR or FT) to `account_id`.
+    /// - Every transfer is followed by callback. Failed transfers are stored in `failed_payouts`
+    /// - If there is not enough gas left to transfer, payout is stored in `failed_payouts` immediately
+    pub (crate) fn internal_payout(
+        &mut self,
+        account_id: &AccountId,
+        token_id: &AccountId,
+        amount: Balance
+    ) {
+        if !has_gas_for_payout(token_id) {
+            self.internal_record_failed_payout(account_id, token_id, amount);
+            return;
+        }
+
+        if token_id == &near() {
+            self.internal_near_transfer_checked(account_id, amount);
+        } else {
+            self.internal_ft_transfer_checked(account_id, token_id, amount);
+        }
+    }
+
+    /// Pays reward to `account_id` or credits it to claimable balance in claim mode
+    pub (crate) fn internal_reward(
+        &mut self,
+        account_id: &AccountId,
+        token_id: &AccountId,
+        amount: Balance,
+        claim_mode: bool
+    ) {
+        if claim_mode {
+            self.internal_credit_claimable(account_id, token_id, amount);
+        } else {
+            self.internal_payout(account_id, token_id, amount);
+        }
+    }
+
+    pub (crate) fn internal_credit_claimable(
+        &mut self,
+        account_id: &AccountId,
+        token_id: &AccountId,
+        amount: Balance
+    ) {
+        let mut claimable = self.claimable.get(account_id).unwrap_or_default();
+        *claimable.entry(token_id.clone()).or_default() += amount;
+        self.claimable.insert(account_id, &claimable);
+    }
+
+    pub (crate) fn internal_claimable(&self, account_id: &AccountId) -> HashMap<AccountId, Balance> {
+        self.claimable.get(account_id).unwrap_or_default()
+    }
+
+    pub (crate) fn internal_record_failed_payout(
+        &mut self,
+        account_id: &AccountId,
+        token_id: &AccountId,
+        amount: Balance
+    ) {
+        let mut failed = self.failed_payouts.get(account_id).unwrap_or_default();
+        *failed.entry(token_id.clone()).or_default() += amount;
+        self.failed_payouts.insert(account_id, &failed);
+        log!(
+            "Payout ( {} yocto{} ) to @{} was not transfered. Stored for retry",
+            amount, match_token_id(token_id), account_id
+        );
+    }
+
+    fn internal_take_failed_payout(&mut self, account_id: &AccountId, token_id: &AccountId) -> Balance {
+        let mut failed = self.failed_payouts.get(account_id).expect("No failed payouts found");
+        let amount = failed.remove(token_id).expect("No failed payouts found for token");
+        if failed.is_empty() {
+            self.failed_payouts.remove(account_id);
+        } else {
+            self.failed_payouts.insert(account_id, &failed);
+        }
+        amount
+    }
+
+    pub (crate) fn internal_failed_payouts(&self, account_id: &AccountId) -> HashMap<AccountId, Balance> {
+        self.failed_payouts.get(account_id).unwrap_or_default()
+    }
+}
+
+#[near_bindgen]
+impl Contract {
+    /// Retries failed payout of given token to the caller.
+    /// - Requires one yoctoNEAR.
+    /// - Panics if caller has no failed payouts for that token
+    /// - If transfer fails again, amount is stored back to failed payouts
+    #[payable]
+    pub fn retry_failed_payout(&mut self, token_id: AccountId) {
+        assert_one_yocto();
+        let account_id = env::predecessor_account_id();
+        let amount = self.internal_take_failed_payout(&account_id, &token_id);
+        self.internal_payout(&account_id, &token_id, amount);
+    }
+
+    /// Transfers claimable reward of given token to the caller.
+    /// - Requires one yoctoNEAR.
+    /// - Panics if caller has nothing to claim in that token
+    /// - Failed transfer is stored to failed payouts
+    #[payable]
+    pub fn claim(&mut self, token_id: AccountId) {
+        assert_one_yocto();
+        let account_id = env::predecessor_account_id();
+        let mut claimable = self.claimable.get(&account_id).expect("Nothing to claim");
+        let amount = claimable.remove(&token_id).expect("Nothing to claim for token");
+        if claimable.is_empty() {
+            self.claimable.remove(&account_id);
+        } else {
+            self.claimable.insert(&account_id, &claimable);
+        }
+        self.internal_payout(&account_id, &token_id, amount);
+    }
+
+    /// Transfers all claimable rewards to the caller.
+    /// - Requires one yoctoNEAR.
+    /// - Panics if caller has nothing to claim
+    #[payable]
+    pub fn claim_all(&mut self) {
+        assert_one_yocto();
+        let account_id = env::predecessor_account_id();
+        let claimable = self.claimable.remove(&account_id).expect("Nothing to claim");
+        for (token_id, amount) in claimable {
+            self.internal_payout(&account_id, &token_id, amount);
+        }
+    }
+
+    /// Returns claimable rewards of account as a list of ( token_id, amount )
+    pub fn get_claimable(&self, account_id: AccountId) -> Vec<(AccountId, U128)> {
+        self.internal_claimable(&account_id)
+            .into_iter()
+            .map(|(token_id, amount)| (token_id, U128(amount)))
+            .collect()
+    }
+
+    /// Returns failed payouts of account as a list of ( token_id, amount )
+    pub fn get_failed_payouts(&self, account_id: AccountId) -> Vec<(AccountId, U128)> {
+        self.internal_failed_payouts(&account_id)
+            .into_iter()
+            .map(|(token_id, amount)| (token_id, U128(amount)))
+            .collect()
+    }
+}
@@ -0,0 +1,115 @@
+use crate::*;
+
+/// Prize table of big lotteries before prize tables were configurable
+pub const DEFAULT_PRIZE_TABLE: &str = "default";
+
+/// Group of big lottery winners paid the same amount
+#[derive(BorshSerialize, BorshDeserialize, Serialize, Deserialize, PartialEq, Clone, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct PrizeTier {
+    pub name: String,
+    /// share of participants winning this tier, `MAX_RATIO` is all participants
+    pub participants_ratio: u32,
+    /// payout of every winner of this tier as ratio of entry fee, E.g 15000 is entry fee +50%
+    pub payout_ratio: u32
+}
+
+/// Prize tiers of big lottery from the top prize down.
+/// - tier paying less than entry fee is a cashback tier, it is paid through cashbacks queue
+/// - if tiers cover all participants, the last tier takes the rest of participants
+#[derive(BorshSerialize, BorshDeserialize, Serialize, Deserialize, PartialEq, Clone, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct PrizeTable {
+    pub tiers: Vec<PrizeTier>
+}
+
+impl Default for PrizeTable {
+    /// 20% of participants get +50%, 30% get +10%, the rest get 50% cashback
+    fn default() -> Self {
+        let tier = |name: &str, participants_ratio, payout_ratio| PrizeTier {
+            name: name.to_string(),
+            participants_ratio,
+            payout_ratio
+        };
+        Self {
+            tiers: vec![
+                tier("UpToFiftyPercent", 2000, 15000),
+                tier("UpToTenPercent", 3000, 11000),
+                tier("Cashback", 5000, 5000),
+            ]
+        }
+    }
+}
+
+impl PrizeTable {
+    pub fn assert_valid(&self) {
+        assert!(!self.tiers.is_empty(), "Prize table has no tiers");
+        for tier in &self.tiers {
+            assert!(
+                tier.participants_ratio > 0 && tier.payout_ratio > 0,
+                "Prize tier {} pays nothing", tier.name
+            );
+        }
+        assert!(
+            self.participants_ratio() <= MAX_RATIO,
+            "Prize tiers cannot cover more than all participants"
+        );
+        assert!(
+            self.tiers.iter().filter(|tier| tier.payout_ratio < MAX_RATIO).count() <= 1,
+            "Prize table can have only one cashback tier"
+        );
+    }
+
+    /// Checks payouts with referral rewards are less than pool of lottery with `num_participants` entries.
+    /// Payouts are rounded down, so checking them for entry fee of `MAX_RATIO` covers any entry fee
+    pub fn assert_payouts_within_pool(&self, num_participants: u32, referral_ratio: u32) {
+        let entry_fee = MAX_RATIO as Balance;
+        let referral_rewards = ratio(entry_fee, referral_ratio) * num_participants as u128;
+        assert!(
+            self.payout(entry_fee, &self.winners_nums(num_participants)) + referral_rewards < entry_fee * num_participants as u128,
+            "Prize table payouts exceed pool minus fees for {} participants", num_participants
+        );
+    }
+
+    fn participants_ratio(&self) -> u32 {
+        self.tiers.iter().map(|tier| tier.participants_ratio).sum()
+    }
+
+    /// Tiers cover all participants, the last tier takes the rest
+    pub fn covers_all(&self) -> bool {
+        self.participants_ratio() == MAX_RATIO
+    }
+
+    pub fn cashback_tier(&self) -> Option<usize> {
+        self.tiers.iter().position(|tier| tier.payout_ratio < MAX_RATIO)
+    }
+
+    /// Winners num of every tier.
+    /// Tier shares are accumulated and rounded down
+    pub fn winners_nums(&self, num_participants: u32) -> Vec<u32> {
+        let mut covered_ratio = 0;
+        let mut covered_num = 0;
+        self.tiers
+            .iter()
+            .map(|tier| {
+                covered_ratio += tier.participants_ratio;
+                let num = u128_ratio(num_participants as _, covered_ratio as _, MAX_RATIO as _) as u32 - covered_num;
+                covered_num += num;
+                num
+            })
+            .collect()
+    }
+
+    pub fn tier_payout(&self, tier_index: usize, entry_fee: Balance) -> Balance {
+        u128_ratio(entry_fee, self.tiers[tier_index].payout_ratio as _, MAX_RATIO as _)
+    }
+
+    /// Total payout to all winners
+    pub fn payout(&self, entry_fee: Balance, tier_winners_nums: &[u32]) -> Balance {
+        tier_winners_nums
+            .iter()
+            .enumerate()
+            .map(|(tier_index, num)| self.tier_payout(tier_index, entry_fee) * *num as u128)
+            .sum()
+    }
+}
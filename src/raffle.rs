@@ -0,0 +1,290 @@
+use std::collections::HashMap;
+
+use near_contract_standards::non_fungible_token::TokenId;
+use near_sdk::Timestamp;
+
+use crate::*;
+
+/// NFT deposited by sponsor as raffle prize
+#[derive(BorshSerialize, BorshDeserialize, Serialize, Deserialize, PartialEq, Clone, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct RafflePrize {
+    pub nft_contract_id: AccountId,
+    pub token_id: TokenId
+}
+
+/// Lottery with NFT prize deposited by sponsor.
+/// Raffle is drawn when it is filled or expires, the winner gets the NFT.
+/// Collected entry fees are split between sponsor and contract fees
+#[derive(BorshSerialize, BorshDeserialize, Serialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct Raffle {
+    pub id: LotteryId,
+    pub lottery_token_id: AccountId,
+    pub lottery_status: LotteryStatus,
+    /// A list of account_ids in this lottery
+    pub entries: LotteryEntries,
+    /// Amount to participate a lottery
+    pub entry_fee: Balance,
+    /// Current amount deposited
+    pub current_pool: Balance,
+    /// Total amount of filled raffle
+    pub required_pool: Balance,
+    pub sponsor: AccountId,
+    pub prize: RafflePrize,
+    /// share of collected entry fees paid to sponsor in Basis Points
+    pub sponsor_ratio: u32,
+    /// Block timestamp after which entries are closed. Raffle without entries is closed and prize is returned
+    pub expires_at: Timestamp,
+    /// Block of the final entry of filled raffle
+    pub filled_block_height: Option<BlockHeight>,
+    pub winner: Option<AccountId>,
+    pub refferal_transfered: Balance,
+    /// Referral rewards paid on entry: entrant -> reward
+    pub referrals: HashMap<AccountId, Balance>,
+    /// Fee parameters in effect when lottery was created
+    pub fees: LotteryFees,
+    /// Inputs and result of the draw
+    pub draw_record: Option<DrawRecord>
+}
+
+impl Raffle {
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        id: LotteryId,
+        lottery_token_id: AccountId,
+        num_participants: u32,
+        entry_fee: Balance,
+        sponsor: AccountId,
+        prize: RafflePrize,
+        expires_at: Timestamp,
+        sponsor_ratio: u32,
+        fees: LotteryFees
+    ) -> Self {
+        assert!(entry_fee > 0, "entry_fee cannot be zero");
+        assert!(num_participants > 0, "num_participants cannot be zero");
+        Self {
+            id,
+            lottery_token_id,
+            lottery_status: LotteryStatus::Active,
+            entries: LotteryEntries::new(id),
+            entry_fee,
+            current_pool: 0,
+            required_pool: entry_fee * num_participants as u128,
+            sponsor,
+            prize,
+            sponsor_ratio,
+            expires_at,
+            filled_block_height: None,
+            winner: None,
+            refferal_transfered: 0,
+            referrals: HashMap::new(),
+            fees,
+            draw_record: None
+        }
+    }
+
+    pub fn is_pools_equal(&self) -> bool {
+        self.current_pool == self.required_pool
+    }
+
+    pub fn is_expiration_reached(&self) -> bool {
+        env::block_timestamp() >= self.expires_at
+    }
+
+    /// Raffle without entries is not drawn at expiration, its prize is returned to sponsor
+    pub fn is_expired(&self) -> bool {
+        self.lottery_status == LotteryStatus::Active && self.is_expiration_reached()
+    }
+
+    pub fn assert_is_finished(&self) {
+        assert_eq!(self.lottery_status, LotteryStatus::Finished, "Lottery is not finished");
+        assert!(self.winner.is_some());
+    }
+
+    /// Filled raffle and expired raffle with entries wait for `draw`
+    pub fn update(&mut self) -> LotteryStatus {
+        if self.lottery_status == LotteryStatus::Active {
+            if self.is_pools_equal() {
+                self.lottery_status = LotteryStatus::Drawing;
+                self.filled_block_height = Some(env::block_height());
+            } else if self.is_expiration_reached() && !self.entries.is_empty() {
+                self.lottery_status = LotteryStatus::Drawing;
+            }
+        }
+        self.lottery_status
+    }
+
+    /// Picks winner of raffle in `Drawing` status.
+    /// Filled raffle is drawn in a later block than the final entry,
+    /// entries of expired raffle are closed in an earlier block
+    pub fn draw(&mut self) -> LotteryStatus {
+        assert_eq!(self.lottery_status, LotteryStatus::Drawing, "Lottery is not waiting for draw");
+        assert!(
+            self.filled_block_height.map_or(true, |filled_at| env::block_height() > filled_at),
+            "Draw is available in a later block than the final entry"
+        );
+        self.lottery_status = LotteryStatus::Finished;
+        self.set_winner(env::random_seed_array());
+        self.lottery_status
+    }
+
+    pub fn add_refferal_transfered(&mut self, account_id: &AccountId, amount: Balance) {
+        self.refferal_transfered += amount;
+        *self.referrals.entry(account_id.clone()).or_default() += amount;
+    }
+
+    /// Removes entry of active raffle.
+    /// Returns refund: entry fee minus referral reward already paid for that entry
+    pub fn leave(&mut self, account_id: &AccountId) -> Balance {
+        assert_eq!(self.lottery_status, LotteryStatus::Active, "Lottery is not active");
+        self.entries.remove(account_id).expect("Not entered");
+        self.current_pool -= self.entry_fee;
+
+        let referral_reward = self.referrals.remove(account_id).unwrap_or_default();
+        self.refferal_transfered -= referral_reward;
+        self.entry_fee - referral_reward
+    }
+
+    pub fn cancel(&mut self) {
+        assert_eq!(self.lottery_status, LotteryStatus::Active, "Lottery is not active");
+        self.lottery_status = LotteryStatus::Cancelled;
+    }
+
+    /// Draw raffle entry
+    pub fn draw_enter(&mut self, account_id: &AccountId, amount: Balance) -> LotteryStatus {
+        assert_eq!(self.update(), LotteryStatus::Active, "Lottery entries are closed");
+        assert_eq!(
+            amount, self.entry_fee,
+            "Supplied: {}, but Required amount to paticipate is: {}",
+            self.entry_fee, amount
+        );
+        assert!(!self.entries.contains(account_id), "Already entered");
+        self.entries.push(account_id, 1);
+        self.current_pool += amount;
+        self.update()
+    }
+
+    fn set_winner(&mut self, random_seed: [u8; 32]) {
+        let entries = self.entries.ticket_holders();
+        assert!(!entries.is_empty(), "Lottery has no entries");
+        let winner_indices = pick_winners(
+            random_seed,
+            self.id,
+            RandomPurpose::RaffleWinner,
+            entries.len() as _,
+            &[],
+            1
+        );
+
+        self.winner = Some(entries[winner_indices[0] as usize].clone());
+        self.draw_record = Some(DrawRecord::new(
+            self.id,
+            LotteryType::Raffle,
+            random_seed,
+            self.entries.hash(),
+            self.entries.len(),
+            entries.len() as _,
+            vec![],
+            winner_indices
+        ));
+    }
+}
+
+impl Contract {
+    /// Creates raffle for NFT deposited by sponsor
+    pub (crate) fn internal_create_raffle(
+        &mut self,
+        sponsor: AccountId,
+        prize: RafflePrize,
+        lottery_token_id: AccountId,
+        num_participants: u32,
+        entry_fee: Balance
+    ) -> LotteryId {
+        let lottery_config = self.internal_lottery_config();
+        let raffle_config = lottery_config.raffle.clone().expect("Raffles are disabled");
+        assert!(
+            raffle_config.nft_contracts.contains(&prize.nft_contract_id),
+            "NFT contract is not accepted for raffles"
+        );
+        assert!(
+            lottery_token_id == near() || self.whitelisted_tokens.contains(&lottery_token_id),
+            "Token is not whitelisted"
+        );
+        self.assert_entries_not_paused(&lottery_token_id, LotteryType::Raffle);
+        self.assert_required_entry_fees(&lottery_token_id, entry_fee, LotteryType::Raffle);
+        self.assert_required_num_participants(num_participants, LotteryType::Raffle);
+
+        let lottery_id = self.next_lottery_id;
+        let raffle = Raffle::new(
+            lottery_id,
+            lottery_token_id,
+            num_participants,
+            entry_fee,
+            sponsor,
+            prize,
+            env::block_timestamp() + raffle_config.duration.0,
+            raffle_config.sponsor_ratio,
+            self.internal_lottery_fees()
+        );
+        self.next_lottery_id += 1;
+        self.internal_set_lottery(&lottery_id, Lottery::Raffle(raffle));
+        lottery_id
+    }
+
+    /// Enters raffle with given id
+    pub (crate) fn raffle_enter(
+        &mut self,
+        entry_account_id: &AccountId,
+        lottery_token_id: AccountId,
+        lottery_id: LotteryId,
+        amount: Balance,
+        referrer_id: Option<AccountId>
+    ) {
+        let mut raffle = match self.internal_get_lottery(lottery_id).expect("Lottery was not found") {
+            Lottery::Raffle(raffle) => raffle,
+            _ => panic!("Lottery is not raffle"),
+        };
+        assert_eq!(
+            raffle.lottery_token_id, lottery_token_id,
+            "Raffle entry fee is paid in {}", raffle.lottery_token_id
+        );
+        let lottery_status = raffle.draw_enter(entry_account_id, amount);
+
+        if let Some(refferer) = referrer_id {
+            let referrer_reward = ratio(amount, raffle.fees.referral_ratio);
+            self.internal_payout(&refferer, &lottery_token_id, referrer_reward);
+            raffle.add_refferal_transfered(entry_account_id, referrer_reward);
+        }
+
+        if lottery_status == LotteryStatus::Drawing {
+            log!("Lottery {} is filled and waiting for draw", lottery_id);
+        }
+        self.internal_set_lottery(&lottery_id, Lottery::Raffle(raffle));
+    }
+
+    /// Returns NFT of closed or cancelled raffle to its sponsor
+    pub (crate) fn internal_return_raffle_prize(&mut self, lottery: &Lottery) {
+        if let Lottery::Raffle(raffle) = lottery {
+            self.internal_nft_payout(&raffle.sponsor, &raffle.prize);
+            log!("Raffle {} prize returned to @{}", raffle.id, raffle.sponsor);
+        }
+    }
+}
+
+#[near_bindgen]
+impl Contract {
+    /// Enters raffle for NFT prize.
+    /// - Called from potential player account
+    /// - Required attached deposit equals to entry fee of raffle, raffle must be in NEAR
+    #[payable]
+    pub fn draw_near_raffle_enter(&mut self, lottery_id: LotteryId, referrer_id: Option<AccountId>) {
+        let account_id = env::predecessor_account_id();
+        let attached_deposit = env::attached_deposit();
+
+        self.check_accepted_subs(&account_id);
+        self.assert_entries_not_paused(&near(), LotteryType::Raffle);
+
+        self.raffle_enter(&account_id, near(), lottery_id, attached_deposit, referrer_id);
+    }
+}
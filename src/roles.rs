@@ -0,0 +1,72 @@
+use near_sdk::require;
+
+use crate::*;
+
+/// Admin roles granted by the contract owner.
+/// Owner implicitly has all roles
+#[derive(BorshSerialize, BorshDeserialize, Serialize, Deserialize, PartialEq, Copy, Clone, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub enum Role {
+    /// manages lotteries config, accepted subs and cancels lotteries
+    ConfigManager,
+    /// manages whitelisted tokens
+    TokenManager,
+    /// withdraws contract fees
+    TreasuryManager,
+    /// pauses and unpauses lottery entries
+    Pauser
+}
+
+impl Contract {
+    pub (crate) fn has_role(&self, account_id: &AccountId, role: Role) -> bool {
+        account_id == &self.internal_config().owner_id
+            || self.roles.get(account_id).is_some_and(|roles| roles.contains(&role))
+    }
+
+    pub (crate) fn assert_role(&self, role: Role) {
+        require!(
+            self.has_role(&env::predecessor_account_id(), role),
+            format!("Requires {:?} role", role)
+        );
+    }
+}
+
+#[near_bindgen]
+impl Contract {
+    /// Grants role to account.
+    /// - Requires one yoctoNEAR.
+    /// - Requires to be called by the contract owner.
+    #[payable]
+    pub fn grant_role(&mut self, account_id: AccountId, role: Role) {
+        assert_one_yocto();
+        self.assert_owner();
+
+        let mut roles = self.roles.get(&account_id).unwrap_or_default();
+        require!(!roles.contains(&role), "Role already granted");
+        roles.push(role);
+        self.roles.insert(&account_id, &roles);
+    }
+
+    /// Revokes role from account.
+    /// - Requires one yoctoNEAR.
+    /// - Requires to be called by the contract owner.
+    #[payable]
+    pub fn revoke_role(&mut self, account_id: AccountId, role: Role) {
+        assert_one_yocto();
+        self.assert_owner();
+
+        let mut roles = self.roles.get(&account_id).expect("Role not granted");
+        require!(roles.contains(&role), "Role not granted");
+        roles.retain(|granted| granted != &role);
+        if roles.is_empty() {
+            self.roles.remove(&account_id);
+        } else {
+            self.roles.insert(&account_id, &roles);
+        }
+    }
+
+    /// Returns accounts with granted roles as a list of ( account_id, roles )
+    pub fn get_role_holders(&self) -> Vec<(AccountId, Vec<Role>)> {
+        self.roles.to_vec()
+    }
+}
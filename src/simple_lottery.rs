@@ -1,4 +1,7 @@
+use std::collections::HashMap;
+
 use crate::*;
+use crate::views::RankedWinner;
 
 #[derive(BorshSerialize, BorshDeserialize, Serialize)]
 #[cfg_attr(not(target_arch = "wasm32"), derive(Clone))]
@@ -7,24 +10,48 @@ pub struct SimpleLottery {
     pub id: LotteryId,
     pub lottery_token_id: AccountId,
     pub lottery_status: LotteryStatus,
-    /// A list of account_ids in this lottery
-    pub entries: Vec<AccountId>,
+    /// Entries with tickets num in entry order
+    pub entries: Vec<Entry>,
     /// Amount to participate a lottery
     pub entry_fee: Balance,
     /// Current amount deposited
     pub current_pool: Balance,
     /// Required total amount for lottery to start
     pub required_pool: Balance,
-    pub winner: Option<AccountId>,
-    pub refferal_transfered: Balance
+    /// Tickets one account can hold
+    pub max_tickets: u32,
+    /// Winners in rank order
+    pub winners: Vec<AccountId>,
+    /// Share of prize for every winner rank in Basis Points
+    pub prize_split: Vec<u32>,
+    pub refferal_transfered: Balance,
+    /// Referral rewards paid on entry: entrant -> reward
+    pub referrals: HashMap<AccountId, Balance>,
+    /// Lottery is refunded if it was not filled before expiration
+    pub expires_at: Option<LotteryExpiration>,
+    /// Fee parameters in effect when lottery was created
+    pub fees: LotteryFees,
+    pub draw_mode: DrawMode,
+    /// Block of the final entry for `Delayed` draw mode
+    pub filled_block_height: Option<BlockHeight>,
+    /// Entrants secrets for `CommitReveal` draw mode
+    pub entropy: EntrantsEntropy,
+    /// Inputs and result of the draw
+    pub draw_record: Option<DrawRecord>
 }
 
 impl SimpleLottery {
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         id: LotteryId,
         lottery_token_id: AccountId,
         num_participants: u32,
-        entry_fee: Balance 
+        entry_fee: Balance,
+        expires_at: Option<LotteryExpiration>,
+        fees: LotteryFees,
+        draw_mode: DrawMode,
+        prize_split: Vec<u32>,
+        max_tickets: u32
     ) -> Self {
         let required_pool:Balance = match entry_fee.checked_mul(num_participants as u128) {
             Some(amount) => amount,
@@ -38,8 +65,17 @@ impl SimpleLottery {
             entry_fee,
             current_pool: 0,
             required_pool,
-            winner: None,
-            refferal_transfered: 0
+            max_tickets,
+            winners: vec![],
+            prize_split,
+            refferal_transfered: 0,
+            referrals: HashMap::new(),
+            expires_at,
+            fees,
+            draw_mode,
+            filled_block_height: None,
+            entropy: EntrantsEntropy::default(),
+            draw_record: None
         };
         lottery.assert_valid();
         lottery
@@ -48,14 +84,18 @@ impl SimpleLottery {
     fn assert_valid(&self) {
         assert!(self.entry_fee > 0, "entry_fee cannot be zero");
         assert!(self.required_pool > 0, "num_participants cannot be zero");
+        assert!(
+            self.prize_split.len() as u128 <= self.required_pool / self.entry_fee,
+            "Prize split has more winners than participants"
+        );
     }
     
-    fn get_accounts_num(&self) -> u32 {
-        self.entries.len() as _
-    }
-
-    fn contains_entry(&self, account_id: &AccountId) -> bool {
-        self.entries.contains(account_id)
+    fn tickets_of(&self, account_id: &AccountId) -> u32 {
+        self.entries
+            .iter()
+            .find(|entry| &entry.account_id == account_id)
+            .map(|entry| entry.tickets)
+            .unwrap_or_default()
     }
 
     fn assert_equals_pool(&self) {
@@ -71,36 +111,126 @@ impl SimpleLottery {
     }
 
     fn is_finished(&self) -> bool {
-        self.lottery_status == LotteryStatus::Finished && self.is_pools_equal() && self.winner.is_some()
+        self.lottery_status == LotteryStatus::Finished && self.is_pools_equal() && !self.winners.is_empty()
     }
 
     pub fn assert_is_finished(&self) {
         self.assert_equals_pool();
-        assert!(self.winner.is_some());
+        assert!(!self.winners.is_empty());
     }
 
     pub fn update(&mut self) -> LotteryStatus {
-        if self.is_pools_equal() {
-            self.lottery_status = LotteryStatus::Finished;
-            self.set_winner();
+        if self.lottery_status == LotteryStatus::Active && self.is_pools_equal() {
+            if self.draw_mode.is_delayed() {
+                self.lottery_status = LotteryStatus::Drawing;
+                self.filled_block_height = Some(env::block_height());
+            } else {
+                self.lottery_status = LotteryStatus::Finished;
+                self.set_winner(env::random_seed_array());
+            }
         }
         self.lottery_status
     }
 
-    pub fn add_refferal_transfered(&mut self, amount: Balance) {
-        self.refferal_transfered += amount
+    /// Picks winners of filled lottery in a later block than the final entry
+    pub fn draw(&mut self) -> LotteryStatus {
+        assert_eq!(self.lottery_status, LotteryStatus::Drawing, "Lottery is not waiting for draw");
+        assert!(
+            self.filled_block_height.is_some_and(|filled_at| env::block_height() > filled_at),
+            "Draw is available in a later block than the final entry"
+        );
+        let random_seed = if let DrawMode::CommitReveal { .. } = self.draw_mode {
+            assert!(
+                self.entropy.is_all_revealed() || !self.is_reveal_window_open(),
+                "Reveal window is not over"
+            );
+            self.entropy.seed()
+        } else {
+            env::random_seed_array()
+        };
+        self.lottery_status = LotteryStatus::Finished;
+        self.set_winner(random_seed);
+        self.lottery_status
+    }
+
+    fn is_reveal_window_open(&self) -> bool {
+        match (self.draw_mode, self.filled_block_height) {
+            (DrawMode::CommitReveal { reveal_window }, Some(filled_at)) => {
+                env::block_height() <= filled_at + reveal_window.0
+            },
+            _ => false,
+        }
     }
 
-    /// Draw lottery entry
-    pub fn draw_enter(&mut self, account_id: &AccountId, amount: Balance) -> LotteryStatus {
+    /// Reveals entrant secret of lottery waiting for draw
+    pub fn reveal_secret(&mut self, account_id: &AccountId, secret: &str) {
+        assert_eq!(self.lottery_status, LotteryStatus::Drawing, "Lottery is not waiting for draw");
+        assert!(self.is_reveal_window_open(), "Reveal window is over");
+        self.entropy.reveal(account_id, secret);
+    }
+
+    pub fn add_refferal_transfered(&mut self, account_id: &AccountId, amount: Balance) {
+        self.refferal_transfered += amount;
+        *self.referrals.entry(account_id.clone()).or_default() += amount;
+    }
+
+    /// Removes entry of active lottery with all its tickets.
+    /// Returns refund: entry fees of tickets minus referral reward already paid for them
+    pub fn leave(&mut self, account_id: &AccountId) -> Balance {
+        assert_eq!(self.lottery_status, LotteryStatus::Active, "Lottery is not active");
+        let index = self.entries
+            .iter()
+            .position(|entry| &entry.account_id == account_id)
+            .expect("Not entered");
+        let entry_fees = self.entry_fee * self.entries.remove(index).tickets as u128;
+        self.current_pool -= entry_fees;
+
+        self.entropy.remove(account_id);
+        let referral_reward = self.referrals.remove(account_id).unwrap_or_default();
+        self.refferal_transfered -= referral_reward;
+        entry_fees - referral_reward
+    }
+
+    pub fn cancel(&mut self) {
+        assert_eq!(self.lottery_status, LotteryStatus::Active, "Lottery is not active");
+        self.lottery_status = LotteryStatus::Cancelled;
+    }
+
+    /// Lottery was not filled before expiration
+    pub fn is_expired(&self) -> bool {
+        self.lottery_status == LotteryStatus::Active
+            && !self.is_pools_equal() 
+            && self.expires_at.map(|expires_at| expires_at.is_reached()).unwrap_or(false)
+    }
+
+    /// Draw lottery entry of `tickets` tickets
+    pub fn draw_enter(
+        &mut self,
+        account_id: &AccountId,
+        amount: Balance,
+        tickets: u32,
+        secret_hash: Option<CryptoHash>
+    ) -> LotteryStatus {
         if !self.is_finished() {
             assert_eq!(
-                amount, self.entry_fee,
+                amount, self.entry_fee * tickets as u128,
                 "Supplied: {}, but Required amount to paticipate is: {}",
-                self.entry_fee, amount
+                self.entry_fee * tickets as u128, amount
+            );
+            assert_tickets_allowed(
+                self.tickets_of(account_id),
+                tickets,
+                self.max_tickets,
+                ((self.required_pool - self.current_pool) / self.entry_fee) as _
             );
-            assert!(!self.contains_entry(account_id), "Already entered");
-            self.entries.push(account_id.clone());
+            if let DrawMode::CommitReveal { .. } = self.draw_mode {
+                let secret_hash = secret_hash.expect("Secret hash is required in commit-reveal draw mode");
+                self.entropy.commit(account_id, secret_hash);
+            }
+            match self.entries.iter_mut().find(|entry| &entry.account_id == account_id) {
+                Some(entry) => entry.tickets += tickets,
+                None => self.entries.push(Entry { account_id: account_id.clone(), tickets }),
+            }
             self.current_pool += amount;
         }
 
@@ -108,19 +238,66 @@ impl SimpleLottery {
         self.update()
     }
 
-    fn set_winner(&mut self) {
-        let total_entries = self.get_accounts_num();
-        let index = get_range_random_number(0, total_entries);
-        let winner = &self.entries[index];
-       
-        self.winner = Some(winner.clone());
+    /// Picks winner of every rank weighted by tickets, an account wins one rank at most
+    fn set_winner(&mut self, random_seed: [u8; 32]) {
+        let tickets_ends = tickets_ends(&self.entries);
+        let (winner_indices, excluded_indices) = pick_ticket_winners(
+            random_seed,
+            self.id,
+            RandomPurpose::SimpleLotteryWinner,
+            self.entries.len() as _,
+            tickets_ends.last().copied().unwrap_or_default(),
+            self.prize_split.len() as _,
+            |ticket| {
+                let index = entry_of_ticket(&tickets_ends, ticket);
+                (index, self.entropy.is_excluded(&self.entries[index as usize].account_id))
+            }
+        );
+
+        self.winners = winner_indices
+            .iter()
+            .map(|index| self.entries[*index as usize].account_id.clone())
+            .collect();
+        self.draw_record = Some(DrawRecord::new(
+            self.id,
+            LotteryType::SimpleLottery,
+            random_seed,
+            hash_entries(&self.entries),
+            self.entries.len() as _,
+            tickets_ends.last().copied().unwrap_or_default(),
+            excluded_indices,
+            winner_indices
+        ));
+    }
+
+    /// Prizes of every winner rank.
+    /// Rounding remainder goes to the first rank
+    pub fn prizes(&self, reward: Balance) -> Vec<Balance> {
+        let mut prizes = self.prize_split
+            .iter()
+            .map(|share| ratio(reward, *share))
+            .collect::<Vec<_>>();
+        prizes[0] += reward - prizes.iter().sum::<Balance>();
+        prizes
     }
 
-    pub fn get_winner_unwrap(&self) -> AccountId {
-        match &self.winner {
-            Some(winner) => winner.clone(),
-            None => panic!("Lottery has no winner"),
+    /// Picked winners with their prizes in rank order.
+    /// If there are less accounts than ranks, prizes of ranks without winner go to the first rank
+    pub fn ranked_winners(&self, reward: Balance) -> Vec<RankedWinner> {
+        let mut prizes = self.prizes(reward);
+        let unawarded = prizes.split_off(self.winners.len()).iter().sum::<Balance>();
+        if let Some(first_prize) = prizes.first_mut() {
+            *first_prize += unawarded;
         }
+        self.winners
+            .iter()
+            .zip(prizes)
+            .enumerate()
+            .map(|(index, (account_id, amount))| RankedWinner {
+                rank: index as u32 + 1,
+                account_id: account_id.clone(),
+                amount: U128(amount)
+            })
+            .collect()
     }
 }
-  
\ No newline at end of file
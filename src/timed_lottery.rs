@@ -0,0 +1,150 @@
+use std::collections::HashMap;
+
+use near_sdk::Timestamp;
+
+use crate::*;
+
+/// Lottery with open-ended number of entrants drawn at a fixed time.
+/// Prize is the whole accumulated pool minus fees
+#[derive(BorshSerialize, BorshDeserialize, Serialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct TimedLottery {
+    pub id: LotteryId,
+    pub lottery_token_id: AccountId,
+    pub lottery_status: LotteryStatus,
+    /// A list of account_ids in this lottery
+    pub entries: LotteryEntries,
+    /// Amount to participate a lottery
+    pub entry_fee: Balance,
+    /// Current amount deposited
+    pub current_pool: Balance,
+    /// Block timestamp after which entries are closed and lottery can be drawn
+    pub draw_at: Timestamp,
+    pub winner: Option<AccountId>,
+    pub refferal_transfered: Balance,
+    /// Referral rewards paid on entry: entrant -> reward
+    pub referrals: HashMap<AccountId, Balance>,
+    /// Fee parameters in effect when lottery was created
+    pub fees: LotteryFees,
+    /// Inputs and result of the draw
+    pub draw_record: Option<DrawRecord>
+}
+
+impl TimedLottery {
+    pub fn new(
+        id: LotteryId,
+        lottery_token_id: AccountId,
+        entry_fee: Balance,
+        draw_at: Timestamp,
+        fees: LotteryFees
+    ) -> Self {
+        assert!(entry_fee > 0, "entry_fee cannot be zero");
+        assert!(draw_at > env::block_timestamp(), "Draw time must be in the future");
+        Self {
+            id,
+            lottery_token_id,
+            lottery_status: LotteryStatus::Active,
+            entries: LotteryEntries::new(id),
+            entry_fee,
+            current_pool: 0,
+            draw_at,
+            winner: None,
+            refferal_transfered: 0,
+            referrals: HashMap::new(),
+            fees,
+            draw_record: None
+        }
+    }
+
+    pub fn is_draw_time_reached(&self) -> bool {
+        env::block_timestamp() >= self.draw_at
+    }
+
+    pub fn assert_is_finished(&self) {
+        assert_eq!(self.lottery_status, LotteryStatus::Finished, "Lottery is not finished");
+        assert!(self.winner.is_some());
+    }
+
+    /// Entries are closed at draw time, lottery waits for `draw`
+    pub fn update(&mut self) -> LotteryStatus {
+        if self.lottery_status == LotteryStatus::Active && self.is_draw_time_reached() {
+            self.lottery_status = LotteryStatus::Drawing;
+        }
+        self.lottery_status
+    }
+
+    /// Picks winner after draw time.
+    /// Entries are closed in an earlier block, so the seed is not known to any entrant
+    pub fn draw(&mut self) -> LotteryStatus {
+        assert!(self.is_draw_time_reached(), "Draw is available after draw time");
+        assert_eq!(self.lottery_status, LotteryStatus::Drawing, "Lottery is not waiting for draw");
+        self.lottery_status = LotteryStatus::Finished;
+        self.set_winner(env::random_seed_array());
+        self.lottery_status
+    }
+
+    pub fn add_refferal_transfered(&mut self, account_id: &AccountId, amount: Balance) {
+        self.refferal_transfered += amount;
+        *self.referrals.entry(account_id.clone()).or_default() += amount;
+    }
+
+    /// Removes entry of active lottery.
+    /// Returns refund: entry fee minus referral reward already paid for that entry
+    pub fn leave(&mut self, account_id: &AccountId) -> Balance {
+        assert_eq!(self.lottery_status, LotteryStatus::Active, "Lottery is not active");
+        self.entries.remove(account_id).expect("Not entered");
+        self.current_pool -= self.entry_fee;
+
+        let referral_reward = self.referrals.remove(account_id).unwrap_or_default();
+        self.refferal_transfered -= referral_reward;
+        self.entry_fee - referral_reward
+    }
+
+    /// Lottery waiting for draw can be cancelled as well, so its entries are not locked
+    pub fn cancel(&mut self) {
+        assert!(
+            matches!(self.lottery_status, LotteryStatus::Active | LotteryStatus::Drawing),
+            "Lottery is not active"
+        );
+        self.lottery_status = LotteryStatus::Cancelled;
+    }
+
+    /// Draw lottery entry
+    pub fn draw_enter(&mut self, account_id: &AccountId, amount: Balance) -> LotteryStatus {
+        assert_eq!(self.update(), LotteryStatus::Active, "Lottery entries are closed");
+        assert_eq!(
+            amount, self.entry_fee,
+            "Supplied: {}, but Required amount to paticipate is: {}",
+            self.entry_fee, amount
+        );
+        self.entries.push(account_id, 1);
+        self.current_pool += amount;
+        self.lottery_status
+    }
+
+    fn set_winner(&mut self, random_seed: [u8; 32]) {
+        let tickets_num = self.entries.tickets_num();
+        assert!(tickets_num > 0, "Lottery has no entries");
+        let winner_indices = pick_winners(
+            random_seed,
+            self.id,
+            RandomPurpose::TimedLotteryWinner,
+            tickets_num,
+            &[],
+            1
+        );
+
+        // every entry is one ticket, so only the winning entry is read
+        self.winner = Some(self.entries.get(winner_indices[0]).account_id);
+        self.draw_record = Some(DrawRecord::new(
+            self.id,
+            LotteryType::TimedLottery,
+            random_seed,
+            self.entries.hash(),
+            self.entries.len(),
+            tickets_num,
+            vec![],
+            winner_indices
+        ));
+    }
+}
@@ -0,0 +1,595 @@
+use std::collections::HashMap;
+
+use near_sdk::json_types::U64;
+use near_sdk::Gas;
+
+use crate::*;
+
+/// Gas kept for `upgrade` call itself, the rest is attached to `migrate`
+const GAS_RESERVED_FOR_UPGRADE: Gas = Gas(Gas::ONE_TERA.0 * 20);
+
+const STATE_KEY: &[u8] = b"STATE";
+/// State written before versioning has no version
+pub (crate) const STATE_VERSION_KEY: &[u8] = b"STATE_VERSION";
+
+/// Layout version of current contract state.
+/// Version 1 is the first versioned release
+pub const STATE_VERSION: u32 = 2;
+const STATE_VERSION_V1: u32 = 1;
+
+pub (crate) fn write_state_version() {
+    env::storage_write(STATE_VERSION_KEY, &STATE_VERSION.try_to_vec().unwrap());
+}
+
+fn read_state_version() -> Option<u32> {
+    env::storage_read(STATE_VERSION_KEY)
+        .map(|version| u32::try_from_slice(&version).expect("Unknown state version"))
+}
+
+/// Contract config before versioning
+#[derive(BorshSerialize, BorshDeserialize)]
+pub struct ConfigV0 {
+    pub owner_id: AccountId,
+    pub contract_fee_ratio: u32,
+    pub treasury_ratio: u32,
+    pub investor_ratio: u32,
+    pub treasury: AccountId,
+    pub investor: AccountId,
+    pub accepted_subs: String
+}
+
+impl From<ConfigV0> for Config {
+    fn from(config: ConfigV0) -> Self {
+        Self {
+            owner_id: config.owner_id,
+            contract_fee_ratio: config.contract_fee_ratio,
+            treasury_ratio: config.treasury_ratio,
+            investor_ratio: config.investor_ratio,
+            referral_ratio: ONE_PERCENT_RATIO,
+            treasury: config.treasury,
+            investor: config.investor,
+            accepted_subs: config.accepted_subs,
+            pending_owner_id: None,
+            fees_timelock: U64(0)
+        }
+    }
+}
+
+/// Contract config of the first versioned release, referral ratio was fixed to 1%
+#[derive(BorshSerialize, BorshDeserialize)]
+pub struct ConfigV1 {
+    pub owner_id: AccountId,
+    pub contract_fee_ratio: u32,
+    pub treasury_ratio: u32,
+    pub investor_ratio: u32,
+    pub treasury: AccountId,
+    pub investor: AccountId,
+    pub accepted_subs: String,
+    pub pending_owner_id: Option<AccountId>,
+    pub fees_timelock: U64
+}
+
+impl From<ConfigV1> for Config {
+    fn from(config: ConfigV1) -> Self {
+        Self {
+            owner_id: config.owner_id,
+            contract_fee_ratio: config.contract_fee_ratio,
+            treasury_ratio: config.treasury_ratio,
+            investor_ratio: config.investor_ratio,
+            referral_ratio: ONE_PERCENT_RATIO,
+            treasury: config.treasury,
+            investor: config.investor,
+            accepted_subs: config.accepted_subs,
+            pending_owner_id: config.pending_owner_id,
+            fees_timelock: config.fees_timelock
+        }
+    }
+}
+
+/// Fees config change of the first versioned release
+#[derive(BorshSerialize, BorshDeserialize)]
+pub struct FeesConfigUpdateV1 {
+    pub contract_fee_ratio: Option<u32>,
+    pub treasury_ratio: Option<u32>,
+    pub investor_ratio: Option<u32>,
+    pub treasury: Option<AccountId>,
+    pub investor: Option<AccountId>,
+    pub fees_timelock: Option<U64>
+}
+
+#[derive(BorshSerialize, BorshDeserialize)]
+pub struct PendingFeesConfigV1 {
+    pub update: FeesConfigUpdateV1,
+    pub applicable_at: U64
+}
+
+impl From<PendingFeesConfigV1> for PendingFeesConfig {
+    fn from(pending: PendingFeesConfigV1) -> Self {
+        let update = pending.update;
+        Self {
+            update: FeesConfigUpdate {
+                contract_fee_ratio: update.contract_fee_ratio,
+                treasury_ratio: update.treasury_ratio,
+                investor_ratio: update.investor_ratio,
+                referral_ratio: None,
+                treasury: update.treasury,
+                investor: update.investor,
+                fees_timelock: update.fees_timelock
+            },
+            applicable_at: pending.applicable_at
+        }
+    }
+}
+
+/// Lotteries config before claim mode and expiration
+#[derive(BorshSerialize, BorshDeserialize)]
+pub struct LotteryConfigV0 {
+    pub entry_fees: HashMap<AccountId, Vec<U128>>,
+    pub num_participants: Vec<u32>,
+    pub big_lottery_num_participants: Vec<u32>
+}
+
+impl From<LotteryConfigV0> for LotteryConfig {
+    fn from(config: LotteryConfigV0) -> Self {
+        LotteryConfig::new(
+            config.entry_fees.into_iter().collect(),
+            config.num_participants,
+            config.big_lottery_num_participants
+        )
+    }
+}
+
+#[derive(BorshSerialize, BorshDeserialize)]
+pub struct SimpleLotteryV0 {
+    pub id: LotteryId,
+    pub lottery_token_id: AccountId,
+    pub lottery_status: LotteryStatus,
+    pub entries: Vec<AccountId>,
+    pub entry_fee: Balance,
+    pub current_pool: Balance,
+    pub required_pool: Balance,
+    pub winner: Option<AccountId>,
+    pub refferal_transfered: Balance
+}
+
+/// Big lottery winners tiers before prize tables
+#[derive(BorshSerialize, BorshDeserialize, PartialEq, PartialOrd, Eq, Hash)]
+pub enum WinnerTypeV0 {
+    UpToFiftyPercent,
+    UpToTenPercent,
+    Cashback
+}
+
+#[derive(BorshSerialize, BorshDeserialize)]
+pub struct BigLotteryV0 {
+    pub id: LotteryId,
+    pub lottery_token_id: AccountId,
+    pub lottery_status: LotteryStatus,
+    pub entries: Vec<AccountId>,
+    pub entry_fee: Balance,
+    pub current_pool: Balance,
+    pub required_pool: Balance,
+    pub winners: HashMap<WinnerTypeV0, Vec<AccountId>>,
+    pub cashbacked_num: u32,
+    pub ten_percent_winners_num: u32,
+    pub fifty_percent_winners_num: u32,
+    pub refferal_transfered: Balance
+}
+
+/// Lottery before versioning
+#[derive(BorshSerialize, BorshDeserialize)]
+pub enum LotteryV0 {
+    SimpleLottery(SimpleLotteryV0),
+    Lottery(BigLotteryV0)
+}
+
+impl LotteryV0 {
+    /// Converts to layout of the first versioned release.
+    /// Old lotteries have no expiration and use fees in effect during migration
+    fn migrate(self, fees: &LotteryFees) -> LotteryV1 {
+        match self {
+            LotteryV0::SimpleLottery(lottery) => LotteryV1::SimpleLottery(SimpleLotteryV1 {
+                id: lottery.id,
+                lottery_token_id: lottery.lottery_token_id,
+                lottery_status: lottery.lottery_status,
+                entries: lottery.entries,
+                entry_fee: lottery.entry_fee,
+                current_pool: lottery.current_pool,
+                required_pool: lottery.required_pool,
+                winner: lottery.winner,
+                refferal_transfered: lottery.refferal_transfered,
+                referrals: HashMap::new(),
+                expires_at: None,
+                fees: fees.clone()
+            }),
+            LotteryV0::Lottery(lottery) => LotteryV1::Lottery(BigLotteryV1 {
+                id: lottery.id,
+                lottery_token_id: lottery.lottery_token_id,
+                lottery_status: lottery.lottery_status,
+                entries: lottery.entries,
+                entry_fee: lottery.entry_fee,
+                current_pool: lottery.current_pool,
+                required_pool: lottery.required_pool,
+                winners: lottery.winners,
+                cashbacked_num: lottery.cashbacked_num,
+                ten_percent_winners_num: lottery.ten_percent_winners_num,
+                fifty_percent_winners_num: lottery.fifty_percent_winners_num,
+                refferal_transfered: lottery.refferal_transfered,
+                referrals: HashMap::new(),
+                expires_at: None,
+                fees: fees.clone()
+            }),
+        }
+    }
+}
+
+/// Lotteries config of the first versioned release, before draw modes, prize tables and new lottery types
+#[derive(BorshSerialize, BorshDeserialize)]
+pub struct LotteryConfigV1 {
+    pub entry_fees: HashMap<AccountId, Vec<U128>>,
+    pub num_participants: Vec<u32>,
+    pub big_lottery_num_participants: Vec<u32>,
+    pub claim_mode_tokens: Vec<AccountId>,
+    pub claim_mode_lottery_types: Vec<LotteryType>,
+    pub expiration: Option<LotteryExpiration>,
+    pub big_lottery_expiration: Option<LotteryExpiration>
+}
+
+impl From<LotteryConfigV1> for LotteryConfig {
+    fn from(config: LotteryConfigV1) -> Self {
+        Self {
+            claim_mode_tokens: config.claim_mode_tokens,
+            claim_mode_lottery_types: config.claim_mode_lottery_types,
+            expiration: config.expiration,
+            big_lottery_expiration: config.big_lottery_expiration,
+            ..LotteryConfig::new(
+                config.entry_fees.into_iter().collect(),
+                config.num_participants,
+                config.big_lottery_num_participants
+            )
+        }
+    }
+}
+
+#[derive(BorshSerialize, BorshDeserialize)]
+pub struct SimpleLotteryV1 {
+    pub id: LotteryId,
+    pub lottery_token_id: AccountId,
+    pub lottery_status: LotteryStatus,
+    pub entries: Vec<AccountId>,
+    pub entry_fee: Balance,
+    pub current_pool: Balance,
+    pub required_pool: Balance,
+    pub winner: Option<AccountId>,
+    pub refferal_transfered: Balance,
+    pub referrals: HashMap<AccountId, Balance>,
+    pub expires_at: Option<LotteryExpiration>,
+    pub fees: LotteryFees
+}
+
+#[derive(BorshSerialize, BorshDeserialize)]
+pub struct BigLotteryV1 {
+    pub id: LotteryId,
+    pub lottery_token_id: AccountId,
+    pub lottery_status: LotteryStatus,
+    pub entries: Vec<AccountId>,
+    pub entry_fee: Balance,
+    pub current_pool: Balance,
+    pub required_pool: Balance,
+    pub winners: HashMap<WinnerTypeV0, Vec<AccountId>>,
+    pub cashbacked_num: u32,
+    pub ten_percent_winners_num: u32,
+    pub fifty_percent_winners_num: u32,
+    pub refferal_transfered: Balance,
+    pub referrals: HashMap<AccountId, Balance>,
+    pub expires_at: Option<LotteryExpiration>,
+    pub fees: LotteryFees
+}
+
+/// Lottery of the first versioned release, before multiple tickets, draw modes and prize tables
+#[derive(BorshSerialize, BorshDeserialize)]
+pub enum LotteryV1 {
+    SimpleLottery(SimpleLotteryV1),
+    Lottery(BigLotteryV1)
+}
+
+impl From<LotteryV1> for Lottery {
+    /// Old lotteries are drawn instantly, use default prize table and one ticket per entry
+    fn from(lottery: LotteryV1) -> Self {
+        match lottery {
+            LotteryV1::SimpleLottery(lottery) => Lottery::SimpleLottery(SimpleLottery {
+                id: lottery.id,
+                lottery_token_id: lottery.lottery_token_id,
+                lottery_status: lottery.lottery_status,
+                entries: lottery.entries
+                    .into_iter()
+                    .map(|account_id| Entry { account_id, tickets: 1 })
+                    .collect(),
+                entry_fee: lottery.entry_fee,
+                current_pool: lottery.current_pool,
+                required_pool: lottery.required_pool,
+                max_tickets: 1,
+                winners: lottery.winner.into_iter().collect(),
+                prize_split: vec![MAX_RATIO],
+                refferal_transfered: lottery.refferal_transfered,
+                referrals: lottery.referrals,
+                expires_at: lottery.expires_at,
+                fees: lottery.fees,
+                draw_mode: DrawMode::Instant,
+                filled_block_height: None,
+                entropy: EntrantsEntropy::default(),
+                draw_record: None
+            }),
+            LotteryV1::Lottery(lottery) => Lottery::Lottery(BigLottery {
+                id: lottery.id,
+                lottery_token_id: lottery.lottery_token_id,
+                lottery_status: lottery.lottery_status,
+                entries: {
+                    let mut entries = LotteryEntries::new(lottery.id);
+                    lottery.entries.iter().for_each(|account_id| entries.push(account_id, 1));
+                    entries
+                },
+                entry_fee: lottery.entry_fee,
+                current_pool: lottery.current_pool,
+                required_pool: lottery.required_pool,
+                max_tickets: 1,
+                prize_table_id: DEFAULT_PRIZE_TABLE.to_string(),
+                prize_table: PrizeTable::default(),
+                tier_winners_nums: vec![
+                    lottery.fifty_percent_winners_num,
+                    lottery.ten_percent_winners_num,
+                    lottery.cashbacked_num
+                ],
+                winners: if lottery.winners.is_empty() {
+                    vec![]
+                } else {
+                    let mut winners = lottery.winners;
+                    [WinnerTypeV0::UpToFiftyPercent, WinnerTypeV0::UpToTenPercent, WinnerTypeV0::Cashback]
+                        .iter()
+                        .map(|winner_type| winners.remove(winner_type).unwrap_or_default())
+                        .collect()
+                },
+                refferal_transfered: lottery.refferal_transfered,
+                referrals: lottery.referrals,
+                expires_at: lottery.expires_at,
+                fees: lottery.fees,
+                draw_mode: DrawMode::Instant,
+                filled_block_height: None,
+                entropy: EntrantsEntropy::default(),
+                draw_record: None
+            }),
+        }
+    }
+}
+
+/// Cancellation record before multiple tickets
+#[derive(BorshSerialize, BorshDeserialize)]
+pub struct LotteryCancellationV1 {
+    pub lottery_id: U64,
+    pub reason: String,
+    pub lottery_token_id: AccountId,
+    pub refunded_entries: Vec<AccountId>,
+    pub entry_fee: U128,
+    pub timestamp: U64
+}
+
+impl From<LotteryCancellationV1> for LotteryCancellation {
+    fn from(cancellation: LotteryCancellationV1) -> Self {
+        let entries = cancellation.refunded_entries
+            .into_iter()
+            .map(|account_id| Entry { account_id, tickets: 1 })
+            .collect::<Vec<_>>();
+        Self {
+            lottery_id: cancellation.lottery_id,
+            reason: cancellation.reason,
+            lottery_token_id: cancellation.lottery_token_id,
+            entries_num: entries.len() as _,
+            tickets_num: entries.len() as _,
+            entries_hash: hash_entries(&entries).into(),
+            entry_fee: cancellation.entry_fee,
+            timestamp: cancellation.timestamp
+        }
+    }
+}
+
+/// Cashbacks buffer before cashbacks queue
+#[derive(BorshSerialize, BorshDeserialize)]
+pub struct StoredCashbackV0 {
+    pub amount: Balance,
+    pub accounts: Vec<AccountId>
+}
+
+/// Contract state before versioning
+#[derive(BorshSerialize, BorshDeserialize)]
+pub struct ContractV0 {
+    pub config: LazyOption<ConfigV0>,
+    pub lotteries: UnorderedMap<LotteryId, LotteryV0>,
+    pub whitelisted_tokens: UnorderedSet<AccountId>,
+    pub fees: UnorderedMap<AccountId, Balance>,
+    pub next_lottery_id: LotteryId,
+    pub cashback_accounts: UnorderedMap<AccountId, StoredCashbackV0>,
+    pub lotteries_config: LazyOption<LotteryConfigV0>
+}
+
+impl ContractV0 {
+    /// Converts state to current layout.
+    /// - Active lotteries are rewritten as `VersionedLottery`
+    /// - Legacy cashbacks buffer is credited to claimable balances, so not transfered cashbacks can be claimed
+    fn migrate(mut self) -> Contract {
+        let config: Config = self.config.get().expect("Config not found").into();
+        let lottery_config: LotteryConfig = self.lotteries_config.get().expect("Lotteries config not found").into();
+
+        let legacy_cashbacks = self.cashback_accounts.to_vec();
+        self.cashback_accounts.clear();
+
+        let legacy_lotteries = self.lotteries.to_vec();
+        self.lotteries.clear();
+
+        let mut contract = Contract {
+            config: LazyOption::new(StorageKey::Config, Some(&config.into())),
+            lotteries: UnorderedMap::new(StorageKey::Lotteries),
+            whitelisted_tokens: self.whitelisted_tokens,
+            fees: self.fees,
+            next_lottery_id: self.next_lottery_id,
+            cashbacks: UnorderedMap::new(StorageKey::Cashbacks),
+            lotteries_config: LazyOption::new(StorageKey::LotteryConfig, Some(&lottery_config.into())),
+            failed_payouts: UnorderedMap::new(StorageKey::FailedPayouts),
+            claimable: UnorderedMap::new(StorageKey::Claimable),
+            fees_locked: UnorderedMap::new(StorageKey::FeesLocked),
+            fees_withdrawals: Vector::new(StorageKey::FeesWithdrawals),
+            cancellations: UnorderedMap::new(StorageKey::Cancellations),
+            pause_state: LazyOption::new(StorageKey::PauseState, Some(&PauseState::default())),
+            ownership_transfers: Vector::new(StorageKey::OwnershipTransfers),
+            roles: UnorderedMap::new(StorageKey::Roles),
+            pending_fees_config: LazyOption::new(StorageKey::PendingFeesConfig, None),
+            draws: UnorderedMap::new(StorageKey::Draws),
+            jackpots: UnorderedMap::new(StorageKey::Jackpots),
+            jackpot_rounds: LookupMap::new(StorageKey::JackpotRounds),
+            lotto_rollovers: UnorderedMap::new(StorageKey::LottoRollovers),
+            failed_nft_transfers: UnorderedMap::new(StorageKey::FailedNftTransfers),
+            settlements: UnorderedMap::new(StorageKey::Settlements),
+            open_jackpot_rounds: LookupMap::new(StorageKey::OpenJackpotRounds),
+            open_lotto_rounds: LookupMap::new(StorageKey::OpenLottoRounds)
+        };
+
+        for (token_id, stored_cashback) in legacy_cashbacks {
+            for account_id in &stored_cashback.accounts {
+                contract.internal_credit_claimable(account_id, &token_id, stored_cashback.amount);
+            }
+            log!(
+                "Legacy cashbacks credited to claimable balances: {} accounts, {} yocto{} each",
+                stored_cashback.accounts.len(), stored_cashback.amount, match_token_id(&token_id)
+            );
+        }
+
+        let fees = contract.internal_lottery_fees();
+        for (lottery_id, lottery) in legacy_lotteries {
+            contract.lotteries.insert(&lottery_id, &Lottery::from(lottery.migrate(&fees)).into());
+        }
+        log!("Migrated {} active lotteries", contract.lotteries.len());
+        contract
+    }
+}
+
+/// Contract state of the first versioned release
+#[derive(BorshSerialize, BorshDeserialize)]
+pub struct ContractV1 {
+    pub config: LazyOption<VersionedConfig>,
+    pub lotteries: UnorderedMap<LotteryId, VersionedLottery>,
+    pub whitelisted_tokens: UnorderedSet<AccountId>,
+    pub fees: UnorderedMap<AccountId, Balance>,
+    pub next_lottery_id: LotteryId,
+    pub cashbacks: UnorderedMap<LotteryId, StoredCashback>,
+    pub lotteries_config: LazyOption<LotteryConfigV1>,
+    pub failed_payouts: UnorderedMap<AccountId, HashMap<AccountId, Balance>>,
+    pub claimable: UnorderedMap<AccountId, HashMap<AccountId, Balance>>,
+    pub fees_locked: UnorderedMap<AccountId, Balance>,
+    pub fees_withdrawals: Vector<FeesWithdrawal>,
+    pub cancellations: UnorderedMap<LotteryId, LotteryCancellationV1>,
+    pub pause_state: LazyOption<PauseState>,
+    pub ownership_transfers: Vector<OwnershipTransfer>,
+    pub roles: UnorderedMap<AccountId, Vec<Role>>,
+    pub pending_fees_config: LazyOption<PendingFeesConfigV1>
+}
+
+impl ContractV1 {
+    /// Converts state to current layout.
+    /// - Lotteries config, pending fees config and cancellation records are rewritten in current layout
+    /// - Config is kept as `VersionedConfig::V1` and converted on read
+    /// - Active lotteries are rewritten as `VersionedLottery::V2`
+    fn migrate(mut self) -> Contract {
+        let lottery_config: LotteryConfig = self.lotteries_config.get().expect("Lotteries config not found").into();
+        let pending_fees_config: Option<PendingFeesConfig> = self.pending_fees_config.get().map(Into::into);
+        self.pending_fees_config.remove();
+
+        let legacy_cancellations = self.cancellations.to_vec();
+        self.cancellations.clear();
+
+        let legacy_lotteries = self.lotteries.to_vec();
+        self.lotteries.clear();
+
+        let mut contract = Contract {
+            config: self.config,
+            lotteries: UnorderedMap::new(StorageKey::Lotteries),
+            whitelisted_tokens: self.whitelisted_tokens,
+            fees: self.fees,
+            next_lottery_id: self.next_lottery_id,
+            cashbacks: self.cashbacks,
+            lotteries_config: LazyOption::new(StorageKey::LotteryConfig, Some(&lottery_config.into())),
+            failed_payouts: self.failed_payouts,
+            claimable: self.claimable,
+            fees_locked: self.fees_locked,
+            fees_withdrawals: self.fees_withdrawals,
+            cancellations: UnorderedMap::new(StorageKey::Cancellations),
+            pause_state: self.pause_state,
+            ownership_transfers: self.ownership_transfers,
+            roles: self.roles,
+            pending_fees_config: LazyOption::new(StorageKey::PendingFeesConfig, pending_fees_config.as_ref()),
+            draws: UnorderedMap::new(StorageKey::Draws),
+            jackpots: UnorderedMap::new(StorageKey::Jackpots),
+            jackpot_rounds: LookupMap::new(StorageKey::JackpotRounds),
+            lotto_rollovers: UnorderedMap::new(StorageKey::LottoRollovers),
+            failed_nft_transfers: UnorderedMap::new(StorageKey::FailedNftTransfers),
+            settlements: UnorderedMap::new(StorageKey::Settlements),
+            open_jackpot_rounds: LookupMap::new(StorageKey::OpenJackpotRounds),
+            open_lotto_rounds: LookupMap::new(StorageKey::OpenLottoRounds)
+        };
+
+        for (lottery_id, cancellation) in legacy_cancellations {
+            contract.cancellations.insert(&lottery_id, &cancellation.into());
+        }
+
+        for (lottery_id, lottery) in legacy_lotteries {
+            contract.lotteries.insert(&lottery_id, &Lottery::from(lottery).into());
+        }
+        log!("Migrated {} active lotteries", contract.lotteries.len());
+        contract
+    }
+}
+
+#[near_bindgen]
+impl Contract {
+    /// Deploys new contract code passed as raw input and calls `migrate` on it.
+    /// - Requires to be called by the contract owner.
+    /// - Input is the wasm binary, not JSON
+    /// - Panics if attached gas does not cover the call itself and `GAS_RESERVED_FOR_UPGRADE`
+    pub fn upgrade(&self) -> Promise {
+        self.assert_owner();
+        let code = env::input().expect("Contract code not found in input");
+        let migrate_gas = env::prepaid_gas().0
+            .checked_sub(env::used_gas().0 + GAS_RESERVED_FOR_UPGRADE.0)
+            .expect("Not enough gas attached for upgrade");
+
+        Promise::new(env::current_account_id())
+            .deploy_contract(code)
+            .function_call(
+                "migrate".to_string(),
+                vec![],
+                0,
+                Gas(migrate_gas)
+            )
+    }
+
+    /// Converts stored state to current layout after upgrade.
+    /// - Can be called only by the contract itself
+    /// - Layout is picked by stored state version, state without version has the layout before versioning
+    /// - State already in current layout is kept as is
+    #[private]
+    #[init(ignore_state)]
+    pub fn migrate() -> Self {
+        let state = env::storage_read(STATE_KEY).expect("Contract state not found");
+        let contract = match read_state_version() {
+            Some(STATE_VERSION) => Contract::try_from_slice(&state).expect("Unknown contract state"),
+            Some(STATE_VERSION_V1) => ContractV1::try_from_slice(&state)
+                .expect("Unknown contract state")
+                .migrate(),
+            Some(version) => panic!("Unknown state version: {}", version),
+            // state written before versioning
+            None => ContractV0::try_from_slice(&state)
+                .expect("Unknown contract state")
+                .migrate(),
+        };
+        write_state_version();
+        contract
+    }
+}
@@ -1,9 +1,15 @@
-use rand::seq::SliceRandom;
+use std::collections::HashMap;
+use std::convert::TryInto;
+
 use crate::*;
 
-uint::construct_uint!(
-    pub struct U256(4);
-);
+#[allow(clippy::all)]
+mod u256 {
+    uint::construct_uint!(
+        pub struct U256(4);
+    );
+}
+pub use u256::U256;
 
 pub const NEAR:&str = "near";
 
@@ -26,7 +32,7 @@ pub(crate) fn match_token_id(account_id: &AccountId) -> String {
         "usdc.fakes.testnet" => "USDC".to_string(),
         "wrap.testnet" => "WNEAR".to_string(),
         "weth.fakes.testnet" => "WETH".to_string(),
-        _ => unimplemented!()
+        _ => binding.clone()
     }
 }
 
@@ -43,35 +49,197 @@ pub(crate) fn ratio(balance: u128, r: u32) -> u128 {
     u128_ratio(balance, u128::from(r), u128::from(MAX_RATIO))
 }
 
-pub (crate) fn get_range_random_number(range_start: u32, range_end: u32) -> usize {
-    let random_seed = env::random_seed_array();
-    let mut rng:StdRng = SeedableRng::from_seed(random_seed);
-    rng.gen_range(range_start, range_end) as _
+/// What random numbers of lottery stream are drawn for.
+/// Streams for different purposes are independent
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum RandomPurpose {
+    SimpleLotteryWinner,
+    BigLotteryOrder,
+    TimedLotteryWinner,
+    JackpotNumber,
+    LottoNumbers,
+    RaffleWinner
+}
+
+/// Random stream of a lottery derived from a draw seed.
+/// Every block of the stream is `sha256(seed || lottery_id || purpose || counter)`,
+/// so lotteries drawn from the same seed get unrelated numbers
+/// and repeated draws continue the stream instead of restarting it
+pub struct LotteryRng {
+    seed: [u8; 32],
+    lottery_id: LotteryId,
+    purpose: RandomPurpose,
+    counter: u64,
+    block: [u8; 32],
+    /// next unused u64 word of current block
+    word: usize
+}
+
+impl LotteryRng {
+    const WORDS_PER_BLOCK: usize = 4;
+
+    pub fn new(seed: [u8; 32], lottery_id: LotteryId, purpose: RandomPurpose) -> Self {
+        Self {
+            seed,
+            lottery_id,
+            purpose,
+            counter: 0,
+            block: [0; 32],
+            word: Self::WORDS_PER_BLOCK
+        }
+    }
+
+    fn next_block(&mut self) {
+        let mut input = self.seed.to_vec();
+        input.extend_from_slice(&self.lottery_id.to_le_bytes());
+        input.push(self.purpose as u8);
+        input.extend_from_slice(&self.counter.to_le_bytes());
+        self.block = env::sha256_array(&input);
+        self.counter += 1;
+        self.word = 0;
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        if self.word == Self::WORDS_PER_BLOCK {
+            self.next_block();
+        }
+        let offset = self.word * 8;
+        self.word += 1;
+        u64::from_le_bytes(self.block[offset..offset + 8].try_into().unwrap())
+    }
+
+    /// Uniform number in `[range_start, range_end)`.
+    /// Values from the incomplete last bucket of u64 are rejected, so there is no modulo bias
+    pub fn gen_range(&mut self, range_start: u32, range_end: u32) -> u32 {
+        assert!(range_start < range_end, "Empty random range");
+        let range = u64::from(range_end - range_start);
+        // 2^64 mod range
+        let rejected = (u64::MAX % range + 1) % range;
+        loop {
+            let value = self.next_u64();
+            if rejected == 0 || value < 0u64.wrapping_sub(rejected) {
+                return range_start + (value % range) as u32;
+            }
+        }
+    }
+
+    /// First `amount` numbers of random permutation of `[0, total)`.
+    pub fn sample(&mut self, total: u32, amount: u32) -> Vec<u32> {
+        assert!(amount <= total, "Sample is larger than population");
+        self.permutation(total).take(amount as usize).collect()
+    }
+
+    /// Random permutation of `[0, total)` generated on demand
+    pub fn permutation(&mut self, total: u32) -> Permutation<'_> {
+        Permutation {
+            rng: self,
+            total,
+            index: 0,
+            swapped: HashMap::new()
+        }
+    }
+}
+
+/// Partial Fisher-Yates shuffle keeping only swapped positions,
+/// so cost depends on numbers taken and not on `total`
+pub struct Permutation<'a> {
+    rng: &'a mut LotteryRng,
+    total: u32,
+    index: u32,
+    swapped: HashMap<u32, u32>
 }
 
-pub (crate) fn shuffle(mut list: Vec<AccountId>) -> Vec<AccountId> {
-    let random_seed = env::random_seed_array();
-    let mut rng:StdRng = SeedableRng::from_seed(random_seed);
-    list.shuffle(&mut rng);
-    list
+impl Iterator for Permutation<'_> {
+    type Item = u32;
+
+    fn next(&mut self) -> Option<u32> {
+        if self.index == self.total {
+            return None;
+        }
+        let other = self.rng.gen_range(self.index, self.total);
+        let picked = self.swapped.get(&other).copied().unwrap_or(other);
+        let current = self.swapped.get(&self.index).copied().unwrap_or(self.index);
+        self.swapped.insert(other, current);
+        self.index += 1;
+        Some(picked)
+    }
 }
 
 #[cfg(not(target_arch = "wasm32"))]
-#[test]
-fn random_smoke_test() {
-    let vec_to_shuffle = vec![
-        "acc1.near".parse::<AccountId>().unwrap(),
-        "acc2.near".parse::<AccountId>().unwrap(),
-        "acc3.near".parse::<AccountId>().unwrap(),
-        "acc4.near".parse::<AccountId>().unwrap(),
-        "acc5.near".parse::<AccountId>().unwrap()
-    ];
-
-    near_sdk::testing_env!(near_sdk::test_utils::VMContextBuilder::new()
-        .random_seed([8; 32])
-        .build());
-
-    assert_eq!(near_sdk::env::random_seed(), [8; 32]);
-    let shuffled = shuffle(vec_to_shuffle);
-    dbg!("shuffled: {:?}", shuffled);
-}
\ No newline at end of file
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Pearson's chi-squared statistic for uniform distribution
+    fn chi_squared(counts: &[u32]) -> f64 {
+        let expected = counts.iter().sum::<u32>() as f64 / counts.len() as f64;
+        counts
+            .iter()
+            .map(|count| (*count as f64 - expected).powi(2) / expected)
+            .sum()
+    }
+
+    #[test]
+    fn test_lottery_streams_are_separated() {
+        let seed = [8; 32];
+        let mut rng = LotteryRng::new(seed, 0, RandomPurpose::SimpleLotteryWinner);
+        let first = (0..8).map(|_| rng.next_u64()).collect::<Vec<_>>();
+        let mut rng = LotteryRng::new(seed, 0, RandomPurpose::SimpleLotteryWinner);
+        assert_eq!(first, (0..8).map(|_| rng.next_u64()).collect::<Vec<_>>());
+        // repeated draws continue the stream
+        assert_ne!(first[..4], first[4..]);
+
+        let mut other_lottery = LotteryRng::new(seed, 1, RandomPurpose::SimpleLotteryWinner);
+        let mut other_purpose = LotteryRng::new(seed, 0, RandomPurpose::BigLotteryOrder);
+        assert_ne!(first[0], other_lottery.next_u64());
+        assert_ne!(first[0], other_purpose.next_u64());
+    }
+
+    #[test]
+    fn test_range_distribution() {
+        let mut rng = LotteryRng::new([8; 32], 0, RandomPurpose::SimpleLotteryWinner);
+        let mut counts = [0u32; 7];
+        for _ in 0..14_000 {
+            let value = rng.gen_range(3, 10);
+            assert!((3..10).contains(&value));
+            counts[(value - 3) as usize] += 1;
+        }
+        // 6 degrees of freedom, p = 0.001
+        assert!(chi_squared(&counts) < 22.46, "{:?}", counts);
+    }
+
+    #[test]
+    fn test_winners_distribution() {
+        let seed = [8; 32];
+        let mut counts = [0u32; 5];
+        for lottery_id in 0..5_000 {
+            let mut rng = LotteryRng::new(seed, lottery_id, RandomPurpose::SimpleLotteryWinner);
+            counts[rng.gen_range(0, 5) as usize] += 1;
+        }
+        // 4 degrees of freedom, p = 0.001
+        assert!(chi_squared(&counts) < 18.47, "{:?}", counts);
+
+        // every sample position is equally likely to be taken by any entry
+        let mut last_positions = [0u32; 5];
+        for lottery_id in 0..5_000 {
+            let mut rng = LotteryRng::new(seed, lottery_id, RandomPurpose::BigLotteryOrder);
+            let sample = rng.sample(5, 3);
+            last_positions[sample[2] as usize] += 1;
+        }
+        assert!(chi_squared(&last_positions) < 18.47, "{:?}", last_positions);
+    }
+
+    #[test]
+    fn test_sample_is_permutation_prefix() {
+        let mut rng = LotteryRng::new([8; 32], 0, RandomPurpose::BigLotteryOrder);
+        let mut sample = rng.sample(1_000_000, 500);
+        sample.sort_unstable();
+        sample.dedup();
+        assert_eq!(sample.len(), 500);
+        assert!(sample.iter().all(|index| *index < 1_000_000));
+
+        let mut full = rng.sample(10, 10);
+        full.sort_unstable();
+        assert_eq!(full, (0..10).collect::<Vec<_>>());
+    }
+}
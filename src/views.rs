@@ -2,21 +2,103 @@ use near_sdk::json_types::U64;
 
 use crate::*;
 
+/// Max accounts listed in logged lottery result, total logs length of receipt is limited
+const MAX_LOGGED_ACCOUNTS: usize = 100;
+/// Participants of larger lotteries are not listed in lottery result
+pub const MAX_LISTED_PARTICIPANTS: u32 = MAX_LOGGED_ACCOUNTS as u32;
+
 #[derive(Serialize, Debug)]
 #[serde(crate = "near_sdk::serde")]
 pub struct ContractParams {
     pub fees_collected: Vec<(AccountId, U128)>,
+    /// fees withdrawals waiting for transfer confirmation
+    pub fees_locked: Vec<(AccountId, U128)>,
     pub config: ConfigView,
     pub cashback_accounts_num: Vec<(AccountId, u32)>,
-    pub whitelisted_tokens: Vec<AccountId>
+    pub whitelisted_tokens: Vec<AccountId>,
+    /// paused lottery entries
+    pub pause_state: PauseState,
+    /// fees config change waiting for timelock
+    pub pending_fees_config: Option<PendingFeesConfig>
 }
 
 #[derive(Serialize, Debug)]
 #[serde(crate = "near_sdk::serde")]
 pub struct BigLotteryParams {
-    pub cashbacked_num: u32,
-    pub ten_percent_winners_num: u32,
-    pub fifty_percent_winners_num: u32,
+    pub prize_table_id: String,
+    pub prize_table: PrizeTable,
+    /// winners num of every prize tier
+    pub tier_winners_nums: Vec<u32>,
+}
+
+#[derive(Serialize, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct SimpleLotteryParams {
+    /// share of prize for every winner rank in Basis Points
+    pub prize_split: Vec<u32>,
+    /// prize of every winner rank when pool is filled
+    pub prizes: Vec<U128>,
+    /// winners in rank order, empty until winners are picked
+    pub winners: Vec<RankedWinner>
+}
+
+#[derive(Serialize, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct TimedLotteryParams {
+    /// block timestamp after which entries are closed and lottery can be drawn
+    pub draw_at: U64,
+    pub winner: Option<AccountId>
+}
+
+#[derive(Serialize, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct JackpotLotteryParams {
+    /// block timestamp after which entries are closed and lottery can be drawn
+    pub draw_at: U64,
+    /// ticket number of entrant is its entry index, drawn number is in `[0, ticket_range)`
+    pub ticket_range: u32,
+    pub round: U64,
+    /// jackpot rolled over from previous rounds
+    pub jackpot: U128,
+    pub drawn_number: Option<u32>,
+    pub winner: Option<AccountId>
+}
+
+#[derive(Serialize, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct LottoParams {
+    /// block timestamp after which entries are closed and lottery can be drawn
+    pub draw_at: U64,
+    pub pick_count: u32,
+    /// numbers are picked from `1..=max_number`
+    pub max_number: u32,
+    pub paytable: Vec<LottoTier>,
+    /// prize pool rolled over from previous rounds
+    pub rollover: U128,
+    /// empty until lottery is drawn
+    pub drawn_numbers: Vec<u32>
+}
+
+#[derive(Serialize, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct RaffleParams {
+    pub sponsor: AccountId,
+    pub prize: RafflePrize,
+    /// share of collected entry fees paid to sponsor in Basis Points
+    pub sponsor_ratio: u32,
+    pub winner: Option<AccountId>
+}
+
+#[derive(Serialize, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct JackpotView {
+    pub series: JackpotSeries,
+    /// jackpot rolled over from previous rounds
+    pub balance: U128,
+    /// drawn rounds
+    pub rounds_num: U64,
+    /// lottery of the current round
+    pub lottery_id: Option<LotteryId>
 }
 
 #[derive(Serialize, Debug)]
@@ -24,12 +106,46 @@ pub struct BigLotteryParams {
 pub struct ConfigView {
     /// contract owner
     pub owner_id: AccountId,
+    /// proposed owner waiting for `accept_ownership`
+    pub pending_owner_id: Option<AccountId>,
+    /// history of ownership transfers
+    pub ownership_transfers: Vec<OwnershipTransfer>,
     /// fees taken from prize pool to contract
     pub contract_fee_ratio: u32,
+    /// fees taken from `contract_fee` to treasury and investor
+    pub treasury_ratio: u32,
+    pub investor_ratio: u32,
+    /// referrer reward taken from entry fee
+    pub referral_ratio: u32,
+    pub treasury: AccountId,
+    pub investor: AccountId,
+    /// delay in nanoseconds before proposed fees config can be applied
+    pub fees_timelock: U64,
     /// lotteries config
     pub entry_fees_required: Vec<(AccountId, Vec<U128>)>,
     pub num_participants_required: Vec<(LotteryType, Vec<u32>)>,
-    pub accepted_subs: String
+    pub accepted_subs: String,
+    /// rewards are credited to claimable balances for these tokens and lottery types
+    pub claim_mode_tokens: Vec<AccountId>,
+    pub claim_mode_lottery_types: Vec<LotteryType>,
+    /// durations after which not filled lotteries are refunded
+    pub expirations: Vec<(LotteryType, Option<LotteryExpiration>)>,
+    pub draw_modes: Vec<(LotteryType, DrawMode)>,
+    /// tickets one account can hold in new lotteries
+    pub max_tickets: Vec<(LotteryType, u32)>,
+    /// prize split of new simple lotteries
+    pub prize_split: Vec<u32>,
+    pub prize_tables: Vec<(String, PrizeTable)>,
+    /// prize table of new big lotteries
+    pub big_lottery_prize_table: String,
+    /// time from creation of timed lottery to its draw in nanoseconds, `None` if timed lotteries are disabled
+    pub timed_lottery_duration: Option<U64>,
+    /// options of jackpot lotteries, `None` if jackpot lotteries are disabled
+    pub jackpot: Option<JackpotConfig>,
+    /// options of lotto lotteries, `None` if lotto is disabled
+    pub lotto: Option<LottoConfig>,
+    /// options of raffles for NFT prizes, `None` if raffles are disabled
+    pub raffle: Option<RaffleConfig>
 }
 
 #[derive(Serialize, Debug)]
@@ -39,20 +155,41 @@ pub struct LotteryView {
     pub lottery_status: LotteryStatus,
     /// A list of lottery_ids in this lottery
     pub entries: Vec<AccountId>,
+    /// Tickets of all entries
+    pub tickets_num: u32,
+    /// Tickets one account can hold
+    pub max_tickets: u32,
     /// Amount to participate a lottery
     pub entry_fee: U128,
     /// Current amount deposited
     pub current_pool: U128,
     /// Required total amount for lottery to start
     pub required_pool: U128,
-    pub big_lottery_params: Option<BigLotteryParams>
+    pub big_lottery_params: Option<BigLotteryParams>,
+    pub simple_lottery_params: Option<SimpleLotteryParams>,
+    pub timed_lottery_params: Option<TimedLotteryParams>,
+    pub jackpot_lottery_params: Option<JackpotLotteryParams>,
+    pub lotto_params: Option<LottoParams>,
+    pub raffle_params: Option<RaffleParams>,
+    /// Lottery is refunded if it was not filled before expiration
+    pub expires_at: Option<LotteryExpiration>,
+    /// Fee parameters in effect when lottery was created
+    pub fees: LotteryFees,
+    pub draw_mode: DrawMode,
+    /// Block of the final entry for `Delayed` draw mode
+    pub filled_block_height: Option<BlockHeight>
 }
 
 #[derive(Serialize, Debug)]
 #[serde(crate = "near_sdk::serde")]
+#[allow(clippy::enum_variant_names)]
 pub enum LotteryResult {
     SimpleLotteryResult(SimpleLotteryResult),
-    BigLotteryResult(BigLotteryResult)
+    BigLotteryResult(BigLotteryResult),
+    TimedLotteryResult(TimedLotteryResult),
+    JackpotLotteryResult(JackpotLotteryResult),
+    LottoResult(LottoResult),
+    RaffleResult(RaffleResult)
 }
 
 #[derive(Serialize, Debug)]
@@ -61,7 +198,11 @@ pub struct SimpleLotteryResult {
     pub lottery_id: U64,
     pub lottery_token_id: AccountId,
     pub participants: Vec<AccountId>,
+    /// first rank winner
     pub winner: AccountId,
+    /// winners in rank order
+    pub winners: Vec<RankedWinner>,
+    /// total amount of all winners
     pub winning_amount: U128,
     pub contract_fee: U128
 }
@@ -71,20 +212,179 @@ pub struct SimpleLotteryResult {
 pub struct BigLotteryResult {
     pub lottery_id: U64,
     pub lottery_token_id: AccountId,
-    pub participants: Vec<AccountId>,
-    pub winners_up_to_50: Vec<AccountId>,
-    pub winners_up_to_10: Vec<AccountId>,
+    /// entries are not listed, they are settled in batches with the rest tier
+    pub participants_num: u32,
+    pub prize_table_id: String,
+    pub tiers: Vec<TierWinners>,
     pub total_winning_amount: U128,
     pub contract_fee: U128
 }
 
+#[derive(Serialize, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct TimedLotteryResult {
+    pub lottery_id: U64,
+    pub lottery_token_id: AccountId,
+    pub participants_num: u32,
+    /// empty if there are more than `MAX_LISTED_PARTICIPANTS` participants
+    pub participants: Vec<AccountId>,
+    pub draw_at: U64,
+    pub winner: AccountId,
+    pub winning_amount: U128,
+    pub contract_fee: U128
+}
+
+#[derive(Serialize, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct JackpotLotteryResult {
+    pub lottery_id: U64,
+    pub lottery_token_id: AccountId,
+    pub participants_num: u32,
+    /// empty if there are more than `MAX_LISTED_PARTICIPANTS` participants
+    pub participants: Vec<AccountId>,
+    pub round: U64,
+    pub drawn_number: u32,
+    /// holder of drawn ticket number
+    pub winner: Option<AccountId>,
+    pub winning_amount: U128,
+    /// jackpot left for the next round
+    pub rolled_over: U128,
+    pub contract_fee: U128
+}
+
+#[derive(Serialize, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct LottoResult {
+    pub lottery_id: U64,
+    pub lottery_token_id: AccountId,
+    pub participants_num: u32,
+    /// empty if there are more than `MAX_LISTED_PARTICIPANTS` participants
+    pub participants: Vec<AccountId>,
+    pub drawn_numbers: Vec<u32>,
+    /// pools of every paytable tier, split between tier winners when entries are settled
+    pub tiers: Vec<LottoTierPool>,
+    /// round pool minus contract fees with pool rolled over from previous rounds
+    pub prize_pool: U128,
+    pub contract_fee: U128
+}
+
+#[derive(Serialize, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct RaffleResult {
+    pub lottery_id: U64,
+    pub lottery_token_id: AccountId,
+    pub participants: Vec<AccountId>,
+    /// NFT transfered to the winner
+    pub prize: RafflePrize,
+    pub winner: AccountId,
+    pub sponsor: AccountId,
+    /// share of collected entry fees paid to sponsor
+    pub sponsor_amount: U128,
+    pub contract_fee: U128
+}
+
+#[derive(Serialize, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct RankedWinner {
+    /// 1 is the first prize
+    pub rank: u32,
+    pub account_id: AccountId,
+    pub amount: U128
+}
+
+#[derive(Serialize, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct TierWinners {
+    pub name: String,
+    /// payout of every winner of tier
+    pub amount: U128,
+    pub winners_num: u32,
+    /// winners of tier taking the rest of entries are not listed
+    pub winners: Vec<AccountId>
+}
+
+#[derive(Serialize, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct LottoTierPool {
+    pub matches: u32,
+    /// share of prize pool
+    pub pool: U128
+}
+
+impl LotteryResult {
+    /// Logs lottery result.
+    /// Accounts of large lotteries are not listed, only their number
+    pub fn log(&self) {
+        match self {
+            LotteryResult::SimpleLotteryResult(result) if result.participants.len() > MAX_LOGGED_ACCOUNTS => {
+                log!(
+                    "Lottery {} finished. Participants: {}, winner: @{}, winning amount: {}, contract fee: {}",
+                    result.lottery_id.0, result.participants.len(), result.winner,
+                    result.winning_amount.0, result.contract_fee.0
+                );
+            },
+            LotteryResult::TimedLotteryResult(result) if result.participants_num > MAX_LISTED_PARTICIPANTS => {
+                log!(
+                    "Lottery {} finished. Participants: {}, winner: @{}, winning amount: {}, contract fee: {}",
+                    result.lottery_id.0, result.participants_num, result.winner,
+                    result.winning_amount.0, result.contract_fee.0
+                );
+            },
+            LotteryResult::RaffleResult(result) if result.participants.len() > MAX_LOGGED_ACCOUNTS => {
+                log!(
+                    "Lottery {} finished. Participants: {}, winner: @{}, prize: {} of {}, sponsor amount: {}, contract fee: {}",
+                    result.lottery_id.0, result.participants.len(), result.winner,
+                    result.prize.token_id, result.prize.nft_contract_id,
+                    result.sponsor_amount.0, result.contract_fee.0
+                );
+            },
+            LotteryResult::JackpotLotteryResult(result) if result.participants_num > MAX_LISTED_PARTICIPANTS => {
+                log!(
+                    "Lottery {} finished. Participants: {}, drawn number: {}, winning amount: {}, rolled over: {}, contract fee: {}",
+                    result.lottery_id.0, result.participants_num, result.drawn_number,
+                    result.winning_amount.0, result.rolled_over.0, result.contract_fee.0
+                );
+            },
+            LotteryResult::LottoResult(result) if result.participants_num > MAX_LISTED_PARTICIPANTS => {
+                log!(
+                    "Lottery {} finished. Participants: {}, drawn numbers: {:?}, prize pool: {}, contract fee: {}",
+                    result.lottery_id.0, result.participants_num, result.drawn_numbers,
+                    result.prize_pool.0, result.contract_fee.0
+                );
+            },
+            LotteryResult::BigLotteryResult(result)
+                if result.tiers.iter().map(|tier| tier.winners.len()).sum::<usize>() > MAX_LOGGED_ACCOUNTS =>
+            {
+                let tiers = result.tiers
+                    .iter()
+                    .map(|tier| format!("{}: {}", tier.name, tier.winners_num))
+                    .collect::<Vec<_>>();
+                log!(
+                    "Lottery {} finished. Participants: {}, winners {}, total winning amount: {}, contract fee: {}",
+                    result.lottery_id.0, result.participants_num, tiers.join(", "),
+                    result.total_winning_amount.0, result.contract_fee.0
+                );
+            },
+            _ => log!("{:#?}", self),
+        }
+    }
+}
+
 impl Contract {
     pub fn get_contract_view(&self) -> ContractParams {
         let config_internal = self.internal_config();
         let lottery_config_internal = self.internal_lottery_config();
         let config = ConfigView {
             owner_id: config_internal.owner_id,
+            pending_owner_id: config_internal.pending_owner_id,
+            ownership_transfers: self.ownership_transfers.to_vec(),
             contract_fee_ratio: config_internal.contract_fee_ratio,
+            treasury_ratio: config_internal.treasury_ratio,
+            investor_ratio: config_internal.investor_ratio,
+            referral_ratio: config_internal.referral_ratio,
+            treasury: config_internal.treasury,
+            investor: config_internal.investor,
+            fees_timelock: config_internal.fees_timelock,
             entry_fees_required: lottery_config_internal
                 .entry_fees
                 .into_iter()
@@ -93,20 +393,46 @@ impl Contract {
                 (LotteryType::SimpleLottery, lottery_config_internal.num_participants),
                 (LotteryType::BigLottery, lottery_config_internal.big_lottery_num_participants),
             ],
-            accepted_subs: config_internal.accepted_subs
+            accepted_subs: config_internal.accepted_subs,
+            claim_mode_tokens: lottery_config_internal.claim_mode_tokens,
+            claim_mode_lottery_types: lottery_config_internal.claim_mode_lottery_types,
+            expirations: vec![
+                (LotteryType::SimpleLottery, lottery_config_internal.expiration),
+                (LotteryType::BigLottery, lottery_config_internal.big_lottery_expiration),
+            ],
+            draw_modes: vec![
+                (LotteryType::SimpleLottery, lottery_config_internal.draw_mode),
+                (LotteryType::BigLottery, lottery_config_internal.big_lottery_draw_mode),
+            ],
+            max_tickets: vec![
+                (LotteryType::SimpleLottery, lottery_config_internal.max_tickets),
+                (LotteryType::BigLottery, lottery_config_internal.big_lottery_max_tickets),
+            ],
+            prize_split: lottery_config_internal.prize_split,
+            prize_tables: lottery_config_internal.prize_tables.into_iter().collect(),
+            big_lottery_prize_table: lottery_config_internal.big_lottery_prize_table,
+            timed_lottery_duration: lottery_config_internal.timed_lottery_duration,
+            jackpot: lottery_config_internal.jackpot,
+            lotto: lottery_config_internal.lotto,
+            raffle: lottery_config_internal.raffle
         };
 
         ContractParams { 
             fees_collected: self.fees.to_vec().iter().map(|(acc, fee)| (acc.clone(), U128(*fee))).collect::<Vec<_>>(), 
+            fees_locked: self.fees_locked.iter().map(|(acc, fee)| (acc, U128(fee))).collect(),
             config,
             cashback_accounts_num: self
-                .cashback_accounts
-                .iter()
-                .map(|(token_id, stored_cashback)| {
-                    (token_id, stored_cashback.accounts.len() as u32)
-                }) 
+                .cashbacks
+                .values()
+                .fold(HashMap::new(), |mut pending: HashMap<AccountId, u32>, stored_cashback| {
+                    *pending.entry(stored_cashback.lottery_token_id.clone()).or_default() += stored_cashback.pending_accounts().len() as u32;
+                    pending
+                })
+                .into_iter()
                 .collect(),
-            whitelisted_tokens: self.whitelisted_tokens.to_vec()
+            whitelisted_tokens: self.whitelisted_tokens.to_vec(),
+            pause_state: self.internal_pause_state(),
+            pending_fees_config: self.pending_fees_config.get()
         }
     }
     pub fn get_lottery_view(&self, lottery: Lottery) -> LotteryView {
@@ -115,26 +441,169 @@ impl Contract {
                 LotteryView { 
                     lottery_token_id: lottery.lottery_token_id,
                     lottery_status: lottery.lottery_status, 
-                    entries: lottery.entries, 
+                    entries: lottery.entries.accounts(), 
+                    tickets_num: lottery.entries.tickets_num(),
+                    max_tickets: lottery.max_tickets,
                     entry_fee: lottery.entry_fee.into(), 
                     current_pool: lottery.current_pool.into(), 
                     required_pool: lottery.required_pool.into(), 
                     big_lottery_params: Some(BigLotteryParams {
-                        cashbacked_num: lottery.cashbacked_num,
-                        ten_percent_winners_num: lottery.ten_percent_winners_num,
-                        fifty_percent_winners_num: lottery.fifty_percent_winners_num,
-                    })
+                        prize_table_id: lottery.prize_table_id,
+                        prize_table: lottery.prize_table,
+                        tier_winners_nums: lottery.tier_winners_nums,
+                    }),
+                    simple_lottery_params: None,
+                    timed_lottery_params: None,
+                    jackpot_lottery_params: None,
+                    lotto_params: None,
+                    raffle_params: None,
+                    expires_at: lottery.expires_at,
+                    fees: lottery.fees,
+                    draw_mode: lottery.draw_mode,
+                    filled_block_height: lottery.filled_block_height
                 }
             },
             Lottery::SimpleLottery(lottery) => {
+                let reward = lottery.required_pool - ratio(lottery.required_pool, lottery.fees.contract_fee_ratio);
+                let simple_lottery_params = SimpleLotteryParams {
+                    winners: lottery.ranked_winners(reward),
+                    prizes: lottery.prizes(reward).into_iter().map(U128).collect(),
+                    prize_split: lottery.prize_split,
+                };
+                LotteryView { 
+                    lottery_token_id: lottery.lottery_token_id,
+                    lottery_status: lottery.lottery_status, 
+                    tickets_num: lottery.entries.iter().map(|entry| entry.tickets).sum(),
+                    entries: lottery.entries.into_iter().map(|entry| entry.account_id).collect(), 
+                    max_tickets: lottery.max_tickets,
+                    entry_fee: lottery.entry_fee.into(), 
+                    current_pool: lottery.current_pool.into(), 
+                    required_pool: lottery.required_pool.into(), 
+                    big_lottery_params: None,
+                    simple_lottery_params: Some(simple_lottery_params),
+                    timed_lottery_params: None,
+                    jackpot_lottery_params: None,
+                    lotto_params: None,
+                    raffle_params: None,
+                    expires_at: lottery.expires_at,
+                    fees: lottery.fees,
+                    draw_mode: lottery.draw_mode,
+                    filled_block_height: lottery.filled_block_height
+                }
+            },
+            Lottery::TimedLottery(lottery) => {
+                LotteryView { 
+                    lottery_token_id: lottery.lottery_token_id,
+                    lottery_status: lottery.lottery_status, 
+                    entries: lottery.entries.accounts(), 
+                    tickets_num: lottery.entries.tickets_num(),
+                    max_tickets: 1,
+                    entry_fee: lottery.entry_fee.into(), 
+                    current_pool: lottery.current_pool.into(), 
+                    // any pool is drawn
+                    required_pool: U128(0), 
+                    big_lottery_params: None,
+                    simple_lottery_params: None,
+                    timed_lottery_params: Some(TimedLotteryParams {
+                        draw_at: U64(lottery.draw_at),
+                        winner: lottery.winner,
+                    }),
+                    jackpot_lottery_params: None,
+                    lotto_params: None,
+                    raffle_params: None,
+                    expires_at: None,
+                    fees: lottery.fees,
+                    draw_mode: DrawMode::Delayed,
+                    filled_block_height: None
+                }
+            },
+            Lottery::JackpotLottery(lottery) => {
+                let jackpot = self.internal_jackpot(&lottery.series());
+                LotteryView { 
+                    lottery_token_id: lottery.lottery_token_id,
+                    lottery_status: lottery.lottery_status, 
+                    entries: lottery.entries.accounts(), 
+                    tickets_num: lottery.entries.tickets_num(),
+                    max_tickets: 1,
+                    entry_fee: lottery.entry_fee.into(), 
+                    current_pool: lottery.current_pool.into(), 
+                    // any pool is drawn
+                    required_pool: U128(0), 
+                    big_lottery_params: None,
+                    simple_lottery_params: None,
+                    timed_lottery_params: None,
+                    jackpot_lottery_params: Some(JackpotLotteryParams {
+                        draw_at: U64(lottery.draw_at),
+                        ticket_range: lottery.ticket_range,
+                        round: U64(jackpot.rounds_num + 1),
+                        jackpot: U128(jackpot.balance),
+                        drawn_number: lottery.drawn_number,
+                        winner: lottery.winner,
+                    }),
+                    lotto_params: None,
+                    raffle_params: None,
+                    expires_at: None,
+                    fees: lottery.fees,
+                    draw_mode: DrawMode::Delayed,
+                    filled_block_height: None
+                }
+            },
+            Lottery::Lotto(lottery) => {
+                let rollover = self.internal_lotto_rollover(&lottery.series());
+                LotteryView { 
+                    lottery_token_id: lottery.lottery_token_id,
+                    lottery_status: lottery.lottery_status, 
+                    entries: lottery.entries.accounts(), 
+                    tickets_num: lottery.entries.tickets_num(),
+                    max_tickets: 1,
+                    entry_fee: lottery.entry_fee.into(), 
+                    current_pool: lottery.current_pool.into(), 
+                    // any pool is drawn
+                    required_pool: U128(0), 
+                    big_lottery_params: None,
+                    simple_lottery_params: None,
+                    timed_lottery_params: None,
+                    jackpot_lottery_params: None,
+                    lotto_params: Some(LottoParams {
+                        draw_at: U64(lottery.draw_at),
+                        pick_count: lottery.pick_count,
+                        max_number: lottery.max_number,
+                        paytable: lottery.paytable,
+                        rollover: U128(rollover),
+                        drawn_numbers: lottery.drawn_numbers,
+                    }),
+                    raffle_params: None,
+                    expires_at: None,
+                    fees: lottery.fees,
+                    draw_mode: DrawMode::Delayed,
+                    filled_block_height: None
+                }
+            },
+            Lottery::Raffle(lottery) => {
                 LotteryView { 
                     lottery_token_id: lottery.lottery_token_id,
                     lottery_status: lottery.lottery_status, 
-                    entries: lottery.entries, 
+                    entries: lottery.entries.accounts(), 
+                    tickets_num: lottery.entries.tickets_num(),
+                    max_tickets: 1,
                     entry_fee: lottery.entry_fee.into(), 
                     current_pool: lottery.current_pool.into(), 
                     required_pool: lottery.required_pool.into(), 
-                    big_lottery_params: None
+                    big_lottery_params: None,
+                    simple_lottery_params: None,
+                    timed_lottery_params: None,
+                    jackpot_lottery_params: None,
+                    lotto_params: None,
+                    raffle_params: Some(RaffleParams {
+                        sponsor: lottery.sponsor,
+                        prize: lottery.prize,
+                        sponsor_ratio: lottery.sponsor_ratio,
+                        winner: lottery.winner,
+                    }),
+                    expires_at: Some(LotteryExpiration::Timestamp(U64(lottery.expires_at))),
+                    fees: lottery.fees,
+                    draw_mode: DrawMode::Delayed,
+                    filled_block_height: lottery.filled_block_height
                 }
             }
         }
@@ -151,6 +620,59 @@ impl Contract {
         self.get_contract_view()
     }
 
+    /// Returns jackpots of all jackpot series with their current rounds
+    pub fn get_jackpots(&self) -> Vec<JackpotView> {
+        let mut jackpots = self.jackpots
+            .iter()
+            .map(|(series, jackpot)| JackpotView {
+                series,
+                balance: U128(jackpot.balance),
+                rounds_num: U64(jackpot.rounds_num),
+                lottery_id: None
+            })
+            .collect::<Vec<_>>();
+        for lottery in self.lotteries.values().map(Lottery::from) {
+            if let Lottery::JackpotLottery(lottery) = lottery {
+                if lottery.lottery_status == LotteryStatus::Cancelled {
+                    continue;
+                }
+                let series = lottery.series();
+                match jackpots.iter_mut().find(|jackpot| jackpot.series == series) {
+                    Some(jackpot) => jackpot.lottery_id = Some(lottery.id),
+                    // first round of series
+                    None => jackpots.push(JackpotView {
+                        series,
+                        balance: U128(0),
+                        rounds_num: U64(0),
+                        lottery_id: Some(lottery.id)
+                    }),
+                }
+            }
+        }
+        jackpots
+    }
+
+    /// Returns drawn rounds of jackpot series from a given round (starting from 1) up to a given limit.
+    /// - If `token_id` was not set - returns rounds of NEAR series
+    pub fn get_jackpot_history(
+        &self,
+        token_id: Option<AccountId>,
+        entry_fee: U128,
+        from_round: Option<U64>,
+        limit: Option<U64>
+    ) -> Vec<JackpotRound> {
+        let series = JackpotSeries {
+            lottery_token_id: token_id.unwrap_or_else(near),
+            entry_fee
+        };
+        let rounds_num = self.internal_jackpot(&series).rounds_num;
+        let from_round = from_round.map(|round| round.0).unwrap_or(1).max(1);
+        let limit = limit.map(|limit| limit.0).unwrap_or(rounds_num);
+        (from_round..=std::cmp::min(rounds_num, from_round.saturating_add(limit).saturating_sub(1)))
+            .filter_map(|round| self.jackpot_rounds.get(&(series.clone(), round)))
+            .collect()
+    }
+
     /// Returns detailed information about an lottery for a given lottery_id.
     pub fn get_lottery(&self, lottery_id: LotteryId) -> Option<LotteryView> {
         self.internal_get_lottery(lottery_id)
@@ -163,7 +685,45 @@ impl Contract {
         let from_index = from_index.unwrap_or(0);
         let limit = limit.unwrap_or(values.len());
         (from_index..std::cmp::min(values.len(), from_index + limit))
-            .map(|index| values.get(index).unwrap())
+            .map(|index| values.get(index).unwrap().into())
             .collect()
     }
+
+    /// Returns entries of lottery with their tickets in entry order from a given index up to a given limit.
+    pub fn get_lottery_entries(&self, lottery_id: LotteryId, from_index: Option<u32>, limit: Option<u32>) -> Vec<Entry> {
+        let lottery = self.internal_get_lottery(lottery_id).expect("Lottery was not found");
+        let from_index = from_index.unwrap_or(0);
+        let limit = limit.unwrap_or(lottery.entries_num());
+        match lottery {
+            Lottery::SimpleLottery(lottery) => lottery.entries
+                .into_iter()
+                .skip(from_index as _)
+                .take(limit as _)
+                .collect(),
+            Lottery::Lottery(lottery) => lottery.entries.paged(from_index, limit),
+            Lottery::TimedLottery(lottery) => lottery.entries.paged(from_index, limit),
+            Lottery::JackpotLottery(lottery) => lottery.entries.paged(from_index, limit),
+            Lottery::Lotto(lottery) => lottery.entries.paged(from_index, limit),
+            Lottery::Raffle(lottery) => lottery.entries.paged(from_index, limit),
+        }
+    }
+
+    /// Returns picked numbers of lotto entries in entry order from a given index up to a given limit.
+    pub fn get_lotto_tickets(&self, lottery_id: LotteryId, from_index: Option<u32>, limit: Option<u32>) -> Vec<LottoTicket> {
+        match self.internal_get_lottery(lottery_id).expect("Lottery was not found") {
+            Lottery::Lotto(lottery) => {
+                lottery.tickets(from_index.unwrap_or(0), limit.unwrap_or_else(|| lottery.entries.len()))
+            },
+            _ => panic!("Lottery is not lotto"),
+        }
+    }
+
+    /// Returns lotto prize pool rolled over from previous rounds of series.
+    /// - If `token_id` was not set - returns rollover of NEAR series
+    pub fn get_lotto_rollover(&self, token_id: Option<AccountId>, entry_fee: U128) -> U128 {
+        U128(self.internal_lotto_rollover(&LottoSeries {
+            lottery_token_id: token_id.unwrap_or_else(near),
+            entry_fee
+        }))
+    }
 }
\ No newline at end of file